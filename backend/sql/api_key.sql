@@ -0,0 +1,21 @@
+-- API key untuk akses service-to-service (lihat ApiKeyService)
+-- Key asli `ubs_<prefix>_<secret>` hanya ditampilkan sekali saat dibuat, yang disimpan SHA-256 hex-nya.
+-- Scopes: permission dipisah `;` (mis. view:CIFLookup;export:CIFLookup), dibatasi lagi oleh permission pemiliknya.
+IF OBJECT_ID(N'dbo.ApiKey') IS NULL
+BEGIN
+    CREATE TABLE [dbo].[ApiKey] (
+        [ApiKeyNID] int IDENTITY(1,1) NOT NULL PRIMARY KEY,
+        [AuthUserNID] int NOT NULL REFERENCES [dbo].[AuthUser] ([AuthUserNID]) ON DELETE CASCADE,
+        [Name] nvarchar(100) NOT NULL,
+        [Prefix] char(8) NOT NULL,
+        [KeyHash] char(64) NOT NULL UNIQUE,
+        [Scopes] nvarchar(2000) NOT NULL,
+        [CreatedAt] datetime NOT NULL DEFAULT GETDATE(),
+        [ExpiresAt] datetime NULL,
+        [LastUsedAt] datetime NULL,
+        [LastUsedIp] nvarchar(64) NULL,
+        [RevokedAt] datetime NULL
+    );
+
+    CREATE INDEX [IX_ApiKey_AuthUserNID] ON [dbo].[ApiKey] ([AuthUserNID]);
+END
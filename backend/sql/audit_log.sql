@@ -0,0 +1,21 @@
+-- Audit trail untuk semua write dan export data (lihat AuditService)
+IF OBJECT_ID(N'dbo.AuditLog') IS NULL
+BEGIN
+    CREATE TABLE [dbo].[AuditLog] (
+        [AuditLogNID] bigint IDENTITY(1,1) NOT NULL PRIMARY KEY,
+        [CreatedAt] datetime NOT NULL DEFAULT GETDATE(),
+        [UserNID] int NULL,
+        [Email] nvarchar(256) NULL,
+        [IPAddress] nvarchar(64) NULL,
+        [Action] nvarchar(50) NOT NULL,
+        [Entity] nvarchar(128) NOT NULL,
+        [EntityKey] nvarchar(256) NULL,
+        [BeforeData] nvarchar(max) NULL,
+        [AfterData] nvarchar(max) NULL,
+        [RequestID] nvarchar(64) NULL
+    );
+
+    CREATE INDEX [IX_AuditLog_CreatedAt] ON [dbo].[AuditLog] ([CreatedAt] DESC);
+    CREATE INDEX [IX_AuditLog_Entity] ON [dbo].[AuditLog] ([Entity], [CreatedAt] DESC);
+    CREATE INDEX [IX_AuditLog_UserNID] ON [dbo].[AuditLog] ([UserNID], [CreatedAt] DESC);
+END
@@ -0,0 +1,31 @@
+-- Sesi login & refresh token (lihat SessionService)
+-- Satu AuthSession per login / perangkat; setiap refresh membuat AuthRefreshToken baru dan
+-- menandai token lama `UsedAt`. Token lama yang dipakai lagi mencabut seluruh sesi.
+IF OBJECT_ID(N'dbo.AuthSession') IS NULL
+BEGIN
+    CREATE TABLE [dbo].[AuthSession] (
+        [SessionID] nvarchar(36) NOT NULL PRIMARY KEY,
+        [AuthUserNID] int NOT NULL,
+        [IpAddress] nvarchar(64) NULL,
+        [AppName] nvarchar(100) NULL,
+        [UserAgent] nvarchar(256) NULL,
+        [CreatedAt] datetime NOT NULL DEFAULT GETDATE(),
+        [LastRefreshAt] datetime NULL,
+        [ExpiresAt] datetime NOT NULL,
+        [RevokedAt] datetime NULL,
+        [RevokedReason] nvarchar(50) NULL
+    );
+
+    CREATE INDEX [IX_AuthSession_AuthUserNID] ON [dbo].[AuthSession] ([AuthUserNID]);
+END
+
+IF OBJECT_ID(N'dbo.AuthRefreshToken') IS NULL
+BEGIN
+    CREATE TABLE [dbo].[AuthRefreshToken] (
+        -- SHA-256 hex dari refresh token, token asli tidak pernah disimpan
+        [TokenHash] char(64) NOT NULL PRIMARY KEY,
+        [SessionID] nvarchar(36) NOT NULL REFERENCES [dbo].[AuthSession] ([SessionID]) ON DELETE CASCADE,
+        [CreatedAt] datetime NOT NULL DEFAULT GETDATE(),
+        [UsedAt] datetime NULL
+    );
+END
@@ -0,0 +1,17 @@
+-- Token sekali pakai untuk reset password dan verifikasi email (lihat AccountService)
+-- Purpose: password_reset | verify_email. Email disimpan untuk rate limit per alamat email.
+IF OBJECT_ID(N'dbo.AuthToken') IS NULL
+BEGIN
+    CREATE TABLE [dbo].[AuthToken] (
+        -- SHA-256 hex dari token, token asli hanya dikirim lewat email
+        [TokenHash] char(64) NOT NULL PRIMARY KEY,
+        [AuthUserNID] int NOT NULL REFERENCES [dbo].[AuthUser] ([AuthUserNID]) ON DELETE CASCADE,
+        [Email] nvarchar(256) NOT NULL,
+        [Purpose] nvarchar(30) NOT NULL,
+        [CreatedAt] datetime NOT NULL DEFAULT GETDATE(),
+        [ExpiresAt] datetime NOT NULL,
+        [UsedAt] datetime NULL
+    );
+
+    CREATE INDEX [IX_AuthToken_Email_Purpose] ON [dbo].[AuthToken] ([Email], [Purpose], [CreatedAt]);
+END
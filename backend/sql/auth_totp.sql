@@ -0,0 +1,34 @@
+-- Two-factor authentication TOTP (lihat TotpService)
+-- SecretEncrypted: v1$<nonce base64>$<ciphertext base64>$<tag base64>, AES-256-CTR + HMAC-SHA256
+-- dengan kunci dari `TOTP_ENCRYPTION_KEY`. Enabled = 0 selama enrolment belum dikonfirmasi.
+IF OBJECT_ID(N'dbo.AuthUserTotp') IS NULL
+BEGIN
+    CREATE TABLE [dbo].[AuthUserTotp] (
+        [AuthUserNID] int NOT NULL PRIMARY KEY REFERENCES [dbo].[AuthUser] ([AuthUserNID]) ON DELETE CASCADE,
+        [SecretEncrypted] nvarchar(256) NOT NULL,
+        [Enabled] bit NOT NULL DEFAULT 0,
+        -- Time step terakhir yang diterima, kode yang sama tidak bisa dipakai dua kali
+        [LastUsedStep] bigint NULL,
+        [CreatedAt] datetime NOT NULL DEFAULT GETDATE(),
+        [EnabledAt] datetime NULL
+    );
+END
+
+IF OBJECT_ID(N'dbo.AuthUserBackupCode') IS NULL
+BEGIN
+    CREATE TABLE [dbo].[AuthUserBackupCode] (
+        [AuthUserNID] int NOT NULL REFERENCES [dbo].[AuthUser] ([AuthUserNID]) ON DELETE CASCADE,
+        -- SHA-256 hex dari backup code, kode asli hanya ditampilkan sekali saat dibuat
+        [CodeHash] char(64) NOT NULL,
+        [CreatedAt] datetime NOT NULL DEFAULT GETDATE(),
+        [UsedAt] datetime NULL,
+        PRIMARY KEY ([AuthUserNID], [CodeHash])
+    );
+END
+
+-- Nonce token login yang menunggu 2FA (lihat `TotpService::begin_login`), dihapus setelah kode diterima
+-- supaya token sementara hanya bisa dipakai sekali
+IF COL_LENGTH(N'dbo.AuthUserTotp', N'PendingNonce') IS NULL
+BEGIN
+    ALTER TABLE [dbo].[AuthUserTotp] ADD [PendingNonce] char(32) NULL;
+END
@@ -0,0 +1,36 @@
+-- User login (lihat CredentialService)
+-- PasswordHash: pbkdf2-sha256$<iterasi>$<salt base64>$<hash base64>
+-- Branches: kode cabang dipisah `;`, dipakai row filter dengan claim `branches`
+IF OBJECT_ID(N'dbo.AuthUser') IS NULL
+BEGIN
+    CREATE TABLE [dbo].[AuthUser] (
+        [AuthUserNID] int IDENTITY(1,1) NOT NULL PRIMARY KEY,
+        [Email] nvarchar(256) NOT NULL UNIQUE,
+        [MobilePhone] nvarchar(20) NULL,
+        [PasswordHash] nvarchar(256) NOT NULL,
+        [DisabledLogin] bit NOT NULL DEFAULT 0,
+        [FailedLoginCount] int NOT NULL DEFAULT 0,
+        [LockedUntil] datetime NULL,
+        [LastLogin] datetime NULL,
+        [Picture] nvarchar(512) NULL,
+        [CompanyID] nvarchar(50) NULL,
+        [CompName] nvarchar(256) NULL,
+        [Branches] nvarchar(1000) NULL,
+        [RegisterDate] datetime NOT NULL DEFAULT GETDATE(),
+        [LastUpdate] datetime NOT NULL DEFAULT GETDATE()
+    );
+END
+
+-- Verifikasi email (lihat AccountService)
+IF COL_LENGTH(N'dbo.AuthUser', N'EmailVerified') IS NULL
+BEGIN
+    ALTER TABLE [dbo].[AuthUser] ADD
+        [EmailVerified] bit NOT NULL DEFAULT 0,
+        [EmailVerifiedAt] datetime NULL;
+END
+
+-- Nama lengkap untuk profil (lihat ProfileService)
+IF COL_LENGTH(N'dbo.AuthUser', N'FullName') IS NULL
+BEGIN
+    ALTER TABLE [dbo].[AuthUser] ADD [FullName] nvarchar(256) NULL;
+END
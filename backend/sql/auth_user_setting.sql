@@ -0,0 +1,12 @@
+-- Preferensi per user (lihat SettingService dan contexts/preferences.rs)
+-- SettingValue: nilai dalam format JSON (mis. "dark", 100) supaya tipe tetap terjaga. Key tanpa baris memakai default.
+IF OBJECT_ID(N'dbo.AuthUserSetting') IS NULL
+BEGIN
+    CREATE TABLE [dbo].[AuthUserSetting] (
+        [AuthUserNID] int NOT NULL REFERENCES [dbo].[AuthUser] ([AuthUserNID]) ON DELETE CASCADE,
+        [SettingKey] nvarchar(50) NOT NULL,
+        [SettingValue] nvarchar(400) NOT NULL,
+        [LastUpdate] datetime NOT NULL DEFAULT GETDATE(),
+        CONSTRAINT [PK_AuthUserSetting] PRIMARY KEY ([AuthUserNID], [SettingKey])
+    );
+END
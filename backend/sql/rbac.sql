@@ -0,0 +1,28 @@
+-- Role-based access control (lihat PermissionService)
+-- Contoh permission: view:CIFLookup, view:*, export:CIFLookup, edit:CIFLookup, menu:home, chart:edit, scope:all (tanpa row filter), unmask:CIFLookup (tanpa masking), admin
+IF OBJECT_ID(N'dbo.Role') IS NULL
+BEGIN
+    CREATE TABLE [dbo].[Role] (
+        [RoleNID] int IDENTITY(1,1) NOT NULL PRIMARY KEY,
+        [RoleName] nvarchar(100) NOT NULL UNIQUE,
+        [Description] nvarchar(256) NULL
+    );
+END
+
+IF OBJECT_ID(N'dbo.RolePermission') IS NULL
+BEGIN
+    CREATE TABLE [dbo].[RolePermission] (
+        [RoleNID] int NOT NULL REFERENCES [dbo].[Role] ([RoleNID]) ON DELETE CASCADE,
+        [Permission] nvarchar(200) NOT NULL,
+        PRIMARY KEY ([RoleNID], [Permission])
+    );
+END
+
+IF OBJECT_ID(N'dbo.UserRole') IS NULL
+BEGIN
+    CREATE TABLE [dbo].[UserRole] (
+        [AuthUserNID] int NOT NULL,
+        [RoleNID] int NOT NULL REFERENCES [dbo].[Role] ([RoleNID]) ON DELETE CASCADE,
+        PRIMARY KEY ([AuthUserNID], [RoleNID])
+    );
+END
@@ -1,7 +1,8 @@
 use actix_web::{get, web, HttpResponse, Responder};
 use utoipa::{OpenApi, ToSchema};
 
-use crate::contexts::model::{ActionResult, Claims, HeaderParams, LoginRequest, TableDataParams};
+use crate::contexts::model::{ActionResult, ApiKeyCreated, ApiKeyInfo, AuditLogParams, CacheInvalidateParams, Claims, CreateApiKeyRequest, DistinctParams, ExportTableParams, ForgotPasswordRequest, HeaderParams, TableEventParams, LoginRequest, PermissionInvalidateParams, ProfileInfo, ReadinessStatus, ResetPasswordRequest, RevokeApiKeyRequest, RevokeSessionRequest, RowRequest, SessionInfo, SettingSchema, TableDataParams, TotpBackupCodes, TotpEnrollment, TwoFactorCodeRequest, TwoFactorPending, UpdateProfileRequest, UpdateSettingsRequest, UserAccessInfo, VerifyEmailRequest};
+use crate::contexts::preferences::UserPreferences;
 
 #[derive(serde::Serialize, ToSchema)]
 struct HealthCheckResponse {
@@ -10,18 +11,34 @@ struct HealthCheckResponse {
 
 // Login Docs
 #[utoipa::path(post, path = "/api/v1/auth/login", request_body = LoginRequest,
+    summary = "Login dengan email & password",
+    description = "Access token dikirim sebagai cookie HttpOnly `token` (berlaku `JWT_EXPIRES_SECS`, default 15 menit), refresh token sebagai cookie `refresh_token` dengan path `/api/v1/auth`. Akun dikunci sementara setelah `LOGIN_MAX_ATTEMPTS` kali gagal.\n\nJika 2FA aktif, response berisi `two_factor_required` dan cookie sementara `two_factor_token`; login diselesaikan di `/api/v1/auth/2fa/verify`.",
     responses(
-        (status = 200, description = "Check Session", body = ActionResult<Claims, String>, example = json!({"result": true, "message": "Login Success", "data": {
-            "user_id": "1",
-            "username": "admin",
-            "email": "LXh4N@example.com",
+        (status = 200, description = "Login Success", body = ActionResult<Claims, String>, example = json!({"result": true, "message": "Login Success", "data": {
+            "result": true,
+            "auth_usernid": 1,
+            "email": "admin@example.com",
+            "mobile_phone": "08123456789",
+            "disabled_login": false,
+            "expired_token": 1735718400,
+            "expired_date": "2025-01-01T08:00:00+00:00",
+            "register_date": "2024-01-01T00:00:00Z",
+            "exp": 1735718400,
             "company_id": "SS",
-            "company_name": "Snake System Tech"
+            "comp_name": "Snake System Tech",
+            "branches": ["JKT"]
         }})),
         (status = 401, description = "Unauthorized", body = ActionResult<String, String>, example = json!({
             "result": false, 
             "message": "Unauthorized", 
-            "error": "Unauthorized"
+            "error": "Invalid email or password",
+            "error_code": "UNAUTHORIZED"
+        })),
+        (status = 403, description = "Account disabled or locked", body = ActionResult<String, String>, example = json!({
+            "result": false,
+            "message": "Forbidden",
+            "error": "Account is locked, try again in 15 minutes",
+            "error_code": "FORBIDDEN"
         })),
         (status = 500, description = "Internal Server Error", body = ActionResult<String, String>, example = json!({
             "result": false, 
@@ -76,8 +93,325 @@ pub fn login_doc() {}
 #[allow(dead_code)]
 pub fn check_session_doc() {}
 
+// Two-Factor Docs
+#[utoipa::path(post, path = "/api/v1/auth/2fa/verify", request_body = TwoFactorCodeRequest,
+    summary = "Langkah kedua login dengan kode 2FA",
+    description = "Membaca cookie `two_factor_token` dari `/api/v1/auth/login` (berlaku `TWO_FACTOR_PENDING_SECS`, default 300). `code` berisi kode 6 digit dari aplikasi authenticator atau backup code. Kode salah dihitung sebagai login gagal. Token hanya berlaku untuk login terakhir dan tidak bisa dipakai lagi setelah kode diterima.",
+    responses(
+        (status = 200, description = "Login Success", body = ActionResult<Claims, String>),
+        (status = 401, description = "Unauthorized", body = ActionResult<String, String>, example = json!({
+            "result": false,
+            "message": "Unauthorized",
+            "error": "Invalid two-factor code",
+            "error_code": "UNAUTHORIZED"
+        }))
+    ),
+    tag = "1. Auth Endpoints"
+)]
+#[allow(dead_code)]
+pub fn verify_two_factor_doc() {}
+
+#[utoipa::path(post, path = "/api/v1/auth/2fa/enroll",
+    summary = "Mulai enrolment 2FA",
+    description = "`Wajib login terlebih dahulu.` Membuat secret TOTP baru (SHA1, 6 digit, 30 detik). Secret disimpan terenkripsi dengan `TOTP_ENCRYPTION_KEY` dan belum aktif sampai dikonfirmasi di `/api/v1/auth/2fa/confirm`.",
+    responses(
+        (status = 200, description = "Enrolment started", body = ActionResult<TotpEnrollment, String>, example = json!({
+            "result": true,
+            "message": "Scan the QR code and confirm with the first code",
+            "data": {
+                "secret": "JBSWY3DPEHPK3PXPJBSWY3DPEHPK3PXP",
+                "provisioning_uri": "otpauth://totp/UBS%20Trade%20Dashboard:admin@example.com?secret=JBSWY3DPEHPK3PXPJBSWY3DPEHPK3PXP&issuer=UBS%20Trade%20Dashboard&algorithm=SHA1&digits=6&period=30"
+            }
+        })),
+        (status = 409, description = "Already enabled", body = ActionResult<String, String>)
+    ),
+    tag = "1. Auth Endpoints"
+)]
+#[allow(dead_code)]
+pub fn enroll_two_factor_doc() {}
+
+#[utoipa::path(post, path = "/api/v1/auth/2fa/confirm", request_body = TwoFactorCodeRequest,
+    summary = "Aktifkan 2FA dengan kode pertama",
+    description = "`Wajib login terlebih dahulu.` Response berisi 10 backup code sekali pakai yang hanya ditampilkan sekali.",
+    responses(
+        (status = 200, description = "Two-factor authentication enabled", body = ActionResult<TotpBackupCodes, String>, example = json!({
+            "result": true,
+            "message": "Two-factor authentication enabled",
+            "data": { "backup_codes": ["k7mq-2xpa", "h3nd-9vwe"] }
+        })),
+        (status = 400, description = "Invalid verification code", body = ActionResult<String, String>)
+    ),
+    tag = "1. Auth Endpoints"
+)]
+#[allow(dead_code)]
+pub fn confirm_two_factor_doc() {}
+
+#[utoipa::path(post, path = "/api/v1/auth/2fa/disable", request_body = TwoFactorCodeRequest,
+    summary = "Nonaktifkan 2FA",
+    description = "`Wajib login terlebih dahulu.` Butuh kode TOTP atau backup code yang valid.",
+    responses(
+        (status = 200, description = "Two-factor authentication disabled", body = ActionResult<String, String>),
+        (status = 400, description = "Invalid verification code", body = ActionResult<String, String>)
+    ),
+    tag = "1. Auth Endpoints"
+)]
+#[allow(dead_code)]
+pub fn disable_two_factor_doc() {}
+
+// Password Reset & Email Verification Docs
+#[utoipa::path(post, path = "/api/v1/auth/password/forgot", request_body = ForgotPasswordRequest,
+    summary = "Minta link reset password",
+    description = "Link berisi token sekali pakai (berlaku `PASSWORD_RESET_TTL_SECS`, default 3600) dikirim ke email lewat mailer `MAILER` (`smtp` atau `log`). Respons selalu sama, baik email terdaftar maupun tidak. Maksimal `MAIL_RATE_LIMIT_PER_HOUR` (default 3) email per alamat per jam.",
+    responses(
+        (status = 200, description = "Reset link sent", body = ActionResult<String, String>, example = json!({
+            "result": true,
+            "message": "If the email is registered, a password reset link has been sent"
+        })),
+        (status = 400, description = "Validation failed", body = ActionResult<String, String>)
+    ),
+    tag = "1. Auth Endpoints"
+)]
+#[allow(dead_code)]
+pub fn forgot_password_doc() {}
+
+#[utoipa::path(post, path = "/api/v1/auth/password/reset", request_body = ResetPasswordRequest,
+    summary = "Atur password baru dengan token reset",
+    description = "Token hanya bisa dipakai sekali. Semua sesi user dicabut dan penguncian akun dibuka.",
+    responses(
+        (status = 200, description = "Password reset", body = ActionResult<String, String>, example = json!({
+            "result": true,
+            "message": "Password has been reset, please login again"
+        })),
+        (status = 400, description = "Invalid or expired token", body = ActionResult<String, String>, example = json!({
+            "result": false,
+            "message": "Bad Request",
+            "error": "Reset link is invalid or has expired",
+            "error_code": "BAD_REQUEST"
+        }))
+    ),
+    tag = "1. Auth Endpoints"
+)]
+#[allow(dead_code)]
+pub fn reset_password_doc() {}
+
+#[utoipa::path(post, path = "/api/v1/auth/verify-email/send",
+    summary = "Kirim link verifikasi email",
+    description = "`Wajib login terlebih dahulu.` Link berlaku `EMAIL_VERIFY_TTL_SECS` (default 86400).",
+    responses(
+        (status = 200, description = "Verification email sent", body = ActionResult<String, String>),
+        (status = 409, description = "Email already verified", body = ActionResult<String, String>),
+        (status = 429, description = "Too many verification emails (`MAIL_RATE_LIMIT_PER_HOUR`)", body = ActionResult<String, String>, example = json!({
+            "result": false,
+            "message": "Too Many Requests",
+            "error": "Too many verification emails requested, try again later",
+            "error_code": "TOO_MANY_REQUESTS"
+        }))
+    ),
+    tag = "1. Auth Endpoints"
+)]
+#[allow(dead_code)]
+pub fn send_verification_email_doc() {}
+
+#[utoipa::path(post, path = "/api/v1/auth/verify-email", request_body = VerifyEmailRequest,
+    summary = "Verifikasi email dengan token",
+    responses(
+        (status = 200, description = "Email verified", body = ActionResult<String, String>),
+        (status = 400, description = "Invalid or expired token", body = ActionResult<String, String>)
+    ),
+    tag = "1. Auth Endpoints"
+)]
+#[allow(dead_code)]
+pub fn verify_email_doc() {}
+
+// API Key Docs
+#[utoipa::path(get, path = "/api/v1/api-keys",
+    summary = "Daftar API key milik user login",
+    description = "`Wajib login terlebih dahulu.` Secret key tidak pernah ditampilkan lagi, hanya `prefix`.",
+    responses(
+        (status = 200, description = "API keys", body = ActionResult<Vec<ApiKeyInfo>, String>, example = json!({
+            "result": true,
+            "message": "API keys retrieved successfully",
+            "data": [{
+                "api_key_nid": 1,
+                "name": "Excel add-in",
+                "prefix": "k3m9x2qa",
+                "scopes": ["view:CIFLookup", "export:CIFLookup"],
+                "created_at": "2025-01-01T08:00:00",
+                "expires_at": "2025-04-01T08:00:00",
+                "last_used_at": "2025-01-02T09:30:00",
+                "last_used_ip": "10.0.0.20",
+                "revoked_at": null
+            }]
+        })),
+        (status = 401, description = "Unauthorized", body = ActionResult<String, String>)
+    ),
+    tag = "1. Auth Endpoints"
+)]
+#[allow(dead_code)]
+pub fn get_api_keys_doc() {}
+
+#[utoipa::path(post, path = "/api/v1/api-keys/create", request_body = CreateApiKeyRequest,
+    summary = "Buat API key",
+    description = "`Wajib login terlebih dahulu.` Key dikirim lewat header `X-API-Key` dan hanya bisa memakai scope yang juga dimiliki pembuatnya (`admin` tidak bisa didelegasikan). Key hanya ditampilkan sekali di response ini.",
+    responses(
+        (status = 200, description = "API key created", body = ActionResult<ApiKeyCreated, String>, example = json!({
+            "result": true,
+            "message": "API key created, store it now because it will not be shown again",
+            "data": {
+                "api_key": "ubs_k3m9x2qa_2Ff0V3b1nQm8yJk4xWc7Rz9tLp6uHs5eDa1gKo0iMv4",
+                "info": { "api_key_nid": 1, "name": "Excel add-in", "prefix": "k3m9x2qa", "scopes": ["view:CIFLookup"] }
+            }
+        })),
+        (status = 403, description = "Scope not owned by user", body = ActionResult<String, String>)
+    ),
+    tag = "1. Auth Endpoints"
+)]
+#[allow(dead_code)]
+pub fn create_api_key_doc() {}
+
+#[utoipa::path(post, path = "/api/v1/api-keys/revoke", request_body = RevokeApiKeyRequest,
+    summary = "Cabut API key",
+    description = "`Wajib login terlebih dahulu.` User biasa hanya bisa mencabut key miliknya, permission `admin` bisa mencabut key siapa saja.",
+    responses(
+        (status = 200, description = "API key revoked", body = ActionResult<String, String>),
+        (status = 404, description = "API key not found", body = ActionResult<String, String>)
+    ),
+    tag = "1. Auth Endpoints"
+)]
+#[allow(dead_code)]
+pub fn revoke_api_key_doc() {}
+
+// Profile Docs
+#[utoipa::path(get, path = "/api/v1/profile",
+    summary = "Profil user login",
+    description = "`Wajib login terlebih dahulu.` `avatar` berisi URL thumbnail 64 / 128 / 256 px yang di-serve dari `/avatars`.",
+    responses(
+        (status = 200, description = "Profile", body = ActionResult<ProfileInfo, String>, example = json!({
+            "result": true,
+            "message": "Profile retrieved successfully",
+            "data": {
+                "auth_usernid": 12,
+                "email": "user@example.com",
+                "full_name": "Budi Santoso",
+                "mobile_phone": "081234567890",
+                "email_verified": true,
+                "picture": "/avatars/12-4f1c9a0b7d2e3f65-256.jpg",
+                "avatar": {
+                    "small": "/avatars/12-4f1c9a0b7d2e3f65-64.jpg",
+                    "medium": "/avatars/12-4f1c9a0b7d2e3f65-128.jpg",
+                    "large": "/avatars/12-4f1c9a0b7d2e3f65-256.jpg"
+                },
+                "comp_name": "PT Contoh",
+                "register_date": "2025-01-01T08:00:00"
+            }
+        })),
+        (status = 401, description = "Unauthorized", body = ActionResult<String, String>)
+    ),
+    tag = "1. Auth Endpoints"
+)]
+#[allow(dead_code)]
+pub fn get_profile_doc() {}
+
+#[utoipa::path(post, path = "/api/v1/profile", request_body = UpdateProfileRequest,
+    summary = "Update profil dan avatar",
+    description = "`Wajib login terlebih dahulu.` Hanya field yang dikirim yang diubah. `avatar` berupa base64 / data URL JPEG, PNG atau WebP (maks 5MB), di-crop persegi dan disimpan sebagai thumbnail JPEG di `AVATAR_DIR`. Cookie `token` diterbitkan ulang dengan claims terbaru.",
+    responses(
+        (status = 200, description = "Profile updated", body = ActionResult<ProfileInfo, String>),
+        (status = 400, description = "Validation failed", body = ActionResult<String, String>, example = json!({
+            "result": false,
+            "message": "Validation failed",
+            "error": { "mobile_phone": "Valus has number from 10-15 length" },
+            "error_code": "VALIDATION_ERROR"
+        }))
+    ),
+    tag = "1. Auth Endpoints"
+)]
+#[allow(dead_code)]
+pub fn update_profile_doc() {}
+
+// Setting Docs
+#[utoipa::path(get, path = "/api/v1/settings",
+    summary = "Preferensi user login",
+    description = "`Wajib login terlebih dahulu.` Setting yang belum pernah diatur berisi nilai default dari `/api/v1/settings/schema`.",
+    responses(
+        (status = 200, description = "Settings", body = ActionResult<UserPreferences, String>, example = json!({
+            "result": true,
+            "message": "Settings retrieved successfully",
+            "data": {
+                "theme": "dark",
+                "page_size": 100,
+                "locale": "id-ID",
+                "timezone": "Asia/Jakarta",
+                "number_format": "1.234,56",
+                "date_format": "dd/MM/yyyy",
+                "default_menu": "dashboard"
+            }
+        })),
+        (status = 401, description = "Unauthorized", body = ActionResult<String, String>)
+    ),
+    tag = "1. Auth Endpoints"
+)]
+#[allow(dead_code)]
+pub fn get_settings_doc() {}
+
+#[utoipa::path(get, path = "/api/v1/settings/schema",
+    summary = "Schema setting user",
+    description = "Tipe, default dan nilai yang valid untuk setiap key setting.",
+    responses(
+        (status = 200, description = "Setting schema", body = ActionResult<Vec<SettingSchema>, String>, example = json!({
+            "result": true,
+            "message": "Setting schema retrieved successfully",
+            "data": [
+                { "key": "theme", "kind": "choice", "default": "system", "values": ["system", "light", "dark"] },
+                { "key": "page_size", "kind": "integer", "default": 100, "min": 10, "max": 1000 },
+                { "key": "default_menu", "kind": "text", "default": "dashboard", "max_length": 100 }
+            ]
+        }))
+    ),
+    tag = "1. Auth Endpoints"
+)]
+#[allow(dead_code)]
+pub fn get_setting_schema_doc() {}
+
+#[utoipa::path(post, path = "/api/v1/settings", request_body = UpdateSettingsRequest,
+    summary = "Update preferensi user",
+    description = "`Wajib login terlebih dahulu.` Hanya key yang dikirim yang diubah, nilai `null` mengembalikan key ke default. Jika ada key yang tidak valid, tidak ada yang disimpan.",
+    responses(
+        (status = 200, description = "Settings updated", body = ActionResult<UserPreferences, String>),
+        (status = 400, description = "Validation failed", body = ActionResult<String, String>, example = json!({
+            "result": false,
+            "message": "Validation failed",
+            "error": { "page_size": "Must be an integer between 10 and 1000" },
+            "error_code": "VALIDATION_ERROR"
+        }))
+    ),
+    tag = "1. Auth Endpoints"
+)]
+#[allow(dead_code)]
+pub fn update_settings_doc() {}
+
+// Refresh Token Docs
+#[utoipa::path(post, path = "/api/v1/auth/refresh",
+    summary = "Perbarui access token dengan refresh token",
+    description = "Membaca cookie `refresh_token`, menerbitkan access token baru dan merotasi refresh token. Refresh token yang sudah pernah dipakai mencabut seluruh sesi.",
+    responses(
+        (status = 200, description = "Token refreshed", body = ActionResult<Claims, String>),
+        (status = 401, description = "Unauthorized", body = ActionResult<String, String>, example = json!({
+            "result": false,
+            "message": "Unauthorized",
+            "error": "Refresh token was already used, session revoked",
+            "error_code": "UNAUTHORIZED"
+        }))
+    ),
+    tag = "1. Auth Endpoints"
+)]
+#[allow(dead_code)]
+pub fn refresh_doc() {}
+
 // Logout Docs
 #[utoipa::path(post, path = "/api/v1/auth/logout", 
+    summary = "Logout dan cabut sesi saat ini",
+    description = "Sesi dicabut di server dan cookie `token` / `refresh_token` dihapus. Tetap berhasil walaupun access token sudah kedaluwarsa.",
     responses(
         (status = 200, description = "Logout Success", body = ActionResult<String, String>)
     ),
@@ -86,6 +420,73 @@ pub fn check_session_doc() {}
 #[allow(dead_code)]
 pub fn logout_doc() {}
 
+// Sessions Docs
+#[utoipa::path(get, path = "/api/v1/auth/sessions",
+    summary = "Daftar sesi aktif user login",
+    description = "`Wajib login terlebih dahulu.` Satu sesi per login / perangkat, `current` menandai sesi yang dipakai request ini.",
+    responses(
+        (status = 200, description = "Active sessions", body = ActionResult<Vec<SessionInfo>, String>, example = json!({
+            "result": true,
+            "message": "Sessions retrieved successfully",
+            "data": [{
+                "session_id": "3f0c2d1e-8a4b-4c8e-9f1a-2b3c4d5e6f70",
+                "ip_address": "10.0.0.12",
+                "app_name": "UBS Trade Dashboard",
+                "user_agent": "Mozilla/5.0",
+                "created_at": "2025-01-01T08:00:00",
+                "last_refresh_at": "2025-01-01T08:15:00",
+                "expires_at": "2025-01-15T08:15:00",
+                "current": true
+            }]
+        })),
+        (status = 401, description = "Unauthorized", body = ActionResult<String, String>)
+    ),
+    tag = "1. Auth Endpoints"
+)]
+#[allow(dead_code)]
+pub fn get_sessions_doc() {}
+
+#[utoipa::path(post, path = "/api/v1/auth/sessions/revoke", request_body = RevokeSessionRequest,
+    summary = "Cabut satu sesi",
+    description = "`Wajib login terlebih dahulu.` Access token dari sesi yang dicabut langsung ditolak dan refresh token-nya tidak bisa dipakai lagi.",
+    responses(
+        (status = 200, description = "Session revoked", body = ActionResult<String, String>),
+        (status = 404, description = "Session not found", body = ActionResult<String, String>)
+    ),
+    tag = "1. Auth Endpoints"
+)]
+#[allow(dead_code)]
+pub fn revoke_session_doc() {}
+
+// Permissions Docs
+#[utoipa::path(
+    get,
+    path = "/api/v1/auth/permissions",
+    summary = "Role & permission user login",
+    description = "Permission dibaca dari table `UserRole`, `Role`, `RolePermission` (`sql/rbac.sql`) dan di-cache per user selama `PERMISSION_CACHE_TTL_SECS` (default 300). Format permission: `view:{tablename}`, `export:{tablename}`, `edit:{tablename}`, `menu:{menu_id}`, `chart:edit`, `admin` (semua akses), dan wildcard seperti `view:*`.",
+    responses(
+        (status = 200, description = "User permissions", body = ActionResult<UserAccessInfo, String>, example = json!({
+            "result": true,
+            "message": "Permissions retrieved successfully",
+            "data": {
+                "auth_usernid": 1,
+                "email": "admin@example.com",
+                "roles": ["Analyst"],
+                "permissions": ["chart:edit", "export:ciflookup", "menu:home", "view:*"]
+            }
+        })),
+        (status = 401, description = "Unauthorized", body = ActionResult<String, String>, example = json!({
+            "result": false,
+            "message": "Unauthorized",
+            "error": "Token not found",
+            "error_code": "UNAUTHORIZED"
+        }))
+    ),
+    tag = "1. Auth Endpoints"
+)]
+#[allow(dead_code)]
+pub fn get_permissions_docs() {}
+
 // Company Docs
 #[utoipa::path(get, path = "/api/v1/generic/company",
     responses(
@@ -123,7 +524,7 @@ pub fn not_found_docs() {}
     get,
     path = "/api/v1/data/header",
     summary = "Get generic columns",
-    description = "`Wajib login terlebih dahulu. Memerlukan token dari cookies` untuk mengecek sesi login pengguna",
+    description = "`Wajib login terlebih dahulu. Memerlukan token dari cookies` untuk mengecek sesi login pengguna.\n\nDefinisi kolom dibaca dari `INFORMATION_SCHEMA.COLUMNS` / `sys.columns` (judul dari CamelCase, `filterControl` dari tipe data), bisa di-override lewat table `metadata.column_config_table` (`ViewName`, `ColumnName`, `Title`, `Sortable`, `FilterControl`, `Visible`). Procedure `Web_CreateTableObject` hanya dipakai jika `metadata.procedure_fallback` aktif.",
     params(
         HeaderParams
     ),
@@ -172,7 +573,7 @@ pub fn get_header_docs(_: web::Query<HeaderParams>) {}
     get,
     path = "/api/v1/data/get-table",
     summary = "Get generic columns",
-    description = "`Wajib get header terlebih dahulu.` untuk mengecek header columns",
+    description = "`Wajib get header terlebih dahulu.` untuk mengecek header columns.\n\nMode offset (default) memakai `offset` + `limit`. Mode keyset: kirim `cursor=` (kosong) untuk halaman pertama, lalu kirim `next_cursor` dari response untuk halaman berikutnya. `next_cursor` tidak ada di halaman terakhir.\n\nSelain cookie / Bearer token, bisa memakai header `X-API-Key` dengan scope `view:{tablename}`.",
     params(
         TableDataParams
     ),
@@ -205,8 +606,19 @@ pub fn get_header_docs(_: web::Query<HeaderParams>) {}
             ]
     
         })),
-        (status = 500, description = "Internal Server Error", example = json!({
-            "error": "Token error: 'Invalid column name 'AutoNID'.' on server S3 executing  on line 1 (code: 207, state: 1, class: 16)"
+        (status = 500, description = "Internal Server Error", body = ActionResult<String, String>, example = json!({
+            "result": false,
+            "message": "Database Error",
+            "error": "An unexpected error occurred. Please contact support with the request ID",
+            "error_code": "DATABASE_ERROR",
+            "request_id": "5f1c8a52-8a7e-4a4e-9a43-0f1f3f0f5d2b"
+        })),
+        (status = 504, description = "Query cancelled (`QUERY_TIMEOUT_GET_TABLE_SECS`, default `QUERY_TIMEOUT_SECS` = 30)", body = ActionResult<String, String>, example = json!({
+            "result": false,
+            "message": "Query Timeout",
+            "error": "The query was cancelled because it did not finish within 30 seconds. Narrow the filter and try again",
+            "error_code": "TIMEOUT",
+            "request_id": "5f1c8a52-8a7e-4a4e-9a43-0f1f3f0f5d2b"
         })),
     ),
     tag = "3. Data Endpoints"
@@ -216,6 +628,243 @@ pub fn get_table_data_docs(params: web::Query<TableDataParams>) {
     params.into_inner();
 }
 
+// Table Events Docs
+#[utoipa::path(
+    get,
+    path = "/api/v1/data/get-table/events",
+    summary = "Live update view lewat Server-Sent Events",
+    description = "Polling kolom `watermark` (default `LastUpdate`) + primary key (`nidkey`) setiap `SSE_POLL_INTERVAL_SECS` (default 5) dan mengirim baris baru/berubah sebagai event `insert` / `update`. `id` setiap event bisa dikirim ulang lewat header `Last-Event-ID` untuk melanjutkan setelah reconnect. Heartbeat dikirim setiap `SSE_HEARTBEAT_SECS` (default 15). Maksimal `SSE_MAX_SUBSCRIBERS_PER_VIEW` (default 20) subscriber per view. Kolom `watermark` dan `nidkey` tidak boleh di-mask untuk user (403), karena nilainya ada di `id` event.",
+    params(
+        TableEventParams
+    ),
+    responses(
+        (status = 200, description = "Event stream", content_type = "text/event-stream", example = json!(
+            "id: WyIyMDI1LTAxLTAxVDEwOjAwOjAwIiwxMjNd\nevent: insert\ndata: {\"DataNID\":123,\"DataName\":\"Jasa Keuangan\",\"LastUpdate\":\"2025-01-01T10:00:00\"}\n\n"
+        )),
+        (status = 503, description = "Too many subscribers", body = ActionResult<String, String>, example = json!({
+            "result": false,
+            "message": "Service Unavailable",
+            "error": "Too many live subscribers for this view (max 20)",
+            "error_code": "SERVICE_UNAVAILABLE"
+        })),
+        (status = 403, description = "Watermark or key column is masked", body = ActionResult<String, String>)
+    ),
+    tag = "3. Data Endpoints"
+)]
+#[allow(dead_code)]
+pub fn get_table_events_docs(_: web::Query<TableEventParams>) {}
+
+// Export Table Data Docs
+#[utoipa::path(
+    get,
+    path = "/api/v1/data/export",
+    summary = "Stream seluruh isi view sebagai NDJSON atau CSV",
+    description = "Untuk tools downstream yang menarik seluruh view. Setiap baris response adalah satu row JSON (`application/x-ndjson`), tanpa paging. Jika terjadi error di tengah stream, baris terakhir berisi `ActionResult` error.\n\nDengan `format=csv`, angka desimal, tanggal (dikonversi dari `DB_TIMEZONE` ke zona waktu user) dan boolean diformat sesuai `/api/v1/settings` user. Pemisah kolom `;` jika desimal memakai koma.",
+    params(
+        ExportTableParams
+    ),
+    responses(
+        (status = 200, description = "NDJSON stream", content_type = "application/x-ndjson", example = json!(
+            "{\"DataNID\":1,\"DataID\":\"DATA-123\",\"DataName\":\"Jasa Keuangan Pasar Senggol\"}\n{\"DataNID\":2,\"DataID\":\"DATA-124\",\"DataName\":\"Jasa Keuangan Pasar Kecil\"}\n"
+        )),
+        (status = 400, description = "Invalid export format", body = ActionResult<String, String>),
+        (status = 500, description = "Internal Server Error", body = ActionResult<String, String>, example = json!({
+            "result": false,
+            "message": "Database Error",
+            "error": "An unexpected error occurred. Please contact support with the request ID",
+            "error_code": "DATABASE_ERROR"
+        }))
+    ),
+    tag = "3. Data Endpoints"
+)]
+#[allow(dead_code)]
+pub fn export_table_data_docs(_: web::Query<ExportTableParams>) {}
+
+// Distinct Values Docs
+#[utoipa::path(
+    get,
+    path = "/api/v1/data/distinct",
+    summary = "Nilai unik kolom untuk filter select",
+    description = "Nilai unik (beserta jumlah baris) dari `column` di bawah `filter` saat ini, filter pada kolom itu sendiri diabaikan. Kolom dengan nilai unik lebih dari `DISTINCT_MAX_CARDINALITY` (default 200) ditolak.",
+    params(
+        DistinctParams
+    ),
+    responses(
+        (status = 200, description = "Distinct values", body = ActionResult<String, String>, example = json!({
+            "result": true,
+            "message": "Data retrieved successfully",
+            "data": [
+                { "Value": "Active", "Count": 120 },
+                { "Value": "Closed", "Count": 14 }
+            ]
+        })),
+        (status = 400, description = "Too many distinct values", body = ActionResult<String, String>, example = json!({
+            "result": false,
+            "message": "Bad Request",
+            "error": "Column 'CustomerName' has too many distinct values (5312 > 200), use a text filter instead",
+            "error_code": "BAD_REQUEST"
+        }))
+    ),
+    tag = "3. Data Endpoints"
+)]
+#[allow(dead_code)]
+pub fn get_distinct_values_docs(_: web::Query<DistinctParams>) {}
+
+// Row Insert Docs
+#[utoipa::path(
+    post,
+    path = "/api/v1/data/row/insert",
+    summary = "Insert baris ke view editable",
+    description = "Hanya untuk view dengan `views.<nama>.editable = true` di `config.json`. `values` divalidasi terhadap metadata kolom (tipe, nullability, panjang maksimal). Kolom identity, computed, rowversion dan kolom versi tidak boleh diisi.",
+    request_body = RowRequest,
+    responses(
+        (status = 200, description = "Row inserted", body = ActionResult<String, String>, example = json!({
+            "result": true,
+            "message": "Row inserted successfully",
+            "data": { "AutoNID": 42, "CIF": "C0001", "LastUpdate": "2025-01-01T10:00:00" }
+        })),
+        (status = 400, description = "Validation failed", body = ActionResult<String, String>, example = json!({
+            "result": false,
+            "message": "Validation failed",
+            "error": { "CIF": "Maximum length is 10 characters", "Branch": "Branch is required" },
+            "error_code": "VALIDATION_ERROR"
+        })),
+        (status = 403, description = "View not editable", body = ActionResult<String, String>)
+    ),
+    tag = "3. Data Endpoints"
+)]
+#[allow(dead_code)]
+pub fn insert_row_docs(_: web::Json<RowRequest>) {}
+
+// Row Update Docs
+#[utoipa::path(
+    post,
+    path = "/api/v1/data/row/update",
+    summary = "Update baris di view editable",
+    description = "`key` = nilai primary key, `version` = nilai kolom versi (`LastUpdate` atau rowversion) saat baris dibaca. Jika baris sudah diubah user lain sejak dibaca, response 409.",
+    request_body = RowRequest,
+    responses(
+        (status = 200, description = "Row updated", body = ActionResult<String, String>),
+        (status = 404, description = "Row not found", body = ActionResult<String, String>),
+        (status = 409, description = "Version conflict", body = ActionResult<String, String>, example = json!({
+            "result": false,
+            "message": "Conflict",
+            "error": "Row was modified by another user, reload and try again",
+            "error_code": "CONFLICT"
+        }))
+    ),
+    tag = "3. Data Endpoints"
+)]
+#[allow(dead_code)]
+pub fn update_row_docs(_: web::Json<RowRequest>) {}
+
+// Row Delete Docs
+#[utoipa::path(
+    post,
+    path = "/api/v1/data/row/delete",
+    summary = "Hapus baris di view editable",
+    description = "Sama seperti update: `key` wajib, `version` wajib jika view punya kolom versi.",
+    request_body = RowRequest,
+    responses(
+        (status = 200, description = "Row deleted", body = ActionResult<String, String>),
+        (status = 404, description = "Row not found", body = ActionResult<String, String>),
+        (status = 409, description = "Version conflict", body = ActionResult<String, String>)
+    ),
+    tag = "3. Data Endpoints"
+)]
+#[allow(dead_code)]
+pub fn delete_row_docs(_: web::Json<RowRequest>) {}
+
+// Chart WebSocket Docs
+#[utoipa::path(
+    get,
+    path = "/api/v1/chart/ws",
+    summary = "WebSocket live refresh bar chart",
+    description = "Setelah upgrade, client mengirim `{\"action\":\"subscribe\",\"menu_id\":\"home\",\"tablename\":\"CIFLookup\",\"filter\":\"{...}\",\"chart_ids\":[\"...\"]}` (`chart_ids` opsional) atau `{\"action\":\"unsubscribe\"}`. Agregasi dihitung ulang setiap `CHART_REFRESH_INTERVAL_SECS` (default 30), satu perhitungan dipakai bersama oleh semua subscriber chart & filter yang sama, dan hanya chart yang berubah yang dikirim sebagai `{\"type\":\"chart\",\"chart_id\",\"chart_name\",\"column\",\"data\"}`. Header `Origin` harus sama dengan host atau terdaftar di `WS_ALLOWED_ORIGINS`. Socket ditutup (close code 1008) saat access token kedaluwarsa, sesi dicabut, atau permission chart yang di-subscribe hilang; dicek setiap interval refresh.",
+    responses(
+        (status = 101, description = "Switching Protocols"),
+        (status = 400, description = "Not a WebSocket request", body = ActionResult<String, String>),
+        (status = 403, description = "Origin not allowed", body = ActionResult<String, String>)
+    ),
+    tag = "3. Data Endpoints"
+)]
+#[allow(dead_code)]
+pub fn chart_socket_docs() {}
+
+// Audit Log Docs
+#[utoipa::path(
+    get,
+    path = "/api/v1/admin/audit",
+    summary = "Baca audit log",
+    description = "Semua insert / update / delete (BarChart dan view editable) serta export data dicatat ke table `AuditLog` (`sql/audit_log.sql`) beserta user dari token, IP, data sebelum dan sesudah. Filter opsional: `user_nid`, `email`, `entity`, `action`, `from` / `to` (YYYY-MM-DD).",
+    params(
+        AuditLogParams
+    ),
+    responses(
+        (status = 200, description = "Audit log", body = ActionResult<String, String>, example = json!({
+            "result": true,
+            "message": "Data retrieved successfully",
+            "data": [
+                {
+                    "AuditLogNID": 1024,
+                    "CreatedAt": "2025-01-01T10:00:00",
+                    "UserNID": 1,
+                    "Email": "admin@example.com",
+                    "IPAddress": "10.0.0.12",
+                    "Action": "update",
+                    "Entity": "BarChart",
+                    "EntityKey": "income-by-branch",
+                    "BeforeData": { "ChartName": "Income" },
+                    "AfterData": { "ChartName": "Income by Branch" },
+                    "RequestID": "6bc71a5d-d6d5-4200-8ee7-0bfdc07cd4df"
+                }
+            ]
+        }))
+    ),
+    tag = "4. Admin Endpoints"
+)]
+#[allow(dead_code)]
+pub fn get_audit_logs_docs(_: web::Query<AuditLogParams>) {}
+
+// Permission Invalidate Docs
+#[utoipa::path(
+    post,
+    path = "/api/v1/admin/permissions/invalidate",
+    summary = "Hapus cache permission",
+    description = "Dipakai setelah role / permission user diubah di database. Tanpa `user_nid` semua cache dihapus.",
+    params(
+        PermissionInvalidateParams
+    ),
+    responses(
+        (status = 200, description = "Permission cache invalidated", body = ActionResult<String, String>),
+        (status = 403, description = "Missing permission 'admin'", body = ActionResult<String, String>)
+    ),
+    tag = "4. Admin Endpoints"
+)]
+#[allow(dead_code)]
+pub fn invalidate_permissions_docs(_: web::Query<PermissionInvalidateParams>) {}
+
+// Cache Invalidate Docs
+#[utoipa::path(
+    post,
+    path = "/api/v1/admin/cache/invalidate",
+    summary = "Hapus cache count & chart satu view",
+    description = "Cache dikonfigurasi di `config.json` (`cache.default_ttl_secs`, `cache.max_entries`, `views.<nama>.cache_ttl_secs`)",
+    params(
+        CacheInvalidateParams
+    ),
+    responses(
+        (status = 200, description = "Cache invalidated", body = ActionResult<String, String>, example = json!({
+            "result": true,
+            "message": "3 cache entries removed",
+            "data": { "tablename": "CIFLookup", "removed": 3 }
+        }))
+    ),
+    tag = "4. Admin Endpoints"
+)]
+#[allow(dead_code)]
+pub fn invalidate_cache_docs(_: web::Query<CacheInvalidateParams>) {}
+
 // Health Check Docs
 #[utoipa::path(
     get,
@@ -233,31 +882,129 @@ pub async fn health_check() -> impl Responder {
     })
 }
 
+// Liveness Docs
+#[utoipa::path(
+    get,
+    path = "/health/live",
+    summary = "Liveness probe",
+    description = "Selalu 200 selama proses aplikasi berjalan, tidak mengecek database",
+    responses(
+        (status = 200, description = "Alive", body = ActionResult<String, String>, example = json!({
+            "result": true,
+            "message": "Alive"
+        }))
+    ),
+    tag = "0. Application Default Endpoints"
+)]
+#[allow(dead_code)]
+pub fn liveness_docs() {}
+
+// Readiness Docs
+#[utoipa::path(
+    get,
+    path = "/health/ready",
+    summary = "Readiness probe",
+    description = "Menjalankan `SELECT 1` lewat pool dengan timeout (`HEALTH_READY_TIMEOUT_MS`, default 3000) dan melaporkan state pool",
+    responses(
+        (status = 200, description = "Ready", body = ActionResult<ReadinessStatus, String>, example = json!({
+            "result": true,
+            "message": "Ready",
+            "data": {
+                "database": "up",
+                "latency_ms": 4,
+                "pool": { "connections": 2, "idle_connections": 2, "max_size": 10, "get_waited": 0, "get_timed_out": 0 }
+            }
+        })),
+        (status = 503, description = "Database unavailable", body = ActionResult<ReadinessStatus, String>, example = json!({
+            "result": false,
+            "message": "Database unavailable",
+            "data": {
+                "database": "down",
+                "latency_ms": 3000,
+                "pool": { "connections": 0, "idle_connections": 0, "max_size": 10, "get_waited": 0, "get_timed_out": 0 }
+            },
+            "error": "Database is not reachable",
+            "error_code": "SERVICE_UNAVAILABLE"
+        }))
+    ),
+    tag = "0. Application Default Endpoints"
+)]
+#[allow(dead_code)]
+pub fn readiness_docs() {}
+
+// Metrics Docs
+#[utoipa::path(
+    get,
+    path = "/metrics",
+    summary = "Prometheus metrics",
+    description = "Metric HTTP per route, state pool database, durasi query per table dan jumlah error per `ActionResult.message` dalam format text Prometheus",
+    responses(
+        (status = 200, description = "Prometheus text format", content_type = "text/plain")
+    ),
+    tag = "0. Application Default Endpoints"
+)]
+#[allow(dead_code)]
+pub fn metrics_docs() {}
+
 #[derive(OpenApi)]
 #[openapi(
     info(
         title = "UBS Trade Dashboard API",
-        description = "Dokumentasi untuk RESTful API UBS Trade Dashboard.\n\nSilakan gunakan token JWT untuk mengakses endpoint yang dilindungi.",
+        description = "Dokumentasi untuk RESTful API UBS Trade Dashboard.\n\nSilakan gunakan token JWT untuk mengakses endpoint yang dilindungi.\n\nSemua endpoint `/api/v1` dibatasi per user / API key / IP (token bucket per route, lihat `rate_limit` di config) dan jumlah request bersamaan per user. Request dengan header `X-API-Key` juga dibatasi per IP (`api_key_ip`) sebelum key diverifikasi. Request yang melewati batas dijawab `429` dengan header `Retry-After` (detik) dan `error_code` `TOO_MANY_REQUESTS`.",
         version = "1.0.0"
     ),
     paths(
         health_check,
+        liveness_docs,
+        readiness_docs,
+        metrics_docs,
         login_doc,
+        verify_two_factor_doc,
         check_session_doc,
+        refresh_doc,
         logout_doc,
+        get_sessions_doc,
+        revoke_session_doc,
+        enroll_two_factor_doc,
+        confirm_two_factor_doc,
+        disable_two_factor_doc,
+        forgot_password_doc,
+        reset_password_doc,
+        send_verification_email_doc,
+        verify_email_doc,
+        get_api_keys_doc,
+        create_api_key_doc,
+        revoke_api_key_doc,
+        get_profile_doc,
+        update_profile_doc,
+        get_settings_doc,
+        get_setting_schema_doc,
+        update_settings_doc,
         get_company_docs,
         not_found_docs,
         get_header_docs,
-        get_table_data_docs
+        get_table_data_docs,
+        get_table_events_docs,
+        export_table_data_docs,
+        get_distinct_values_docs,
+        insert_row_docs,
+        update_row_docs,
+        delete_row_docs,
+        chart_socket_docs,
+        invalidate_cache_docs,
+        get_audit_logs_docs,
+        get_permissions_docs,
+        invalidate_permissions_docs
     ),
     components(
-        schemas(ActionResult<Claims, String>)
+        schemas(ActionResult<Claims, String>, ActionResult<TwoFactorPending, String>)
     ),
     tags(
         (name = "0. Application Default Endpoints", description = "Default path application endpoints"),
         (name = "1. Auth Endpoints", description = "Authentication related endpoints"),
         (name = "2. Generic Endpoints", description = "Another related endpoints"),
-        (name = "3. Data Endpoints", description = "Data related endpoints")
+        (name = "3. Data Endpoints", description = "Data related endpoints"),
+        (name = "4. Admin Endpoints", description = "Administration endpoints")
     )
 )]
 
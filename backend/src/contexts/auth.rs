@@ -0,0 +1,284 @@
+use std::{env, future::{ready, Ready}, net::IpAddr, sync::Arc};
+
+use actix_web::{body::{BoxBody, MessageBody}, cookie::{time, Cookie, SameSite}, dev::{Payload, ServiceRequest, ServiceResponse}, http::header, middleware::Next, web, FromRequest, HttpMessage, HttpRequest};
+use bb8::Pool;
+use futures::future::LocalBoxFuture;
+use jsonwebtoken::{decode, encode, errors::ErrorKind, DecodingKey, EncodingKey, Header, Validation};
+use serde::{Deserialize, Serialize};
+
+use crate::services::{api_key_service::{ApiKeyIdentity, ApiKeyService}, permission_service::{PermissionService, UserAccess}, session_service::SessionService};
+
+use super::{column_mask::ColumnMasks, config::APP_CONFIG, connection::ConnectionManager, error::AppError, model::Claims, row_scope::{is_identifier, RowScope, SCOPE_ALL}};
+
+/// Nama cookie yang menyimpan access token
+pub const TOKEN_COOKIE: &str = "token";
+/// Header API key untuk akses service-to-service (reporting job, Excel add-in)
+pub const API_KEY_HEADER: &str = "x-api-key";
+/// Nama cookie refresh token, hanya dikirim ke endpoint `/api/v1/auth`
+pub const REFRESH_COOKIE: &str = "refresh_token";
+/// Nama cookie login yang sudah lolos password tapi menunggu verifikasi 2FA
+pub const TWO_FACTOR_COOKIE: &str = "two_factor_token";
+/// Nilai `purpose` token login yang menunggu 2FA
+const TWO_FACTOR_PURPOSE: &str = "2fa";
+
+/// Isi token login yang menunggu 2FA. Field-nya berbeda dengan `Claims`, jadi tidak bisa dipakai sebagai access token
+#[derive(Serialize, Deserialize)]
+struct PendingTwoFactor {
+    sub: i32,
+    purpose: String,
+    /// Harus sama dengan `AuthUserTotp.PendingNonce`, dihapus saat kode diterima supaya token hanya sekali pakai
+    nonce: String,
+    exp: usize,
+}
+
+/// Ambil token dari cookie `token`, atau header `Authorization: Bearer <token>`
+pub fn token_from_request(req: &HttpRequest) -> Option<String> {
+    if let Some(cookie) = req.cookie(TOKEN_COOKIE) {
+        return Some(cookie.value().to_string());
+    }
+
+    req.headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .map(|token| token.trim().to_string())
+}
+
+fn jwt_secret() -> Result<String, AppError> {
+    env::var("JWT_SECRET").map_err(|_| AppError::Internal("JWT_SECRET harus diatur".to_string()))
+}
+
+pub fn encode_claims(claims: &Claims) -> Result<String, AppError> {
+    encode(&Header::default(), claims, &EncodingKey::from_secret(jwt_secret()?.as_bytes()))
+        .map_err(|e| AppError::Internal(format!("Failed to create token: {}", e)))
+}
+
+/// Cookie HttpOnly untuk access token. Set `COOKIE_SECURE=false` untuk development tanpa HTTPS
+pub fn token_cookie(token: String, max_age_secs: i64) -> Cookie<'static> {
+    auth_cookie(TOKEN_COOKIE, token, "/", max_age_secs)
+}
+
+pub fn refresh_cookie(token: String, max_age_secs: i64) -> Cookie<'static> {
+    auth_cookie(REFRESH_COOKIE, token, "/api/v1/auth", max_age_secs)
+}
+
+pub fn two_factor_cookie(token: String, max_age_secs: i64) -> Cookie<'static> {
+    auth_cookie(TWO_FACTOR_COOKIE, token, "/api/v1/auth/2fa", max_age_secs)
+}
+
+fn auth_cookie(name: &'static str, value: String, path: &'static str, max_age_secs: i64) -> Cookie<'static> {
+    let secure = env::var("COOKIE_SECURE").map(|v| v != "false").unwrap_or(true);
+
+    Cookie::build(name, value)
+        .path(path)
+        .http_only(true)
+        .secure(secure)
+        .same_site(SameSite::Lax)
+        .max_age(time::Duration::seconds(max_age_secs.max(0)))
+        .finish()
+}
+
+pub fn decode_claims(token: &str) -> Result<Claims, AppError> {
+    let secret = jwt_secret()?;
+
+    decode::<Claims>(token, &DecodingKey::from_secret(secret.as_bytes()), &Validation::default())
+        .map_err(|e| match e.kind() {
+            ErrorKind::ExpiredSignature => AppError::Unauthorized("Token has expired".to_string()),
+            _ => AppError::Unauthorized("Invalid token".to_string()),
+        })
+        .and_then(|data| {
+            if data.claims.disabled_login {
+                return Err(AppError::Forbidden("Account is disabled".to_string()));
+            }
+            if data.claims.sid.as_deref().is_some_and(SessionService::is_revoked) {
+                return Err(AppError::Unauthorized("Session has been revoked".to_string()));
+            }
+
+            Ok(data.claims)
+        })
+}
+
+/// Token login yang menunggu 2FA untuk `user_nid`, berlaku `ttl_secs` detik
+pub fn encode_pending_two_factor(user_nid: i32, nonce: &str, ttl_secs: i64) -> Result<String, AppError> {
+    let pending = PendingTwoFactor {
+        sub: user_nid,
+        purpose: TWO_FACTOR_PURPOSE.to_string(),
+        nonce: nonce.to_string(),
+        exp: (chrono::Utc::now().timestamp() + ttl_secs) as usize,
+    };
+
+    encode(&Header::default(), &pending, &EncodingKey::from_secret(jwt_secret()?.as_bytes()))
+        .map_err(|e| AppError::Internal(format!("Failed to create token: {}", e)))
+}
+
+/// `AuthUserNID` dan nonce dari token login yang menunggu 2FA
+pub fn decode_pending_two_factor(token: &str) -> Result<(i32, String), AppError> {
+    let secret = jwt_secret()?;
+
+    decode::<PendingTwoFactor>(token, &DecodingKey::from_secret(secret.as_bytes()), &Validation::default())
+        .ok()
+        .filter(|data| data.claims.purpose == TWO_FACTOR_PURPOSE)
+        .map(|data| (data.claims.sub, data.claims.nonce))
+        .ok_or_else(|| AppError::Unauthorized("Two-factor login has expired, please login again".to_string()))
+}
+
+/// User-Agent client untuk daftar sesi, dipotong supaya muat di kolom database
+pub fn user_agent(req: &HttpRequest) -> Option<String> {
+    req.headers()
+        .get(header::USER_AGENT)
+        .and_then(|value| value.to_str().ok())
+        .map(|value| value.chars().take(256).collect())
+}
+
+/// IP client dari koneksi TCP. `X-Forwarded-For` hanya dibaca jika koneksi datang dari `trusted_proxies`,
+/// dan diambil dari kanan: alamat pertama yang bukan proxy terpercaya, karena isi sebelah kiri bisa dikarang client.
+pub fn client_ip(req: &HttpRequest) -> Option<String> {
+    let trusted = |ip: &IpAddr| APP_CONFIG.trusted_proxies.contains(ip);
+    let peer = req.peer_addr()?.ip();
+    if !trusted(&peer) {
+        return Some(peer.to_string());
+    }
+
+    let forwarded: Vec<IpAddr> = req.headers()
+        .get_all("x-forwarded-for")
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .filter_map(|ip| ip.trim().parse().ok())
+        .collect();
+
+    let ip = forwarded.iter().rev().find(|ip| !trusted(ip)).or(forwarded.first()).copied().unwrap_or(peer);
+    Some(ip.to_string())
+}
+
+/// Middleware: verifikasi header `X-API-Key` dan simpan `ApiKeyIdentity` di request extensions.
+/// `UserPermissions` dan `AuditActor` memakai identitas ini seperti token JWT; endpoint yang hanya
+/// meminta `Claims` (akun, sesi, 2FA, API key) tetap butuh login browser.
+pub async fn api_key_middleware(req: ServiceRequest, next: Next<impl MessageBody + 'static>) -> Result<ServiceResponse<BoxBody>, actix_web::Error> {
+    let Some(key) = req.headers().get(API_KEY_HEADER).and_then(|value| value.to_str().ok()).map(str::to_string) else {
+        return next.call(req).await.map(ServiceResponse::map_into_boxed_body);
+    };
+
+    let identity = match req.app_data::<web::Data<Pool<ConnectionManager>>>() {
+        Some(pool) => ApiKeyService::authenticate(pool, &key, client_ip(req.request())).await,
+        None => Err(AppError::Internal("Database pool is not configured".to_string())),
+    };
+
+    match identity {
+        Ok(identity) => {
+            req.extensions_mut().insert(identity);
+            next.call(req).await.map(ServiceResponse::map_into_boxed_body)
+        }
+        Err(e) => Ok(req.error_response(e)),
+    }
+}
+
+/// Extractor `Claims` dari token request. Pakai `Option<Claims>` untuk endpoint yang boleh anonim.
+impl FromRequest for Claims {
+    type Error = AppError;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        ready(
+            token_from_request(req)
+                .ok_or_else(|| AppError::Unauthorized("Token not found".to_string()))
+                .and_then(|token| decode_claims(&token)),
+        )
+    }
+}
+
+/// Pelaku aksi untuk audit log: user dari token (jika ada) dan IP client
+#[derive(Debug, Clone, Default)]
+pub struct AuditActor {
+    pub user_nid: Option<i32>,
+    pub email: Option<String>,
+    pub ip_address: Option<String>,
+}
+
+impl FromRequest for AuditActor {
+    type Error = AppError;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        let claims = match req.extensions().get::<Arc<ApiKeyIdentity>>() {
+            Some(identity) => Some(identity.claims.clone()),
+            None => token_from_request(req).and_then(|token| decode_claims(&token).ok()),
+        };
+
+        ready(Ok(Self {
+            user_nid: claims.as_ref().map(|c| c.auth_usernid),
+            email: claims.map(|c| c.email),
+            ip_address: client_ip(req),
+        }))
+    }
+}
+
+/// User yang sudah login beserta role & permission-nya. Dipakai sebagai extractor di handler,
+/// lalu cek akses dengan `require("view:{tablename}")`.
+pub struct UserPermissions {
+    pub claims: Claims,
+    pub access: Arc<UserAccess>,
+    /// Scope API key jika request memakai `X-API-Key`, membatasi `access` milik pemilik key
+    pub scopes: Option<Arc<UserAccess>>,
+}
+
+impl UserPermissions {
+    pub fn has(&self, permission: &str) -> bool {
+        self.access.has(permission) && self.scopes.as_ref().is_none_or(|scopes| scopes.has(permission))
+    }
+
+    /// Tolak request dengan API key, untuk aksi yang hanya boleh dari login browser
+    pub fn require_login(&self) -> Result<(), AppError> {
+        match self.scopes {
+            Some(_) => Err(AppError::Forbidden("This action is not allowed with an API key".to_string())),
+            None => Ok(()),
+        }
+    }
+
+    pub fn require(&self, permission: &str) -> Result<(), AppError> {
+        if self.has(permission) {
+            return Ok(());
+        }
+
+        Err(AppError::Forbidden(format!("Missing permission '{}'", permission)))
+    }
+
+    /// Row filter untuk view ini, dilewati jika user punya `scope:all`
+    pub fn row_scope(&self, tablename: &str) -> Result<RowScope, AppError> {
+        if !is_identifier(tablename) {
+            return Err(AppError::BadRequest(format!("Invalid view name '{}'", tablename)));
+        }
+
+        if self.has(SCOPE_ALL) {
+            return Ok(RowScope::default());
+        }
+
+        RowScope::for_view(&self.claims, tablename)
+    }
+
+    /// Masking kolom untuk view ini berdasarkan permission user
+    pub fn column_masks(&self, tablename: &str) -> ColumnMasks {
+        ColumnMasks::for_view(tablename, |permission| self.has(permission))
+    }
+}
+
+impl FromRequest for UserPermissions {
+    type Error = AppError;
+    type Future = LocalBoxFuture<'static, Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, payload: &mut Payload) -> Self::Future {
+        let api_key = req.extensions().get::<Arc<ApiKeyIdentity>>().cloned();
+        let claims = match &api_key {
+            Some(identity) => Ok(identity.claims.clone()),
+            None => Claims::from_request(req, payload).into_inner(),
+        };
+        let pool = req.app_data::<web::Data<Pool<ConnectionManager>>>().cloned();
+
+        Box::pin(async move {
+            let claims = claims?;
+            let pool = pool.ok_or_else(|| AppError::Internal("Database pool is not configured".to_string()))?;
+            let access = PermissionService::access_for(&pool, claims.auth_usernid).await?;
+
+            Ok(Self { claims, access, scopes: api_key.map(|identity| identity.scopes.clone()) })
+        })
+    }
+}
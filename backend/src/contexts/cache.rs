@@ -0,0 +1,137 @@
+use std::{collections::{BTreeMap, HashMap}, sync::Mutex, time::{Duration, Instant}};
+
+use once_cell::sync::Lazy;
+use serde_json::Value as JsonValue;
+
+use super::{config::APP_CONFIG, metrics::METRICS, row_scope::RowScope};
+
+/// Key cache: (nama view, filter yang sudah dinormalisasi, jenis agregasi, row scope user)
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+pub struct CacheKey {
+    pub tablename: String,
+    pub filter: String,
+    pub aggregation: String,
+    pub scope: String,
+}
+
+impl CacheKey {
+    pub fn new(tablename: &str, filter: Option<&str>, aggregation: &str) -> Self {
+        Self {
+            tablename: tablename.to_lowercase(),
+            filter: normalize_filter(filter),
+            aggregation: aggregation.to_string(),
+            scope: String::new(),
+        }
+    }
+
+    /// Pisahkan entry per row scope, untuk data yang dibatasi row-level security
+    pub fn scoped(mut self, scope: &RowScope) -> Self {
+        self.scope = scope.cache_scope();
+        self
+    }
+}
+
+struct CacheEntry {
+    value: JsonValue,
+    inserted_at: Instant,
+    expires_at: Instant,
+}
+
+/// Cache in-process untuk hasil count dan agregasi chart
+pub struct QueryCache {
+    entries: Mutex<HashMap<CacheKey, CacheEntry>>,
+}
+
+pub static QUERY_CACHE: Lazy<QueryCache> = Lazy::new(|| QueryCache {
+    entries: Mutex::new(HashMap::new()),
+});
+
+/// Urutkan key filter supaya `{"a":1,"b":2}` dan `{"b":2,"a":1}` memakai entry yang sama
+pub fn normalize_filter(filter: Option<&str>) -> String {
+    match filter {
+        None | Some("{filter:undefined}") => String::new(),
+        Some(filter) => match serde_json::from_str::<BTreeMap<String, JsonValue>>(filter) {
+            Ok(map) if map.is_empty() => String::new(),
+            Ok(map) => serde_json::to_string(&map).unwrap_or_else(|_| filter.to_string()),
+            Err(_) => filter.to_string(),
+        },
+    }
+}
+
+impl QueryCache {
+    /// TTL cache untuk view, `None` jika cache tidak aktif untuk view tersebut
+    pub fn ttl_for(tablename: &str) -> Option<Duration> {
+        if !APP_CONFIG.cache.enabled {
+            return None;
+        }
+
+        let ttl = APP_CONFIG.view(tablename).cache_ttl_secs.unwrap_or(APP_CONFIG.cache.default_ttl_secs);
+        (ttl > 0).then(|| Duration::from_secs(ttl))
+    }
+
+    pub fn get(&self, key: &CacheKey) -> Option<JsonValue> {
+        Self::ttl_for(&key.tablename)?;
+
+        let mut entries = self.entries.lock().unwrap();
+        let value = match entries.get(key) {
+            Some(entry) if entry.expires_at > Instant::now() => Some(entry.value.clone()),
+            Some(_) => {
+                entries.remove(key);
+                None
+            }
+            None => None,
+        };
+
+        METRICS.observe_cache(&key.aggregation, value.is_some());
+        value
+    }
+
+    pub fn insert(&self, key: CacheKey, value: JsonValue) {
+        let Some(ttl) = Self::ttl_for(&key.tablename) else {
+            return;
+        };
+
+        let now = Instant::now();
+        let mut entries = self.entries.lock().unwrap();
+
+        if entries.len() >= APP_CONFIG.cache.max_entries && !entries.contains_key(&key) {
+            entries.retain(|_, entry| entry.expires_at > now);
+
+            // Masih penuh: buang entry paling lama
+            if entries.len() >= APP_CONFIG.cache.max_entries {
+                if let Some(oldest) = entries.iter().min_by_key(|(_, entry)| entry.inserted_at).map(|(k, _)| k.clone()) {
+                    entries.remove(&oldest);
+                }
+            }
+        }
+
+        entries.insert(key, CacheEntry {
+            value,
+            inserted_at: now,
+            expires_at: now + ttl,
+        });
+    }
+
+    /// Hapus semua entry milik satu view, return jumlah entry yang dihapus
+    pub fn invalidate_view(&self, tablename: &str) -> usize {
+        let tablename = tablename.to_lowercase();
+        let mut entries = self.entries.lock().unwrap();
+        let before = entries.len();
+        entries.retain(|key, _| key.tablename != tablename);
+        before - entries.len()
+    }
+}
+
+/// Helper agregasi: ambil dari cache atau jalankan `load` lalu simpan hasilnya
+pub async fn cached<F, E>(key: CacheKey, load: F) -> Result<JsonValue, E>
+where
+    F: std::future::Future<Output = Result<JsonValue, E>>,
+{
+    if let Some(value) = QUERY_CACHE.get(&key) {
+        return Ok(value);
+    }
+
+    let value = load.await?;
+    QUERY_CACHE.insert(key, value.clone());
+    Ok(value)
+}
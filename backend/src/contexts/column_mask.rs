@@ -0,0 +1,139 @@
+use std::env;
+
+use serde_json::{json, Value as JsonValue};
+use sha2::{Digest, Sha256};
+
+use super::{config::{MaskRule, APP_CONFIG}, error::AppError};
+
+/// Masking kolom yang berlaku untuk satu user pada satu view.
+/// Dipasang pada output `row_to_json` sebelum dikirim ke client (tabel, export, SSE, row CRUD).
+#[derive(Debug, Clone, Default)]
+pub struct ColumnMasks {
+    rules: Vec<(String, MaskRule)>,
+}
+
+impl ColumnMasks {
+    /// Aturan dari `views.<tablename>.masks` yang permission-nya tidak dimiliki user
+    pub fn for_view(tablename: &str, has: impl Fn(&str) -> bool) -> Self {
+        let rules = APP_CONFIG.view(tablename).masks.into_iter()
+            .filter(|mask| {
+                let permission = mask.permission.clone().unwrap_or_else(|| format!("unmask:{}", tablename));
+                !has(&permission)
+            })
+            .map(|mask| (mask.column, mask.rule))
+            .collect();
+
+        Self { rules }
+    }
+
+    fn rule(&self, column: &str) -> Option<&MaskRule> {
+        self.rules.iter().find(|(c, _)| c.eq_ignore_ascii_case(column)).map(|(_, rule)| rule)
+    }
+
+    pub fn is_masked(&self, column: &str) -> bool {
+        self.rule(column).is_some()
+    }
+
+    /// Kolom yang dihapus seluruhnya dari output `apply`
+    pub fn is_hidden(&self, column: &str) -> bool {
+        matches!(self.rule(column), Some(MaskRule::Hide))
+    }
+
+    /// Tolak operasi yang membuka nilai asli kolom (distinct, group by chart)
+    pub fn require_unmasked(&self, column: &str) -> Result<(), AppError> {
+        if self.is_masked(column) {
+            return Err(AppError::Forbidden(format!("Column '{}' is masked", column)));
+        }
+
+        Ok(())
+    }
+
+    /// Hapus filter client pada kolom yang di-mask supaya filter tidak bisa dipakai menebak nilai
+    pub fn strip_filter(&self, filter: Option<String>) -> Option<String> {
+        let filter = filter?;
+        if self.rules.is_empty() {
+            return Some(filter);
+        }
+
+        match serde_json::from_str::<serde_json::Map<String, JsonValue>>(&filter) {
+            Ok(mut map) => {
+                map.retain(|key, _| !self.is_masked(key));
+                Some(JsonValue::Object(map).to_string())
+            }
+            Err(_) => Some(filter),
+        }
+    }
+
+    /// Tandai kolom header yang di-mask (`masked: <rule>`); filter dimatikan, kolom `hide` disembunyikan
+    pub fn mark_header(&self, columns: &mut [JsonValue]) {
+        for column in columns.iter_mut() {
+            let Some(field) = column.get("field").and_then(|f| f.as_str()).map(str::to_string) else {
+                continue;
+            };
+            let (Some(rule), Some(column)) = (self.rule(&field), column.as_object_mut()) else {
+                continue;
+            };
+
+            let name = match rule {
+                MaskRule::Hide => {
+                    column.insert("visible".to_string(), json!(false));
+                    "hide"
+                }
+                MaskRule::Partial { .. } => "partial",
+                MaskRule::Hash => "hash",
+                MaskRule::Bucket { .. } => "bucket",
+            };
+            column.insert("masked".to_string(), json!(name));
+            column.remove("filterControl");
+        }
+    }
+
+    /// Terapkan masking ke satu baris hasil `row_to_json`
+    pub fn apply(&self, mut row: JsonValue) -> JsonValue {
+        let Some(object) = row.as_object_mut().filter(|_| !self.rules.is_empty()) else {
+            return row;
+        };
+
+        for (column, rule) in &self.rules {
+            let Some(key) = object.keys().find(|k| k.eq_ignore_ascii_case(column)).cloned() else {
+                continue;
+            };
+
+            if let MaskRule::Hide = rule {
+                object.remove(&key);
+            } else if let Some(value) = object.get_mut(&key).filter(|v| !v.is_null()) {
+                *value = Self::mask_value(rule, value);
+            }
+        }
+
+        row
+    }
+
+    fn mask_value(rule: &MaskRule, value: &JsonValue) -> JsonValue {
+        let text = match value {
+            JsonValue::String(s) => s.clone(),
+            v => v.to_string(),
+        };
+
+        match rule {
+            MaskRule::Hide => JsonValue::Null,
+            MaskRule::Partial { visible } => {
+                let chars: Vec<char> = text.chars().collect();
+                if chars.len() <= *visible {
+                    return json!("*".repeat(chars.len()));
+                }
+                let tail: String = chars[chars.len() - visible..].iter().collect();
+                json!(format!("****{}", tail))
+            }
+            MaskRule::Hash => {
+                let secret = env::var("MASK_HASH_SECRET").or_else(|_| env::var("JWT_SECRET")).unwrap_or_default();
+                let digest = Sha256::digest(format!("{}:{}", secret, text).as_bytes());
+                json!(digest.iter().take(8).map(|b| format!("{:02x}", b)).collect::<String>())
+            }
+            MaskRule::Bucket { size } => match value.as_f64().or_else(|| text.trim().parse().ok()) {
+                Some(number) if *size > 0.0 => json!((number / size).floor() * size),
+                _ => JsonValue::Null,
+            },
+        }
+    }
+}
@@ -0,0 +1,194 @@
+use std::{collections::HashMap, env, fs, io::ErrorKind, net::IpAddr};
+
+use once_cell::sync::Lazy;
+use serde::Deserialize;
+
+use super::logger::write_log;
+
+/// Konfigurasi aplikasi dari file JSON (`APP_CONFIG_PATH`, default `config.json`).
+/// Jika file tidak ada, semua nilai memakai default; file yang tidak valid menghentikan startup.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct AppConfig {
+    pub cache: CacheConfig,
+    pub metadata: MetadataConfig,
+    pub rate_limit: RateLimitConfig,
+    /// IP reverse proxy yang header `X-Forwarded-For`-nya dipercaya untuk menentukan IP client
+    pub trusted_proxies: Vec<IpAddr>,
+    pub views: HashMap<String, ViewConfig>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct CacheConfig {
+    pub enabled: bool,
+    pub default_ttl_secs: u64,
+    pub max_entries: usize,
+}
+
+impl Default for CacheConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            default_ttl_secs: 60,
+            max_entries: 1000,
+        }
+    }
+}
+
+/// Sumber definisi kolom untuk `/data/header`
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct MetadataConfig {
+    /// Pakai `dbo.Web_CreateTableObject` jika metadata dari INFORMATION_SCHEMA gagal / kosong
+    pub procedure_fallback: bool,
+    /// Table override judul / filter / sort per kolom, kosongkan untuk mematikan
+    pub column_config_table: String,
+}
+
+impl Default for MetadataConfig {
+    fn default() -> Self {
+        Self {
+            procedure_fallback: false,
+            column_config_table: "dbo.ColumnConfig".to_string(),
+        }
+    }
+}
+
+/// Batas request per user / API key / IP untuk path `/api/v1`, lihat `rate_limit_middleware`
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct RateLimitConfig {
+    pub enabled: bool,
+    /// Bucket untuk path yang tidak cocok dengan `routes`
+    pub default: RateLimitRule,
+    /// Override per prefix path (mis. `/api/v1/data/export`), prefix terpanjang yang dipakai
+    pub routes: HashMap<String, RateLimitRule>,
+    /// Bucket per IP untuk request dengan header `X-API-Key`, dicek sebelum key diverifikasi ke database
+    pub api_key_ip: RateLimitRule,
+    /// Request yang sedang berjalan (query database) per user, `0` untuk mematikan
+    pub max_concurrent_per_user: usize,
+    /// Lama menunggu slot kosong sebelum dijawab 429
+    pub concurrency_wait_ms: u64,
+    /// Prefix path stream panjang (SSE / WebSocket) yang tidak dihitung sebagai query bersamaan
+    pub concurrency_exempt: Vec<String>,
+}
+
+/// Token bucket: `capacity` request sekaligus, terisi `refill_per_sec` token per detik
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub struct RateLimitRule {
+    pub capacity: f64,
+    pub refill_per_sec: f64,
+}
+
+impl Default for RateLimitConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            default: RateLimitRule { capacity: 120.0, refill_per_sec: 2.0 },
+            routes: HashMap::from([
+                ("/api/v1/data/export".to_string(), RateLimitRule { capacity: 5.0, refill_per_sec: 0.1 }),
+                ("/api/v1/chart/bar".to_string(), RateLimitRule { capacity: 30.0, refill_per_sec: 0.5 }),
+                ("/api/v1/auth/login".to_string(), RateLimitRule { capacity: 10.0, refill_per_sec: 0.1 }),
+            ]),
+            api_key_ip: RateLimitRule { capacity: 240.0, refill_per_sec: 4.0 },
+            max_concurrent_per_user: 4,
+            concurrency_wait_ms: 2000,
+            concurrency_exempt: vec!["/api/v1/data/get-table/events".to_string(), "/api/v1/chart/ws".to_string()],
+        }
+    }
+}
+
+/// Pengaturan per view / table, key-nya nama view (case-insensitive)
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(default)]
+pub struct ViewConfig {
+    /// TTL cache count & chart untuk view ini, `0` untuk mematikan cache
+    pub cache_ttl_secs: Option<u64>,
+    /// Boleh insert / update / delete lewat `/data/row/*`
+    pub editable: bool,
+    /// Primary key untuk update / delete, default dari metadata atau `AutoNID`
+    pub primary_key: Option<String>,
+    /// Kolom optimistic concurrency (datetime atau rowversion), default `LastUpdate`
+    pub version_column: Option<String>,
+    /// Filter baris wajib berdasarkan claim user, lihat `RowScope`
+    pub row_filters: Vec<RowFilterConfig>,
+    /// Masking kolom sensitif untuk user tanpa permission `unmask:{view}`
+    pub masks: Vec<ColumnMaskConfig>,
+}
+
+/// Satu row filter: hanya baris dengan `column` bernilai sama dengan `claim` user yang terlihat
+#[derive(Debug, Clone, Deserialize)]
+pub struct RowFilterConfig {
+    pub column: String,
+    /// Nama claim token: `company_id`, `comp_name`, `branches`, `auth_usernid` atau `email`
+    pub claim: String,
+}
+
+/// Aturan masking satu kolom, contoh `{ "column": "EndIncome", "rule": "bucket", "size": 1000000 }`
+#[derive(Debug, Clone, Deserialize)]
+pub struct ColumnMaskConfig {
+    pub column: String,
+    /// Permission yang membuka nilai asli, default `unmask:{view}`
+    pub permission: Option<String>,
+    #[serde(flatten)]
+    pub rule: MaskRule,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "rule", rename_all = "snake_case")]
+pub enum MaskRule {
+    /// Kolom dihapus dari output
+    Hide,
+    /// Hanya `visible` karakter terakhir yang terlihat, contoh `****1234`
+    Partial {
+        #[serde(default = "default_mask_visible")]
+        visible: usize,
+    },
+    /// SHA-256 (dengan secret) supaya nilai masih bisa dibandingkan tanpa terlihat
+    Hash,
+    /// Angka dibulatkan ke bawah ke kelipatan `size`
+    Bucket { size: f64 },
+}
+
+fn default_mask_visible() -> usize {
+    4
+}
+
+pub static APP_CONFIG: Lazy<AppConfig> = Lazy::new(AppConfig::load);
+
+impl AppConfig {
+    /// File yang ada tapi tidak valid menghentikan startup: jatuh ke default berarti
+    /// row filter dan column mask diam-diam tidak aktif.
+    fn load() -> Self {
+        let path = env::var("APP_CONFIG_PATH").unwrap_or_else(|_| "config.json".to_string());
+
+        let content = match fs::read_to_string(&path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == ErrorKind::NotFound => {
+                write_log("WARN", &format!("Config file '{}' not found. Using defaults, row filters and column masks are disabled", path));
+                println!("⚠️ Config file '{}' not found, row filters and column masks are disabled", path);
+                return AppConfig::default();
+            }
+            Err(e) => Self::abort(&format!("Failed to read config file '{}': {}", path, e)),
+        };
+
+        match serde_json::from_str::<AppConfig>(&content) {
+            Ok(config) => config,
+            Err(e) => Self::abort(&format!("Invalid config file '{}': {}", path, e)),
+        }
+    }
+
+    fn abort(message: &str) -> ! {
+        write_log("ERROR", message);
+        panic!("{}", message);
+    }
+
+    /// Ambil konfigurasi view, default jika view tidak terdaftar
+    pub fn view(&self, tablename: &str) -> ViewConfig {
+        self.views.iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case(tablename))
+            .map(|(_, config)| config.clone())
+            .unwrap_or_default()
+    }
+}
@@ -1,10 +1,102 @@
-use bb8::{Pool, PooledConnection};
-use bb8_tiberius::ConnectionManager;
+use bb8::{ManageConnection, Pool, PooledConnection};
+use bb8_tiberius::rt::Client;
 use tiberius::Config;
 use tokio::sync::{Mutex, MutexGuard};
-use std::{env, sync::Arc};
+use std::{env, ops::{Deref, DerefMut}, sync::Arc, time::Duration};
+
+use super::{error::AppError, logger::write_log};
 
 pub type DbPool = Pool<ConnectionManager>;
+
+/// Jumlah maksimum koneksi di pool
+pub const POOL_MAX_SIZE: u32 = 10;
+
+/// Koneksi tiberius di dalam pool.
+/// Selama `in_flight` bernilai true koneksi sedang menjalankan query yang bisa dibatalkan.
+/// Jika future-nya di-drop (timeout / client disconnect) sebelum `mark_done`, pool menganggap
+/// koneksi rusak dan menutupnya, sehingga SQL Server ikut membatalkan query di session tersebut.
+/// Tiberius belum punya API attention/cancel, jadi menutup koneksi adalah cara pembatalannya.
+pub struct DbConnection {
+    client: Client,
+    in_flight: bool,
+}
+
+impl DbConnection {
+    pub fn mark_in_flight(&mut self) {
+        self.in_flight = true;
+    }
+
+    pub fn mark_done(&mut self) {
+        self.in_flight = false;
+    }
+}
+
+impl Deref for DbConnection {
+    type Target = Client;
+
+    fn deref(&self) -> &Self::Target {
+        &self.client
+    }
+}
+
+impl DerefMut for DbConnection {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.client
+    }
+}
+
+/// Wrapper `bb8_tiberius::ConnectionManager` yang membuang koneksi dengan query yang belum selesai
+pub struct ConnectionManager {
+    inner: bb8_tiberius::ConnectionManager,
+}
+
+impl ConnectionManager {
+    pub fn new(config: Config) -> Self {
+        Self { inner: bb8_tiberius::ConnectionManager::new(config) }
+    }
+}
+
+impl ManageConnection for ConnectionManager {
+    type Connection = DbConnection;
+    type Error = bb8_tiberius::Error;
+
+    async fn connect(&self) -> Result<Self::Connection, Self::Error> {
+        let client = self.inner.connect().await?;
+        Ok(DbConnection { client, in_flight: false })
+    }
+
+    async fn is_valid(&self, conn: &mut Self::Connection) -> Result<(), Self::Error> {
+        self.inner.is_valid(&mut conn.client).await
+    }
+
+    fn has_broken(&self, conn: &mut Self::Connection) -> bool {
+        if conn.in_flight {
+            write_log("WARN", "Closing database connection with an unfinished query (timeout or client disconnect)");
+        }
+        conn.in_flight
+    }
+}
+
+/// Batas waktu query per endpoint dari `QUERY_TIMEOUT_<ENDPOINT>_SECS`,
+/// fallback ke `QUERY_TIMEOUT_SECS` (default 30 detik)
+pub fn query_timeout(endpoint: &str) -> Duration {
+    let secs: u64 = env::var(format!("QUERY_TIMEOUT_{}_SECS", endpoint))
+        .or_else(|_| env::var("QUERY_TIMEOUT_SECS"))
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(30);
+
+    Duration::from_secs(secs)
+}
+
+/// Error 504 untuk query yang dibatalkan karena melewati batas waktu
+pub fn query_cancelled(timeout: Duration) -> AppError {
+    AppError::Timeout(format!(
+        "The query was cancelled because it did not finish within {} seconds. Narrow the filter and try again",
+        timeout.as_secs()
+    ))
+}
+
 pub struct Transaction<'a> {
     pub conn: Arc<Mutex<Option<PooledConnection<'a, ConnectionManager>>>>, // 🔥 Pakai lifetime 'a
     committed: bool,
@@ -36,8 +128,9 @@ impl<'a> Drop for Transaction<'a> {
         if !self.committed {
             if let Ok(mut conn_guard) = self.conn.try_lock() {
                 if let Some(mut conn) = conn_guard.take() {
-                    // Eksekusi rollback langsung tanpa async
-                    let _ = conn.simple_query("ROLLBACK");
+                    // Tidak bisa await ROLLBACK di Drop, jadi koneksi ditutup oleh pool
+                    // dan SQL Server me-rollback transaksi yang masih terbuka
+                    conn.mark_in_flight();
                 }
             }
         }
@@ -46,9 +139,9 @@ impl<'a> Drop for Transaction<'a> {
 
 /// Membuat pool koneksi database
 pub async fn create_pool(database: &str) -> Result<DbPool, Box<dyn std::error::Error + Send + Sync>> {
-    let database_url: String = env::var("DATABASE_URL").expect("DATABASE_URL harus diatur");
-    let database_user: String = env::var("DATABASE_USER").expect("DATABASE_USER harus diatur");
-    let database_password: String = env::var("DATABASE_PASSWORD").expect("DATABASE_PASSWORD harus diatur");
+    let database_url: String = env::var("DATABASE_URL").map_err(|_| "DATABASE_URL harus diatur")?;
+    let database_user: String = env::var("DATABASE_USER").map_err(|_| "DATABASE_USER harus diatur")?;
+    let database_password: String = env::var("DATABASE_PASSWORD").map_err(|_| "DATABASE_PASSWORD harus diatur")?;
 
     let connection_string = format!(
         "Server={};User={};Password={};TrustServerCertificate=true;Database={}",
@@ -58,12 +151,48 @@ pub async fn create_pool(database: &str) -> Result<DbPool, Box<dyn std::error::E
     let config: Config = Config::from_ado_string(&connection_string)?;
     let manager: ConnectionManager = ConnectionManager::new(config);
     let pool: Pool<ConnectionManager> = Pool::builder()
-            .max_size(10)
+            .max_size(POOL_MAX_SIZE)
             .connection_timeout(std::time::Duration::from_secs(30))
             .idle_timeout(std::time::Duration::from_secs(60))
             .max_lifetime(std::time::Duration::from_secs(300))
-            .max_size(10)
             .build(manager).await?;
 
     Ok(pool)
-}
\ No newline at end of file
+}
+
+/// Jalankan `SELECT 1` lewat pool dengan batas waktu
+pub async fn ping_database(pool: &DbPool, timeout: Duration) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let ping = async {
+        let mut conn = pool.get().await?;
+        conn.mark_in_flight();
+        conn.simple_query("SELECT 1").await?.into_row().await?;
+        conn.mark_done();
+        Ok::<(), Box<dyn std::error::Error + Send + Sync>>(())
+    };
+
+    tokio::time::timeout(timeout, ping)
+        .await
+        .map_err(|_| format!("Database did not respond within {} ms", timeout.as_millis()))?
+}
+
+/// Tunggu database siap saat startup dengan exponential backoff.
+/// Jika percobaan habis, aplikasi tetap jalan dan `/health/ready` akan melaporkan database down.
+pub async fn wait_for_database(pool: &DbPool) -> bool {
+    let max_retries: u32 = env::var("DB_STARTUP_RETRIES").ok().and_then(|v| v.parse().ok()).unwrap_or(5);
+    let mut delay = Duration::from_secs(1);
+
+    for attempt in 1..=max_retries.max(1) {
+        match ping_database(pool, Duration::from_secs(10)).await {
+            Ok(_) => return true,
+            Err(e) => {
+                write_log("WARN", &format!("Database not ready (attempt {}/{}): {}", attempt, max_retries, e));
+                if attempt < max_retries {
+                    tokio::time::sleep(delay).await;
+                    delay = (delay * 2).min(Duration::from_secs(30));
+                }
+            }
+        }
+    }
+
+    false
+}
@@ -0,0 +1,166 @@
+use std::{collections::HashMap, fmt};
+
+use actix_web::{http::{header, StatusCode}, HttpResponse, ResponseError};
+use bb8::RunError;
+use serde_json::{json, Value as JsonValue};
+use validator::ValidationErrors;
+
+use crate::services::validation_service::validator::format_validation_errors;
+
+use super::{logger::write_log, model::ActionResult};
+
+/// Error aplikasi yang dipakai oleh semua service dan handler.
+/// Detail dari `Database` dan `Internal` hanya ditulis ke log, tidak dikirim ke client.
+#[derive(Debug)]
+#[allow(dead_code)]
+pub enum AppError {
+    Validation(HashMap<String, String>),
+    BadRequest(String),
+    NotFound(String),
+    Unauthorized(String),
+    Forbidden(String),
+    Conflict(String),
+    /// Detail dan detik yang dikirim di header `Retry-After`
+    TooManyRequests(String, u64),
+    Timeout(String),
+    Unavailable(String),
+    Database(String),
+    Internal(String),
+}
+
+impl AppError {
+    /// Kode error internal untuk dicocokkan di log / monitoring
+    pub fn code(&self) -> &'static str {
+        match self {
+            AppError::Validation(_) => "VALIDATION_ERROR",
+            AppError::BadRequest(_) => "BAD_REQUEST",
+            AppError::NotFound(_) => "NOT_FOUND",
+            AppError::Unauthorized(_) => "UNAUTHORIZED",
+            AppError::Forbidden(_) => "FORBIDDEN",
+            AppError::Conflict(_) => "CONFLICT",
+            AppError::TooManyRequests(..) => "TOO_MANY_REQUESTS",
+            AppError::Timeout(_) => "TIMEOUT",
+            AppError::Unavailable(_) => "SERVICE_UNAVAILABLE",
+            AppError::Database(_) => "DATABASE_ERROR",
+            AppError::Internal(_) => "INTERNAL_ERROR",
+        }
+    }
+
+    /// Pesan singkat yang aman untuk ditampilkan ke client
+    pub fn message(&self) -> &'static str {
+        match self {
+            AppError::Validation(_) => "Validation failed",
+            AppError::BadRequest(_) => "Bad Request",
+            AppError::NotFound(_) => "Not Found",
+            AppError::Unauthorized(_) => "Unauthorized",
+            AppError::Forbidden(_) => "Forbidden",
+            AppError::Conflict(_) => "Conflict",
+            AppError::TooManyRequests(..) => "Too Many Requests",
+            AppError::Timeout(_) => "Query Timeout",
+            AppError::Unavailable(_) => "Service Unavailable",
+            AppError::Database(_) => "Database Error",
+            AppError::Internal(_) => "Internal Server Error",
+        }
+    }
+
+    /// Detail error untuk field `error` di `ActionResult`
+    fn client_error(&self) -> JsonValue {
+        match self {
+            AppError::Validation(errors) => json!(errors),
+            AppError::BadRequest(detail)
+            | AppError::NotFound(detail)
+            | AppError::Unauthorized(detail)
+            | AppError::Forbidden(detail)
+            | AppError::Conflict(detail)
+            | AppError::TooManyRequests(detail, _)
+            | AppError::Timeout(detail)
+            | AppError::Unavailable(detail) => json!(detail),
+            AppError::Database(_) | AppError::Internal(_) => {
+                json!("An unexpected error occurred. Please contact support with the request ID")
+            }
+        }
+    }
+}
+
+impl fmt::Display for AppError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AppError::Validation(errors) => write!(f, "{}: {:?}", self.message(), errors),
+            AppError::BadRequest(detail)
+            | AppError::NotFound(detail)
+            | AppError::Unauthorized(detail)
+            | AppError::Forbidden(detail)
+            | AppError::Conflict(detail)
+            | AppError::TooManyRequests(detail, _)
+            | AppError::Timeout(detail)
+            | AppError::Unavailable(detail)
+            | AppError::Database(detail)
+            | AppError::Internal(detail) => write!(f, "{}: {}", self.message(), detail),
+        }
+    }
+}
+
+impl std::error::Error for AppError {}
+
+impl ResponseError for AppError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            AppError::Validation(_) | AppError::BadRequest(_) => StatusCode::BAD_REQUEST,
+            AppError::NotFound(_) => StatusCode::NOT_FOUND,
+            AppError::Unauthorized(_) => StatusCode::UNAUTHORIZED,
+            AppError::Forbidden(_) => StatusCode::FORBIDDEN,
+            AppError::Conflict(_) => StatusCode::CONFLICT,
+            AppError::TooManyRequests(..) => StatusCode::TOO_MANY_REQUESTS,
+            AppError::Timeout(_) => StatusCode::GATEWAY_TIMEOUT,
+            AppError::Unavailable(_) => StatusCode::SERVICE_UNAVAILABLE,
+            AppError::Database(_) | AppError::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        let level = if self.status_code().is_server_error() { "ERROR" } else { "WARN" };
+        write_log(level, &format!("[{}] {}", self.code(), self));
+
+        let result: ActionResult<(), JsonValue> = ActionResult {
+            result: false,
+            message: self.message().to_string(),
+            data: None,
+            error: Some(self.client_error()),
+            error_code: Some(self.code().to_string()),
+        };
+
+        let mut response = HttpResponse::build(self.status_code());
+        if let AppError::TooManyRequests(_, retry_after_secs) = self {
+            response.insert_header((header::RETRY_AFTER, retry_after_secs.to_string()));
+        }
+
+        response.json(result)
+    }
+}
+
+impl From<ValidationErrors> for AppError {
+    fn from(errors: ValidationErrors) -> Self {
+        AppError::Validation(format_validation_errors(&errors))
+    }
+}
+
+impl From<tiberius::error::Error> for AppError {
+    fn from(err: tiberius::error::Error) -> Self {
+        AppError::Database(err.to_string())
+    }
+}
+
+impl From<RunError<bb8_tiberius::Error>> for AppError {
+    fn from(err: RunError<bb8_tiberius::Error>) -> Self {
+        match err {
+            RunError::TimedOut => AppError::Unavailable("Timed out waiting for a database connection".to_string()),
+            RunError::User(e) => AppError::Database(format!("Database connection failed: {}", e)),
+        }
+    }
+}
+
+impl From<Box<dyn std::error::Error + Send + Sync>> for AppError {
+    fn from(err: Box<dyn std::error::Error + Send + Sync>) -> Self {
+        AppError::Internal(err.to_string())
+    }
+}
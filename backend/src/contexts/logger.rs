@@ -3,6 +3,8 @@ use std::fs::OpenOptions;
 use std::io::Write;
 use chrono::Local;
 
+use super::request_id::current_request_id;
+
 /// 📂 Inisialisasi folder dan file log berdasarkan konfigurasi `.env`
 // pub fn init_log() -> std::io::Result<String> {
 
@@ -33,7 +35,11 @@ pub fn write_log(level: &str, message: &str) {
     let log_file = format!("{}/log-{}.txt", log_dir, date); // testing untuk file rs sebagai log
 
     if let Ok(mut file) = OpenOptions::new().create(true).append(true).open(&log_file) {
-        let log_msg = format!("[{}] [{}] {}\n", Local::now().format("%Y-%m-%d %H:%M:%S"), level, message);
+        // Sertakan request ID agar log service bisa dicocokkan dengan access log
+        let log_msg = match current_request_id() {
+            Some(request_id) => format!("[{}] [{}] [{}] {}\n", Local::now().format("%Y-%m-%d %H:%M:%S"), level, request_id, message),
+            None => format!("[{}] [{}] {}\n", Local::now().format("%Y-%m-%d %H:%M:%S"), level, message),
+        };
         let _ = file.write_all(log_msg.as_bytes());
     }
 }
\ No newline at end of file
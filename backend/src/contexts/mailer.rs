@@ -0,0 +1,293 @@
+use std::{env, fs, path::PathBuf, sync::Arc, time::Duration};
+
+use async_native_tls::TlsConnector;
+use base64::{engine::general_purpose, Engine as _};
+use futures::{future::BoxFuture, io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader}, FutureExt};
+use handlebars::Handlebars;
+use once_cell::sync::Lazy;
+use serde_json::Value as JsonValue;
+use tokio::net::TcpStream;
+use tokio_util::compat::TokioAsyncReadCompatExt;
+
+use super::{error::AppError, logger::write_log};
+
+/// Template email (handlebars) yang ikut di-compile ke binary
+static TEMPLATES: Lazy<Handlebars<'static>> = Lazy::new(|| {
+    let mut handlebars = Handlebars::new();
+    handlebars.set_strict_mode(true);
+
+    let templates = [
+        ("password_reset", include_str!("../../templates/email/password_reset.hbs")),
+        ("verify_email", include_str!("../../templates/email/verify_email.hbs")),
+    ];
+    for (name, source) in templates {
+        if let Err(e) = handlebars.register_template_string(name, source) {
+            write_log("ERROR", &format!("Invalid email template '{}': {}", name, e));
+        }
+    }
+
+    handlebars
+});
+
+/// Email HTML yang siap dikirim
+#[derive(Debug, Clone)]
+pub struct EmailMessage {
+    pub to: String,
+    pub subject: String,
+    pub html_body: String,
+}
+
+impl EmailMessage {
+    /// Render template `templates/email/{template}.hbs` dengan `data`
+    pub fn from_template(to: &str, subject: &str, template: &str, data: &JsonValue) -> Result<Self, AppError> {
+        let html_body = TEMPLATES.render(template, data)
+            .map_err(|e| AppError::Internal(format!("Failed to render email template '{}': {}", template, e)))?;
+
+        Ok(Self { to: to.to_string(), subject: subject.to_string(), html_body })
+    }
+
+    /// Pesan MIME lengkap (header + body base64), dipakai SMTP dan file `.eml`
+    fn to_mime(&self, from: &str) -> String {
+        let body = general_purpose::STANDARD.encode(self.html_body.as_bytes());
+        let body_lines: Vec<&str> = body.as_bytes().chunks(76)
+            .map(|chunk| std::str::from_utf8(chunk).unwrap_or_default())
+            .collect();
+        let domain = from.rsplit('@').next().unwrap_or("localhost").trim_end_matches('>');
+
+        format!(
+            "From: {}\r\nTo: {}\r\nSubject: =?UTF-8?B?{}?=\r\nDate: {}\r\nMessage-ID: <{}@{}>\r\nMIME-Version: 1.0\r\nContent-Type: text/html; charset=utf-8\r\nContent-Transfer-Encoding: base64\r\n\r\n{}\r\n",
+            from,
+            self.to,
+            general_purpose::STANDARD.encode(self.subject.as_bytes()),
+            chrono::Utc::now().to_rfc2822(),
+            uuid::Uuid::new_v4(),
+            domain,
+            body_lines.join("\r\n")
+        )
+    }
+}
+
+/// Pengirim email. Implementasi dipilih dengan `MAILER` (`smtp` atau `log`), lihat `mailer_from_env`
+pub trait Mailer: Send + Sync {
+    fn send<'a>(&'a self, message: &'a EmailMessage) -> BoxFuture<'a, Result<(), AppError>>;
+}
+
+/// Mailer dari environment. `MAILER` sebaiknya diatur eksplisit; jika kosong / tidak dikenal
+/// dipakai `log` dengan peringatan, karena email (termasuk link reset password) tidak terkirim.
+pub fn mailer_from_env() -> Arc<dyn Mailer> {
+    let mailer = env::var("MAILER").unwrap_or_default().to_lowercase();
+
+    match mailer.as_str() {
+        "smtp" => Arc::new(SmtpMailer::from_env()),
+        "log" => Arc::new(LogMailer::from_env()),
+        _ => {
+            let message = format!("MAILER is '{}', expected 'smtp' or 'log'. Emails will NOT be sent, only logged without body", mailer);
+            write_log("WARN", &message);
+            println!("⚠️ {}", message);
+            Arc::new(LogMailer::from_env())
+        }
+    }
+}
+
+fn mail_from() -> String {
+    env::var("MAIL_FROM").unwrap_or_else(|_| "UBS Trade Dashboard <no-reply@localhost>".to_string())
+}
+
+/// Mode koneksi SMTP dari `SMTP_SECURITY`: `starttls` (default, port 587), `tls` (port 465) atau `none`
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum SmtpSecurity {
+    StartTls,
+    Tls,
+    None,
+}
+
+pub struct SmtpMailer {
+    host: String,
+    port: u16,
+    security: SmtpSecurity,
+    username: Option<String>,
+    password: Option<String>,
+    from: String,
+    timeout: Duration,
+}
+
+impl SmtpMailer {
+    pub fn from_env() -> Self {
+        let security = match env::var("SMTP_SECURITY").unwrap_or_default().to_lowercase().as_str() {
+            "tls" => SmtpSecurity::Tls,
+            "none" => SmtpSecurity::None,
+            _ => SmtpSecurity::StartTls,
+        };
+        let default_port = match security {
+            SmtpSecurity::Tls => 465,
+            SmtpSecurity::StartTls => 587,
+            SmtpSecurity::None => 25,
+        };
+
+        Self {
+            host: env::var("SMTP_HOST").unwrap_or_else(|_| "localhost".to_string()),
+            port: env::var("SMTP_PORT").ok().and_then(|v| v.parse().ok()).unwrap_or(default_port),
+            security,
+            username: env::var("SMTP_USERNAME").ok().filter(|v| !v.is_empty()),
+            password: env::var("SMTP_PASSWORD").ok(),
+            from: mail_from(),
+            timeout: Duration::from_secs(env::var("SMTP_TIMEOUT_SECS").ok().and_then(|v| v.parse().ok()).unwrap_or(30)),
+        }
+    }
+
+    /// Alamat email saja dari `Nama <alamat>` untuk `MAIL FROM`
+    fn envelope_from(&self) -> &str {
+        match (self.from.find('<'), self.from.rfind('>')) {
+            (Some(start), Some(end)) if start < end => &self.from[start + 1..end],
+            _ => self.from.as_str(),
+        }
+    }
+
+    async fn deliver(&self, message: &EmailMessage) -> Result<(), AppError> {
+        let tcp = TcpStream::connect((self.host.as_str(), self.port)).await
+            .map_err(|e| AppError::Unavailable(format!("SMTP connect to {}:{} failed: {}", self.host, self.port, e)))?
+            .compat();
+
+        match self.security {
+            SmtpSecurity::Tls => {
+                let tls = self.tls_connect(tcp).await?;
+                let mut session = SmtpSession::new(tls);
+                session.expect(220).await?;
+                session.ehlo().await?;
+                self.transaction(&mut session, message).await
+            }
+            SmtpSecurity::StartTls => {
+                let mut session = SmtpSession::new(tcp);
+                session.expect(220).await?;
+                session.ehlo().await?;
+                session.command("STARTTLS", 220).await?;
+
+                let tls = self.tls_connect(session.into_inner()).await?;
+                let mut session = SmtpSession::new(tls);
+                session.ehlo().await?;
+                self.transaction(&mut session, message).await
+            }
+            SmtpSecurity::None => {
+                let mut session = SmtpSession::new(tcp);
+                session.expect(220).await?;
+                session.ehlo().await?;
+                self.transaction(&mut session, message).await
+            }
+        }
+    }
+
+    async fn tls_connect<S: AsyncRead + AsyncWrite + Unpin>(&self, stream: S) -> Result<async_native_tls::TlsStream<S>, AppError> {
+        TlsConnector::new().connect(self.host.as_str(), stream).await
+            .map_err(|e| AppError::Unavailable(format!("SMTP TLS handshake failed: {}", e)))
+    }
+
+    async fn transaction<S: AsyncRead + AsyncWrite + Unpin>(&self, session: &mut SmtpSession<S>, message: &EmailMessage) -> Result<(), AppError> {
+        if let Some(username) = &self.username {
+            let credentials = format!("\0{}\0{}", username, self.password.as_deref().unwrap_or_default());
+            session.command(&format!("AUTH PLAIN {}", general_purpose::STANDARD.encode(credentials)), 235).await?;
+        }
+
+        session.command(&format!("MAIL FROM:<{}>", self.envelope_from()), 250).await?;
+        session.command(&format!("RCPT TO:<{}>", message.to), 250).await?;
+        session.command("DATA", 354).await?;
+        // Body base64 tidak punya baris yang diawali titik, jadi tidak perlu dot-stuffing
+        session.command(&format!("{}.", message.to_mime(&self.from)), 250).await?;
+        session.command("QUIT", 221).await
+    }
+}
+
+impl Mailer for SmtpMailer {
+    fn send<'a>(&'a self, message: &'a EmailMessage) -> BoxFuture<'a, Result<(), AppError>> {
+        async move {
+            tokio::time::timeout(self.timeout, self.deliver(message)).await
+                .map_err(|_| AppError::Timeout(format!("Sending email to {} timed out", message.to)))?
+        }.boxed()
+    }
+}
+
+/// Percakapan SMTP di atas satu stream (plain atau TLS)
+struct SmtpSession<S> {
+    stream: BufReader<S>,
+}
+
+impl<S: AsyncRead + AsyncWrite + Unpin> SmtpSession<S> {
+    fn new(stream: S) -> Self {
+        Self { stream: BufReader::new(stream) }
+    }
+
+    fn into_inner(self) -> S {
+        self.stream.into_inner()
+    }
+
+    /// Baca balasan server (bisa multi-baris `250-...`) dan cocokkan kodenya
+    async fn expect(&mut self, code: u16) -> Result<(), AppError> {
+        let mut reply = String::new();
+        loop {
+            let mut line = String::new();
+            let read = self.stream.read_line(&mut line).await
+                .map_err(|e| AppError::Unavailable(format!("SMTP read failed: {}", e)))?;
+            if read == 0 {
+                return Err(AppError::Unavailable("SMTP connection closed".to_string()));
+            }
+
+            reply.push_str(&line);
+            if line.as_bytes().get(3) != Some(&b'-') {
+                break;
+            }
+        }
+
+        match reply.get(..3).and_then(|c| c.parse::<u16>().ok()) {
+            Some(actual) if actual == code => Ok(()),
+            _ => Err(AppError::Unavailable(format!("Unexpected SMTP reply: {}", reply.trim()))),
+        }
+    }
+
+    async fn command(&mut self, line: &str, code: u16) -> Result<(), AppError> {
+        let write_failed = |e: std::io::Error| AppError::Unavailable(format!("SMTP write failed: {}", e));
+        let writer = self.stream.get_mut();
+        writer.write_all(format!("{}\r\n", line).as_bytes()).await.map_err(write_failed)?;
+        writer.flush().await.map_err(write_failed)?;
+
+        self.expect(code).await
+    }
+
+    async fn ehlo(&mut self) -> Result<(), AppError> {
+        let hostname = env::var("SMTP_HELO_NAME").unwrap_or_else(|_| "localhost".to_string());
+        self.command(&format!("EHLO {}", hostname), 250).await
+    }
+}
+
+/// Mailer untuk development: penerima & subject ditulis ke log, isi email hanya ke `MAIL_OUTPUT_DIR/*.eml`
+/// jika diatur. Body tidak pernah masuk log karena bisa berisi link reset password yang masih aktif.
+pub struct LogMailer {
+    output_dir: Option<PathBuf>,
+    from: String,
+}
+
+impl LogMailer {
+    pub fn from_env() -> Self {
+        Self {
+            output_dir: env::var("MAIL_OUTPUT_DIR").ok().filter(|v| !v.is_empty()).map(PathBuf::from),
+            from: mail_from(),
+        }
+    }
+}
+
+impl Mailer for LogMailer {
+    fn send<'a>(&'a self, message: &'a EmailMessage) -> BoxFuture<'a, Result<(), AppError>> {
+        async move {
+            let Some(dir) = &self.output_dir else {
+                write_log("INFO", &format!("[mail] To: {} Subject: {} (body not logged, set MAIL_OUTPUT_DIR to keep it)", message.to, message.subject));
+                return Ok(());
+            };
+
+            let path = dir.join(format!("{}-{}.eml", chrono::Local::now().format("%Y%m%d%H%M%S"), uuid::Uuid::new_v4()));
+            fs::create_dir_all(dir)
+                .and_then(|_| fs::write(&path, message.to_mime(&self.from)))
+                .map_err(|e| AppError::Internal(format!("Failed to write email to {}: {}", path.display(), e)))?;
+
+            write_log("INFO", &format!("[mail] To: {} Subject: {} saved to {}", message.to, message.subject, path.display()));
+            Ok(())
+        }.boxed()
+    }
+}
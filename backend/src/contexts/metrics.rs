@@ -0,0 +1,169 @@
+use std::time::{Duration, Instant};
+
+use actix_web::{body::{self, BoxBody, MessageBody}, dev::{ServiceRequest, ServiceResponse}, http::header, middleware::Next};
+use bb8::Pool;
+use once_cell::sync::Lazy;
+use prometheus::{Encoder, HistogramOpts, HistogramVec, IntCounterVec, IntGauge, Opts, Registry, TextEncoder};
+use serde_json::Value as JsonValue;
+
+use super::connection::{ConnectionManager, POOL_MAX_SIZE};
+
+pub struct Metrics {
+    registry: Registry,
+    http_requests_total: IntCounterVec,
+    http_request_duration_seconds: HistogramVec,
+    db_query_duration_seconds: HistogramVec,
+    errors_total: IntCounterVec,
+    cache_requests_total: IntCounterVec,
+    db_pool_connections: IntGauge,
+    db_pool_idle_connections: IntGauge,
+    db_pool_max_size: IntGauge,
+    db_pool_get_waited_total: IntGauge,
+    db_pool_get_timed_out_total: IntGauge,
+    db_pool_get_wait_seconds_total: IntGauge,
+}
+
+/// Registry global untuk semua metric aplikasi
+pub static METRICS: Lazy<Metrics> = Lazy::new(Metrics::new);
+
+impl Metrics {
+    fn new() -> Self {
+        let registry = Registry::new_custom(Some("ubs".to_string()), None).unwrap();
+
+        let http_requests_total = IntCounterVec::new(
+            Opts::new("http_requests_total", "Total HTTP requests per route"),
+            &["method", "route", "status"],
+        ).unwrap();
+
+        let http_request_duration_seconds = HistogramVec::new(
+            HistogramOpts::new("http_request_duration_seconds", "HTTP request latency per route"),
+            &["method", "route"],
+        ).unwrap();
+
+        let db_query_duration_seconds = HistogramVec::new(
+            HistogramOpts::new("db_query_duration_seconds", "SQL query duration per operation and table")
+                .buckets(vec![0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0, 60.0]),
+            &["operation", "table"],
+        ).unwrap();
+
+        let errors_total = IntCounterVec::new(
+            Opts::new("errors_total", "Error responses grouped by ActionResult message"),
+            &["status", "message"],
+        ).unwrap();
+
+        let cache_requests_total = IntCounterVec::new(
+            Opts::new("cache_requests_total", "Query cache lookups per aggregation and outcome"),
+            &["aggregation", "outcome"],
+        ).unwrap();
+
+        let db_pool_connections = IntGauge::new("db_pool_connections", "Connections currently managed by the pool").unwrap();
+        let db_pool_idle_connections = IntGauge::new("db_pool_idle_connections", "Idle connections in the pool").unwrap();
+        let db_pool_max_size = IntGauge::new("db_pool_max_size", "Maximum pool size").unwrap();
+        let db_pool_get_waited_total = IntGauge::new("db_pool_get_waited_total", "Connection checkouts that had to wait").unwrap();
+        let db_pool_get_timed_out_total = IntGauge::new("db_pool_get_timed_out_total", "Connection checkouts that timed out").unwrap();
+        let db_pool_get_wait_seconds_total = IntGauge::new("db_pool_get_wait_seconds_total", "Total seconds spent waiting for a connection").unwrap();
+
+        registry.register(Box::new(http_requests_total.clone())).unwrap();
+        registry.register(Box::new(http_request_duration_seconds.clone())).unwrap();
+        registry.register(Box::new(db_query_duration_seconds.clone())).unwrap();
+        registry.register(Box::new(errors_total.clone())).unwrap();
+        registry.register(Box::new(cache_requests_total.clone())).unwrap();
+        registry.register(Box::new(db_pool_connections.clone())).unwrap();
+        registry.register(Box::new(db_pool_idle_connections.clone())).unwrap();
+        registry.register(Box::new(db_pool_max_size.clone())).unwrap();
+        registry.register(Box::new(db_pool_get_waited_total.clone())).unwrap();
+        registry.register(Box::new(db_pool_get_timed_out_total.clone())).unwrap();
+        registry.register(Box::new(db_pool_get_wait_seconds_total.clone())).unwrap();
+
+        Self {
+            registry,
+            http_requests_total,
+            http_request_duration_seconds,
+            db_query_duration_seconds,
+            errors_total,
+            cache_requests_total,
+            db_pool_connections,
+            db_pool_idle_connections,
+            db_pool_max_size,
+            db_pool_get_waited_total,
+            db_pool_get_timed_out_total,
+            db_pool_get_wait_seconds_total,
+        }
+    }
+
+    /// Catat durasi query SQL, dipanggil setelah query berhasil agar label table tetap terbatas
+    pub fn observe_query(&self, operation: &str, table: &str, elapsed: Duration) {
+        self.db_query_duration_seconds
+            .with_label_values(&[operation, table])
+            .observe(elapsed.as_secs_f64());
+    }
+
+    /// Catat cache hit / miss, label hanya jenis agregasi (tanpa nama kolom)
+    pub fn observe_cache(&self, aggregation: &str, hit: bool) {
+        let kind = aggregation.split(':').next().unwrap_or(aggregation);
+        self.cache_requests_total
+            .with_label_values(&[kind, if hit { "hit" } else { "miss" }])
+            .inc();
+    }
+
+    /// Render semua metric ke format text Prometheus
+    pub fn render(&self, pool: &Pool<ConnectionManager>) -> String {
+        let state = pool.state();
+        self.db_pool_connections.set(state.connections as i64);
+        self.db_pool_idle_connections.set(state.idle_connections as i64);
+        self.db_pool_max_size.set(POOL_MAX_SIZE as i64);
+        self.db_pool_get_waited_total.set(state.statistics.get_waited as i64);
+        self.db_pool_get_timed_out_total.set(state.statistics.get_timed_out as i64);
+        self.db_pool_get_wait_seconds_total.set(state.statistics.get_wait_time.as_secs() as i64);
+
+        let mut buffer = Vec::new();
+        let _ = TextEncoder::new().encode(&self.registry.gather(), &mut buffer);
+        String::from_utf8(buffer).unwrap_or_default()
+    }
+}
+
+/// Middleware: hitung jumlah request, latency per route dan error per `ActionResult.message`
+pub async fn metrics_middleware(req: ServiceRequest, next: Next<impl MessageBody + 'static>) -> Result<ServiceResponse<BoxBody>, actix_web::Error> {
+    let started = Instant::now();
+    let method = req.method().to_string();
+
+    let res = next.call(req).await?;
+
+    // Pakai pattern route (bukan path asli) supaya label tidak meledak
+    let route = res.request().match_pattern().unwrap_or_else(|| "unmatched".to_string());
+    let status = res.status();
+
+    METRICS.http_requests_total
+        .with_label_values(&[&method, &route, status.as_str()])
+        .inc();
+    METRICS.http_request_duration_seconds
+        .with_label_values(&[&method, &route])
+        .observe(started.elapsed().as_secs_f64());
+
+    if !(status.is_client_error() || status.is_server_error()) {
+        return Ok(res.map_into_boxed_body());
+    }
+
+    let is_json = res.headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.starts_with("application/json"));
+
+    if !is_json {
+        METRICS.errors_total.with_label_values(&[status.as_str(), "unknown"]).inc();
+        return Ok(res.map_into_boxed_body());
+    }
+
+    let (req, res) = res.into_parts();
+    let (res, body) = res.into_parts();
+    let bytes = body::to_bytes(body).await.unwrap_or_default();
+
+    let message = serde_json::from_slice::<JsonValue>(&bytes)
+        .ok()
+        .and_then(|json| json.get("message").and_then(|m| m.as_str()).map(|m| m.to_string()))
+        .unwrap_or_else(|| "unknown".to_string());
+
+    METRICS.errors_total.with_label_values(&[status.as_str(), &message]).inc();
+
+    Ok(ServiceResponse::new(req, res.set_body(BoxBody::new(bytes))))
+}
@@ -1,8 +1,10 @@
+use std::collections::HashMap;
+
 use chrono::{DateTime, NaiveDate, TimeZone, Utc};
 use serde::{Deserialize, Deserializer, Serialize};
 use utoipa::{IntoParams, ToSchema};
 use validator::Validate;
-use crate::services::validation_service::validator::{required, valid_password};
+use crate::services::validation_service::validator::{required, valid_name, valid_password, valid_phone_number, validate_base64_image};
 // pub struct DateTimeConverter;
 
 // impl DateTimeConverter {
@@ -20,6 +22,8 @@ pub struct ActionResult<T, E> {
     pub data: Option<T>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub error: Option<E>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error_code: Option<String>,
 }
 
 // Implementasi Default
@@ -30,6 +34,7 @@ impl<T, E> Default for ActionResult<T, E> {
             message: String::new(),
             data: None,
             error: None,
+            error_code: None,
         }
     }
 }
@@ -49,6 +54,14 @@ pub struct Claims {
     pub comp_name: Option<String>,
     pub ip_address: Option<String>,
     pub app_name: Option<String>,
+    #[serde(default)]
+    pub company_id: Option<String>,
+    /// Kode cabang yang boleh diakses user
+    #[serde(default)]
+    pub branches: Vec<String>,
+    /// ID sesi (refresh token) yang menerbitkan token ini
+    #[serde(default)]
+    pub sid: Option<String>,
 }
 
 #[derive(Debug, Deserialize, Validate, ToSchema)]
@@ -107,6 +120,7 @@ pub struct Order {
 pub struct TableDataParams {
     pub tablename: String,
     pub limit: i32,
+    #[serde(default)]
     pub offset: i32,
     #[param(required = false)]
     pub filter: Option<String>,
@@ -114,6 +128,41 @@ pub struct TableDataParams {
     pub order: Option<String>,
     pub nidkey: Option<String>,
     // pub nidvalue: Option<String>,
+    /// Mode keyset: kirim `cursor=` (kosong) untuk halaman pertama, lalu `next_cursor` dari response
+    #[param(required = false)]
+    pub cursor: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize, IntoParams)]
+pub struct ExportTableParams {
+    pub tablename: String,
+    #[param(required = false)]
+    pub filter: Option<String>,
+    pub sort: Option<String>,
+    pub order: Option<String>,
+    /// `ndjson` (default, nilai mentah) atau `csv` (diformat sesuai preferensi user)
+    pub format: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize, IntoParams)]
+pub struct DistinctParams {
+    pub tablename: String,
+    pub column: String,
+    #[param(required = false)]
+    pub filter: Option<String>,
+    /// Cari nilai yang mengandung teks ini
+    pub search: Option<String>,
+    /// Jumlah nilai maksimal, default 50 (maks 500)
+    pub limit: Option<i32>,
+}
+
+/// Isi cursor keyset (di-encode base64 sebelum dikirim ke client)
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TableCursor {
+    pub sort: String,
+    pub order: String,
+    pub value: serde_json::Value,
+    pub key: serde_json::Value,
 }
 
 #[derive(Debug)]
@@ -128,6 +177,8 @@ pub struct ResultList {
     pub totalNotFiltered: i32,
     pub total: i32,
     pub rows: Vec<serde_json::Value>, // Pastikan ini bisa dikonversi ke JSON
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub next_cursor: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Validate)]
@@ -148,6 +199,23 @@ pub struct DeleteBarChart {
     pub chart_id: Option<String>,
 }
 
+/// Payload insert / update / delete baris di view yang `editable`
+#[derive(Debug, Serialize, Deserialize, Validate, ToSchema)]
+pub struct RowRequest {
+    #[validate(custom(function = "required"))]
+    pub tablename: Option<String>,
+    /// Nilai primary key (update / delete)
+    #[schema(value_type = Object)]
+    pub key: Option<serde_json::Value>,
+    /// Nilai kolom versi saat baris dibaca (`LastUpdate` / rowversion)
+    #[schema(value_type = Object)]
+    pub version: Option<serde_json::Value>,
+    /// Nilai kolom yang ditulis (insert / update)
+    #[serde(default)]
+    #[schema(value_type = Object)]
+    pub values: serde_json::Map<String, serde_json::Value>,
+}
+
 #[derive(Debug, Clone, Deserialize, IntoParams)]
 pub struct BarChartParams {
     pub tablename: String,
@@ -158,4 +226,266 @@ pub struct BarChartParams {
 #[derive(Debug, Clone, Deserialize, IntoParams)]
 pub struct HeaderParams {
     pub tablename: String,
-}
\ No newline at end of file
+}
+#[derive(Debug, Serialize, ToSchema)]
+pub struct PoolStatus {
+    pub connections: u32,
+    pub idle_connections: u32,
+    pub max_size: u32,
+    pub get_waited: u64,
+    pub get_timed_out: u64,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ReadinessStatus {
+    pub database: String,
+    pub latency_ms: u128,
+    pub pool: PoolStatus,
+}
+
+#[derive(Debug, Clone, Deserialize, IntoParams)]
+pub struct CacheInvalidateParams {
+    pub tablename: String,
+}
+
+#[derive(Debug, Clone, Deserialize, IntoParams)]
+pub struct PermissionInvalidateParams {
+    /// Kosongkan untuk menghapus cache semua user
+    pub user_nid: Option<i32>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct UserAccessInfo {
+    pub auth_usernid: i32,
+    pub email: String,
+    pub roles: Vec<String>,
+    pub permissions: Vec<String>,
+}
+
+/// Sesi login aktif (satu per perangkat / refresh token family)
+#[derive(Debug, Serialize, ToSchema)]
+pub struct SessionInfo {
+    pub session_id: String,
+    pub ip_address: Option<String>,
+    pub app_name: Option<String>,
+    pub user_agent: Option<String>,
+    pub created_at: Option<chrono::NaiveDateTime>,
+    pub last_refresh_at: Option<chrono::NaiveDateTime>,
+    pub expires_at: Option<chrono::NaiveDateTime>,
+    /// Sesi yang dipakai request ini
+    pub current: bool,
+}
+
+#[derive(Debug, Deserialize, Validate, ToSchema)]
+pub struct RevokeSessionRequest {
+    #[validate(custom(function = "required"))]
+    pub session_id: Option<String>,
+}
+
+/// Login yang sudah lolos password dan menunggu kode 2FA di `/auth/2fa/verify`
+#[derive(Debug, Serialize, ToSchema)]
+pub struct TwoFactorPending {
+    pub two_factor_required: bool,
+    /// Sisa waktu (detik) untuk memasukkan kode
+    pub expires_in: i64,
+}
+
+/// Secret TOTP baru, ditampilkan sekali untuk discan aplikasi authenticator
+#[derive(Debug, Serialize, ToSchema)]
+pub struct TotpEnrollment {
+    pub secret: String,
+    pub provisioning_uri: String,
+}
+
+/// Backup code sekali pakai, hanya ditampilkan sekali saat 2FA diaktifkan
+#[derive(Debug, Serialize, ToSchema)]
+pub struct TotpBackupCodes {
+    pub backup_codes: Vec<String>,
+}
+
+#[derive(Debug, Deserialize, Validate, ToSchema)]
+pub struct TwoFactorCodeRequest {
+    /// Kode 6 digit dari aplikasi authenticator, atau backup code
+    #[validate(custom(function = "required"))]
+    pub code: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Validate, ToSchema)]
+pub struct ForgotPasswordRequest {
+    #[validate(required, email(message = "Invalid email format"))]
+    pub email: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Validate, ToSchema)]
+pub struct ResetPasswordRequest {
+    /// Token dari link email reset password
+    #[validate(custom(function = "required"))]
+    pub token: Option<String>,
+
+    #[validate(custom(function = "required"), custom(function = "valid_password"))]
+    pub password: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Validate, ToSchema)]
+pub struct VerifyEmailRequest {
+    /// Token dari link email verifikasi
+    #[validate(custom(function = "required"))]
+    pub token: Option<String>,
+}
+
+/// API key tanpa secret, untuk daftar key milik user
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ApiKeyInfo {
+    pub api_key_nid: i32,
+    pub name: String,
+    /// Ditampilkan sebagai `ubs_<prefix>_...` supaya key bisa dikenali
+    pub prefix: String,
+    pub scopes: Vec<String>,
+    pub created_at: Option<chrono::NaiveDateTime>,
+    pub expires_at: Option<chrono::NaiveDateTime>,
+    pub last_used_at: Option<chrono::NaiveDateTime>,
+    pub last_used_ip: Option<String>,
+    pub revoked_at: Option<chrono::NaiveDateTime>,
+}
+
+/// API key baru, `api_key` hanya dikirim sekali ini
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ApiKeyCreated {
+    pub api_key: String,
+    pub info: ApiKeyInfo,
+}
+
+#[derive(Debug, Deserialize, Validate, ToSchema)]
+pub struct CreateApiKeyRequest {
+    #[validate(custom(function = "required"), length(max = 100))]
+    pub name: Option<String>,
+
+    /// Permission yang boleh dipakai key, mis. `view:CIFLookup`, `export:CIFLookup`
+    #[validate(length(min = 1, message = "At least one scope is required"))]
+    pub scopes: Vec<String>,
+
+    /// Default `API_KEY_DEFAULT_DAYS` (90)
+    #[validate(range(min = 1, max = 3650))]
+    pub expires_in_days: Option<i32>,
+}
+
+#[derive(Debug, Deserialize, Validate, ToSchema)]
+pub struct RevokeApiKeyRequest {
+    #[validate(required)]
+    pub api_key_nid: Option<i32>,
+}
+
+/// URL thumbnail avatar (WebP persegi) yang di-serve dari `/avatars`
+#[derive(Debug, Serialize, ToSchema)]
+pub struct AvatarUrls {
+    /// 64x64
+    pub small: String,
+    /// 128x128
+    pub medium: String,
+    /// 256x256, sama dengan `picture`
+    pub large: String,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ProfileInfo {
+    pub auth_usernid: i32,
+    pub email: String,
+    pub full_name: Option<String>,
+    pub mobile_phone: Option<String>,
+    pub email_verified: bool,
+    pub picture: Option<String>,
+    /// Kosong jika user belum upload avatar
+    pub avatar: Option<AvatarUrls>,
+    pub comp_name: Option<String>,
+    pub register_date: Option<chrono::NaiveDateTime>,
+}
+
+/// Field yang tidak dikirim tidak diubah
+#[derive(Debug, Deserialize, Validate, ToSchema)]
+pub struct UpdateProfileRequest {
+    #[validate(length(max = 256), custom(function = "valid_name"))]
+    pub full_name: Option<String>,
+
+    #[validate(custom(function = "valid_phone_number"))]
+    pub mobile_phone: Option<String>,
+
+    /// Base64 atau data URL gambar JPEG / PNG / WebP, maksimal 5MB
+    #[validate(custom(function = "validate_base64_image"))]
+    pub avatar: Option<String>,
+
+    /// Hapus avatar yang tersimpan, diabaikan jika `avatar` dikirim
+    #[serde(default)]
+    pub remove_avatar: bool,
+}
+
+/// Definisi satu setting untuk form pengaturan di frontend
+#[derive(Debug, Serialize, ToSchema)]
+pub struct SettingSchema {
+    pub key: String,
+    /// `choice`, `integer` atau `text`
+    pub kind: String,
+    #[schema(value_type = Object)]
+    pub default: serde_json::Value,
+    /// Pilihan yang valid untuk `choice`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub values: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub min: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_length: Option<usize>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct UpdateSettingsRequest {
+    /// Key yang diubah, contoh `{ "theme": "dark", "page_size": 50 }`. Nilai `null` mengembalikan ke default.
+    #[schema(value_type = Object)]
+    pub values: HashMap<String, serde_json::Value>,
+}
+
+#[derive(Debug, Clone, Deserialize, IntoParams)]
+pub struct AuditLogParams {
+    pub user_nid: Option<i32>,
+    pub email: Option<String>,
+    /// Nama view / table, misalnya `BarChart` atau `CIFLookup`
+    pub entity: Option<String>,
+    /// `insert`, `update`, `delete`, `export`
+    pub action: Option<String>,
+    /// Tanggal awal (YYYY-MM-DD)
+    pub from: Option<String>,
+    /// Tanggal akhir inklusif (YYYY-MM-DD)
+    pub to: Option<String>,
+    #[serde(default)]
+    pub offset: i32,
+    /// Default 100, maksimal 1000
+    pub limit: Option<i32>,
+}
+
+#[derive(Debug, Clone, Deserialize, IntoParams)]
+pub struct TableEventParams {
+    pub tablename: String,
+    #[param(required = false)]
+    pub filter: Option<String>,
+    /// Primary key, default `AutoNID`
+    pub nidkey: Option<String>,
+    /// Kolom watermark perubahan, default `LastUpdate`
+    pub watermark: Option<String>,
+    /// Alternatif header `Last-Event-ID` untuk melanjutkan dari event terakhir
+    pub last_event_id: Option<String>,
+}
+
+/// Pesan dari client di WebSocket `/chart/ws`
+#[derive(Debug, Deserialize)]
+#[serde(tag = "action", rename_all = "lowercase")]
+pub enum ChartSocketMessage {
+    /// Subscribe ke chart milik satu menu, menggantikan subscription sebelumnya
+    Subscribe {
+        menu_id: String,
+        tablename: String,
+        filter: Option<String>,
+        /// Batasi ke ChartID tertentu, default semua chart di menu
+        chart_ids: Option<Vec<String>>,
+    },
+    Unsubscribe,
+}
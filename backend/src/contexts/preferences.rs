@@ -0,0 +1,206 @@
+use std::{collections::HashMap, env};
+
+use chrono::{Duration, NaiveDateTime, Timelike};
+use serde::Serialize;
+use serde_json::{json, Value as JsonValue};
+use utoipa::ToSchema;
+
+/// Tipe dan default satu setting user
+pub enum SettingKind {
+    Choice { values: &'static [&'static str], default: &'static str },
+    Integer { min: i64, max: i64, default: i64 },
+    /// Teks bebas berupa identifier (huruf, angka, `-`, `_`, `/`)
+    Text { max_length: usize, default: &'static str },
+}
+
+/// Zona waktu yang didukung beserta offset-nya. Hanya zona tanpa DST supaya konversi tidak butuh database tz.
+const TIMEZONES: [(&str, i64); 9] = [
+    ("Asia/Jakarta", 7 * 3600),
+    ("Asia/Makassar", 8 * 3600),
+    ("Asia/Jayapura", 9 * 3600),
+    ("Asia/Singapore", 8 * 3600),
+    ("Asia/Kuala_Lumpur", 8 * 3600),
+    ("Asia/Bangkok", 7 * 3600),
+    ("Asia/Hong_Kong", 8 * 3600),
+    ("Asia/Tokyo", 9 * 3600),
+    ("UTC", 0),
+];
+
+/// Format tanggal yang bisa dipilih user dan pola chrono-nya
+const DATE_FORMATS: [(&str, &str); 5] = [
+    ("dd/MM/yyyy", "%d/%m/%Y"),
+    ("dd-MM-yyyy", "%d-%m-%Y"),
+    ("dd MMM yyyy", "%d %b %Y"),
+    ("yyyy-MM-dd", "%Y-%m-%d"),
+    ("MM/dd/yyyy", "%m/%d/%Y"),
+];
+
+/// Format angka (contoh 1234.5): pemisah ribuan dan desimal
+const NUMBER_FORMATS: [(&str, (&str, char)); 4] = [
+    ("1.234,56", (".", ',')),
+    ("1,234.56", (",", '.')),
+    ("1 234,56", (" ", ',')),
+    ("1234.56", ("", '.')),
+];
+
+/// Nama pilihan (kolom pertama) dari tabel di atas untuk `SettingKind::Choice`
+const fn names<T: Copy, const N: usize>(table: &[(&'static str, T); N]) -> [&'static str; N] {
+    let mut names = [""; N];
+    let mut i = 0;
+    while i < N {
+        names[i] = table[i].0;
+        i += 1;
+    }
+    names
+}
+
+/// Schema semua setting user. Key yang tidak terdaftar di sini ditolak saat update.
+pub const SETTINGS: &[(&str, SettingKind)] = &[
+    ("theme", SettingKind::Choice { values: &["system", "light", "dark"], default: "system" }),
+    ("page_size", SettingKind::Integer { min: 10, max: 1000, default: 100 }),
+    ("locale", SettingKind::Choice { values: &["id-ID", "en-US"], default: "id-ID" }),
+    ("timezone", SettingKind::Choice { values: &names(&TIMEZONES), default: "Asia/Jakarta" }),
+    ("number_format", SettingKind::Choice { values: &names(&NUMBER_FORMATS), default: "1.234,56" }),
+    ("date_format", SettingKind::Choice { values: &names(&DATE_FORMATS), default: "dd/MM/yyyy" }),
+    ("default_menu", SettingKind::Text { max_length: 100, default: "dashboard" }),
+];
+
+impl SettingKind {
+    pub fn default_value(&self) -> JsonValue {
+        match self {
+            SettingKind::Choice { default, .. } | SettingKind::Text { default, .. } => json!(default),
+            SettingKind::Integer { default, .. } => json!(default),
+        }
+    }
+
+    /// Cek tipe dan batas nilai, `Err` berisi pesan untuk client
+    pub fn validate(&self, value: &JsonValue) -> Result<(), String> {
+        match self {
+            SettingKind::Choice { values, .. } => match value.as_str() {
+                Some(v) if values.contains(&v) => Ok(()),
+                _ => Err(format!("Must be one of: {}", values.join(", "))),
+            },
+            SettingKind::Integer { min, max, .. } => match value.as_i64() {
+                Some(v) if (*min..=*max).contains(&v) => Ok(()),
+                _ => Err(format!("Must be an integer between {} and {}", min, max)),
+            },
+            SettingKind::Text { max_length, .. } => match value.as_str() {
+                Some(v) if !v.is_empty() && v.len() <= *max_length
+                    && v.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '/') => Ok(()),
+                _ => Err(format!("Must be 1-{} characters of letters, numbers, '-', '_' or '/'", max_length)),
+            },
+        }
+    }
+}
+
+pub fn setting(key: &str) -> Option<&'static SettingKind> {
+    SETTINGS.iter().find(|(k, _)| *k == key).map(|(_, kind)| kind)
+}
+
+fn timezone_offset(name: &str) -> i64 {
+    TIMEZONES.iter().find(|(tz, _)| *tz == name).map(|(_, offset)| *offset).unwrap_or_default()
+}
+
+/// Preferensi user yang sudah digabung dengan default, dipakai backend saat memformat export
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct UserPreferences {
+    pub theme: String,
+    pub page_size: i64,
+    pub locale: String,
+    pub timezone: String,
+    pub number_format: String,
+    pub date_format: String,
+    pub default_menu: String,
+}
+
+impl Default for UserPreferences {
+    fn default() -> Self {
+        Self::from_values(&HashMap::new())
+    }
+}
+
+impl UserPreferences {
+    /// Nilai tersimpan yang tidak valid (mis. schema berubah) diganti default
+    pub fn from_values(values: &HashMap<String, JsonValue>) -> Self {
+        let value = |key: &str| -> JsonValue {
+            let kind = setting(key).expect("setting key is registered in SETTINGS");
+            values.get(key)
+                .filter(|v| kind.validate(v).is_ok())
+                .cloned()
+                .unwrap_or_else(|| kind.default_value())
+        };
+        let text = |key: &str| value(key).as_str().unwrap_or_default().to_string();
+
+        Self {
+            theme: text("theme"),
+            page_size: value("page_size").as_i64().unwrap_or_default(),
+            locale: text("locale"),
+            timezone: text("timezone"),
+            number_format: text("number_format"),
+            date_format: text("date_format"),
+            default_menu: text("default_menu"),
+        }
+    }
+
+    fn separators(&self) -> (&'static str, char) {
+        NUMBER_FORMATS.iter()
+            .find(|(name, _)| *name == self.number_format)
+            .map(|(_, separators)| *separators)
+            .unwrap_or((".", ','))
+    }
+
+    /// Pemisah kolom CSV: `;` jika desimal memakai koma (konvensi Excel untuk locale tersebut)
+    pub fn csv_delimiter(&self) -> char {
+        match self.separators().1 {
+            ',' => ';',
+            _ => ',',
+        }
+    }
+
+    /// Angka dengan pemisah ribuan dan desimal sesuai `number_format`
+    pub fn format_number(&self, value: f64) -> String {
+        let (thousands, decimal) = self.separators();
+        let raw = value.abs().to_string();
+        let (int_part, frac_part) = raw.split_once('.').unwrap_or((raw.as_str(), ""));
+
+        let mut grouped = String::new();
+        for (i, digit) in int_part.chars().enumerate() {
+            if i > 0 && (int_part.len() - i) % 3 == 0 {
+                grouped.push_str(thousands);
+            }
+            grouped.push(digit);
+        }
+
+        let sign = if value < 0.0 { "-" } else { "" };
+        match frac_part.is_empty() {
+            true => format!("{}{}", sign, grouped),
+            false => format!("{}{}{}{}", sign, grouped, decimal, frac_part),
+        }
+    }
+
+    /// Tanggal sesuai `date_format`. Nilai dengan jam 00:00:00 dianggap tanggal saja dan tidak dikonversi zona waktunya,
+    /// selain itu dikonversi dari zona waktu database (`DB_TIMEZONE`, default `Asia/Jakarta`) ke `timezone` user.
+    pub fn format_datetime(&self, value: NaiveDateTime) -> String {
+        let pattern = DATE_FORMATS.iter()
+            .find(|(name, _)| *name == self.date_format)
+            .map(|(_, pattern)| *pattern)
+            .unwrap_or("%d/%m/%Y");
+
+        if value.num_seconds_from_midnight() == 0 && value.nanosecond() == 0 {
+            return value.format(pattern).to_string();
+        }
+
+        let db_timezone = env::var("DB_TIMEZONE").unwrap_or_else(|_| "Asia/Jakarta".to_string());
+        let local = value + Duration::seconds(timezone_offset(&self.timezone) - timezone_offset(&db_timezone));
+        local.format(&format!("{} %H:%M:%S", pattern)).to_string()
+    }
+
+    pub fn format_bool(&self, value: bool) -> &'static str {
+        match (self.locale.as_str(), value) {
+            ("id-ID", true) => "Ya",
+            ("id-ID", false) => "Tidak",
+            (_, true) => "Yes",
+            (_, false) => "No",
+        }
+    }
+}
@@ -0,0 +1,192 @@
+use std::{collections::HashMap, pin::Pin, sync::{Arc, Mutex}, task::{Context, Poll}, time::{Duration, Instant}};
+
+use actix_web::{body::{BodySize, BoxBody, MessageBody}, dev::{ServiceRequest, ServiceResponse}, middleware::Next, web::Bytes, HttpMessage};
+use once_cell::sync::Lazy;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+use crate::services::api_key_service::ApiKeyIdentity;
+
+use super::{auth::{client_ip, decode_claims, token_from_request, API_KEY_HEADER}, config::{RateLimitRule, APP_CONFIG}, error::AppError};
+
+/// Bucket yang tidak dipakai dibersihkan setiap interval ini
+const CLEANUP_INTERVAL: Duration = Duration::from_secs(60);
+/// Nama bucket `api_key_ip`, tidak bentrok dengan prefix path di `routes`
+const API_KEY_IP_ROUTE: &str = "api-key";
+
+struct Bucket {
+    tokens: f64,
+    updated_at: Instant,
+}
+
+impl Bucket {
+    /// Isi ulang token sejak update terakhir, lalu ambil satu.
+    /// `Err(detik)` jika bucket kosong, yaitu waktu sampai satu token tersedia.
+    fn take(&mut self, rule: &RateLimitRule) -> Result<(), u64> {
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.updated_at).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * rule.refill_per_sec).min(rule.capacity);
+        self.updated_at = now;
+
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            return Ok(());
+        }
+
+        match rule.refill_per_sec > 0.0 {
+            true => Err(((1.0 - self.tokens) / rule.refill_per_sec).ceil().max(1.0) as u64),
+            false => Err(60),
+        }
+    }
+
+    /// Bucket yang sudah penuh kembali sama dengan bucket baru, jadi aman dihapus
+    fn is_idle(&self, rule: &RateLimitRule) -> bool {
+        self.tokens + self.updated_at.elapsed().as_secs_f64() * rule.refill_per_sec >= rule.capacity
+    }
+}
+
+struct RateLimiter {
+    /// Bucket per (prefix route, identitas)
+    buckets: HashMap<(String, String), Bucket>,
+    last_cleanup: Instant,
+}
+
+static LIMITER: Lazy<Mutex<RateLimiter>> = Lazy::new(|| Mutex::new(RateLimiter { buckets: HashMap::new(), last_cleanup: Instant::now() }));
+
+/// Semaphore per identitas untuk membatasi request yang berjalan bersamaan
+static CONCURRENCY: Lazy<Mutex<HashMap<String, Arc<Semaphore>>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// `true` jika `path` sama dengan `prefix` atau berada di bawahnya (batas segmen `/`)
+fn path_matches(path: &str, prefix: &str) -> bool {
+    let prefix = prefix.trim_end_matches('/');
+    path.strip_prefix(prefix).is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+}
+
+/// Aturan untuk path: prefix terpanjang di `routes`, atau `default` dengan key `*`
+fn rule_for(path: &str) -> (String, RateLimitRule) {
+    let config = &APP_CONFIG.rate_limit;
+
+    config.routes.iter()
+        .filter(|(prefix, _)| path_matches(path, prefix))
+        .max_by_key(|(prefix, _)| prefix.len())
+        .map(|(prefix, rule)| (prefix.clone(), *rule))
+        .unwrap_or_else(|| ("*".to_string(), config.default))
+}
+
+/// Aturan untuk bucket yang sudah tersimpan
+fn rule_for_bucket(route: &str) -> RateLimitRule {
+    match route {
+        API_KEY_IP_ROUTE => APP_CONFIG.rate_limit.api_key_ip,
+        route => rule_for(route).1,
+    }
+}
+
+/// Identitas yang dibatasi: API key, user dari token, atau IP client untuk request anonim
+fn identity(req: &ServiceRequest) -> String {
+    if let Some(identity) = req.extensions().get::<Arc<ApiKeyIdentity>>() {
+        return format!("key:{}", identity.api_key_nid);
+    }
+
+    match token_from_request(req.request()).and_then(|token| decode_claims(&token).ok()) {
+        Some(claims) => format!("user:{}", claims.auth_usernid),
+        None => format!("ip:{}", client_ip(req.request()).unwrap_or_default()),
+    }
+}
+
+fn check_rate(route: String, rule: &RateLimitRule, identity: &str) -> Result<(), AppError> {
+    let mut limiter = LIMITER.lock().unwrap();
+
+    if limiter.last_cleanup.elapsed() >= CLEANUP_INTERVAL {
+        limiter.buckets.retain(|(route, _), bucket| !bucket.is_idle(&rule_for_bucket(route)));
+        limiter.last_cleanup = Instant::now();
+    }
+
+    let message = format!("Rate limit exceeded for {}", if route == "*" || route == API_KEY_IP_ROUTE { "this API" } else { route.as_str() });
+    limiter.buckets
+        .entry((route, identity.to_string()))
+        .or_insert_with(|| Bucket { tokens: rule.capacity, updated_at: Instant::now() })
+        .take(rule)
+        .map_err(|retry_after| AppError::TooManyRequests(format!("{}, retry in {} seconds", message, retry_after), retry_after))
+}
+
+/// Tunggu slot query untuk identitas, paling lama `concurrency_wait_ms`
+async fn acquire_slot(identity: &str, max_concurrent: usize) -> Result<OwnedSemaphorePermit, AppError> {
+    let semaphore = {
+        let mut semaphores = CONCURRENCY.lock().unwrap();
+        // Semaphore yang tidak sedang dipakai request lain tidak perlu disimpan
+        if semaphores.len() > 1024 {
+            semaphores.retain(|_, semaphore| Arc::strong_count(semaphore) > 1);
+        }
+        semaphores.entry(identity.to_string())
+            .or_insert_with(|| Arc::new(Semaphore::new(max_concurrent)))
+            .clone()
+    };
+
+    let wait = Duration::from_millis(APP_CONFIG.rate_limit.concurrency_wait_ms);
+    match tokio::time::timeout(wait, semaphore.acquire_owned()).await {
+        Ok(Ok(permit)) => Ok(permit),
+        _ => Err(AppError::TooManyRequests(format!("Too many concurrent requests, at most {} at a time", max_concurrent), 1)),
+    }
+}
+
+/// Body respons yang menahan slot concurrency sampai selesai dikirim (export besar di-stream)
+struct PermitBody {
+    body: BoxBody,
+    _permit: OwnedSemaphorePermit,
+}
+
+impl MessageBody for PermitBody {
+    type Error = Box<dyn std::error::Error>;
+
+    fn size(&self) -> BodySize {
+        self.body.size()
+    }
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Result<Bytes, Self::Error>>> {
+        Pin::new(&mut self.get_mut().body).poll_next(cx)
+    }
+}
+
+/// Middleware: bucket per IP untuk request dengan header `X-API-Key`. Dipasang di luar `api_key_middleware`
+/// supaya key palsu yang dikirim terus-menerus tidak sampai ke database.
+pub async fn api_key_ip_limit_middleware(req: ServiceRequest, next: Next<impl MessageBody + 'static>) -> Result<ServiceResponse<BoxBody>, actix_web::Error> {
+    let config = &APP_CONFIG.rate_limit;
+
+    if config.enabled && req.headers().contains_key(API_KEY_HEADER) {
+        let identity = format!("ip:{}", client_ip(req.request()).unwrap_or_default());
+        if let Err(e) = check_rate(API_KEY_IP_ROUTE.to_string(), &config.api_key_ip, &identity) {
+            return Ok(req.error_response(e));
+        }
+    }
+
+    next.call(req).await.map(ServiceResponse::map_into_boxed_body)
+}
+
+/// Middleware: token bucket per route + identitas dan batas request bersamaan per identitas untuk `/api/v1`.
+/// Harus berjalan setelah `api_key_middleware` supaya request API key dihitung per key.
+pub async fn rate_limit_middleware(req: ServiceRequest, next: Next<impl MessageBody + 'static>) -> Result<ServiceResponse<BoxBody>, actix_web::Error> {
+    let config = &APP_CONFIG.rate_limit;
+    let path = req.path().to_string();
+
+    if !config.enabled || !path_matches(&path, "/api/v1") {
+        return next.call(req).await.map(ServiceResponse::map_into_boxed_body);
+    }
+
+    let identity = identity(&req);
+    let (route, rule) = rule_for(&path);
+    if let Err(e) = check_rate(route, &rule, &identity) {
+        return Ok(req.error_response(e));
+    }
+
+    let exempt = config.concurrency_exempt.iter().any(|prefix| path_matches(&path, prefix));
+    if config.max_concurrent_per_user == 0 || exempt {
+        return next.call(req).await.map(ServiceResponse::map_into_boxed_body);
+    }
+
+    let permit = match acquire_slot(&identity, config.max_concurrent_per_user).await {
+        Ok(permit) => permit,
+        Err(e) => return Ok(req.error_response(e)),
+    };
+
+    let res = next.call(req).await?;
+    Ok(res.map_body(|_, body| BoxBody::new(PermitBody { body: BoxBody::new(body), _permit: permit })))
+}
@@ -0,0 +1,78 @@
+use actix_web::{body::{self, BoxBody, MessageBody}, dev::{ServiceRequest, ServiceResponse}, http::header::{self, HeaderName, HeaderValue}, middleware::Next};
+use serde_json::Value as JsonValue;
+use uuid::Uuid;
+
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
+
+tokio::task_local! {
+    static REQUEST_ID: String;
+}
+
+/// Ambil request ID dari task yang sedang berjalan (dipakai oleh service & logger)
+pub fn current_request_id() -> Option<String> {
+    REQUEST_ID.try_with(|id| id.clone()).ok()
+}
+
+/// Jalankan future (mis. task dari `tokio::spawn`) dengan request ID yang sama seperti request asalnya
+pub async fn scope_request_id<F: std::future::Future>(request_id: Option<String>, f: F) -> F::Output {
+    REQUEST_ID.scope(request_id.unwrap_or_default(), f).await
+}
+
+/// Header dari client hanya dipakai jika aman untuk ditulis ke log
+fn is_valid_request_id(value: &str) -> bool {
+    !value.is_empty()
+        && value.len() <= 64
+        && value.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.')
+}
+
+/// Middleware: terima atau buat `X-Request-Id`, kirim balik di header dan di body error
+pub async fn request_id_middleware(req: ServiceRequest, next: Next<impl MessageBody + 'static>) -> Result<ServiceResponse<BoxBody>, actix_web::Error> {
+    let request_id: String = req.headers()
+        .get(REQUEST_ID_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .filter(|value| is_valid_request_id(value))
+        .map(|value| value.to_string())
+        .unwrap_or_else(|| Uuid::new_v4().to_string());
+
+    let res: ServiceResponse<_> = REQUEST_ID.scope(request_id.clone(), next.call(req)).await?;
+    let mut res: ServiceResponse<BoxBody> = attach_request_id_to_error_body(res, &request_id).await;
+
+    if let Ok(value) = HeaderValue::from_str(&request_id) {
+        res.headers_mut().insert(HeaderName::from_static(REQUEST_ID_HEADER), value);
+    }
+
+    Ok(res)
+}
+
+/// Tambahkan field `request_id` ke body JSON (ActionResult) untuk response 4xx/5xx
+async fn attach_request_id_to_error_body(res: ServiceResponse<impl MessageBody + 'static>, request_id: &str) -> ServiceResponse<BoxBody> {
+    let is_json = res.headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.starts_with("application/json"));
+
+    if !(res.status().is_client_error() || res.status().is_server_error()) || !is_json {
+        return res.map_into_boxed_body();
+    }
+
+    let (req, res) = res.into_parts();
+    let (res, body) = res.into_parts();
+
+    let bytes = match body::to_bytes(body).await {
+        Ok(bytes) => bytes,
+        Err(_) => return ServiceResponse::new(req, res.set_body(BoxBody::new(()))),
+    };
+
+    let bytes = match serde_json::from_slice::<JsonValue>(&bytes) {
+        Ok(JsonValue::Object(mut json_obj)) => {
+            json_obj.entry("request_id").or_insert_with(|| JsonValue::String(request_id.to_string()));
+            serde_json::to_vec(&json_obj).map(Into::into).unwrap_or(bytes)
+        }
+        _ => bytes,
+    };
+
+    let mut res = res.set_body(BoxBody::new(bytes));
+    res.headers_mut().remove(header::CONTENT_LENGTH);
+
+    ServiceResponse::new(req, res)
+}
@@ -0,0 +1,99 @@
+use std::fmt::Write;
+
+use serde_json::Value as JsonValue;
+
+use super::{config::APP_CONFIG, error::AppError, model::Claims};
+
+/// Permission untuk melihat semua baris tanpa row filter (admin otomatis memilikinya)
+pub const SCOPE_ALL: &str = "scope:all";
+
+/// Nama view / kolom yang aman disisipkan ke SQL
+pub fn is_identifier(name: &str) -> bool {
+    !name.is_empty() && name.chars().all(|c| c.is_alphanumeric() || c == '_')
+}
+
+/// Row-level security satu view untuk satu user: pasangan (kolom, nilai yang boleh dilihat).
+/// Kondisinya selalu di-AND-kan ke WHERE, jadi filter dari client hanya bisa mempersempit hasil.
+#[derive(Debug, Clone, Default)]
+pub struct RowScope {
+    conditions: Vec<(String, Vec<String>)>,
+}
+
+impl RowScope {
+    /// Row filter dari `views.<tablename>.row_filters` dengan nilai dari claims
+    pub fn for_view(claims: &Claims, tablename: &str) -> Result<Self, AppError> {
+        let conditions = APP_CONFIG.view(tablename).row_filters.iter()
+            .map(|filter| {
+                if !is_identifier(&filter.column) {
+                    return Err(AppError::Internal(format!("Invalid row filter column '{}' for '{}'", filter.column, tablename)));
+                }
+
+                Ok((filter.column.clone(), Self::claim_values(claims, &filter.claim)?))
+            })
+            .collect::<Result<Vec<_>, AppError>>()?;
+
+        Ok(Self { conditions })
+    }
+
+    fn claim_values(claims: &Claims, claim: &str) -> Result<Vec<String>, AppError> {
+        let values = match claim {
+            "company_id" => claims.company_id.clone().into_iter().collect(),
+            "comp_name" => claims.comp_name.clone().into_iter().collect(),
+            "branches" => claims.branches.clone(),
+            "auth_usernid" => vec![claims.auth_usernid.to_string()],
+            "email" => vec![claims.email.clone()],
+            _ => return Err(AppError::Internal(format!("Unknown row filter claim '{}'", claim))),
+        };
+
+        Ok(values.into_iter().filter(|v| !v.is_empty()).collect())
+    }
+
+    pub fn conditions(&self) -> &[(String, Vec<String>)] {
+        &self.conditions
+    }
+
+    /// Kondisi ` AND [kolom] IN (...)`, kosong jika view tidak punya row filter.
+    /// User tanpa nilai claim tidak melihat baris apa pun.
+    pub fn and_clause(&self) -> String {
+        let mut clause = String::new();
+
+        for (column, values) in &self.conditions {
+            if values.is_empty() {
+                clause.push_str(" AND 1=0");
+                continue;
+            }
+
+            let values: Vec<String> = values.iter().map(|v| format!("N'{}'", v.replace('\'', "''"))).collect();
+            let _ = write!(clause, " AND [{}] IN ({})", column, values.join(", "));
+        }
+
+        clause
+    }
+
+    /// Awal WHERE untuk semua query view
+    pub fn where_clause(&self) -> String {
+        format!(" WHERE 1=1 {} ", self.and_clause())
+    }
+
+    /// Bagian cache key, supaya count & chart tidak dipakai bersama oleh user dengan scope berbeda
+    pub fn cache_scope(&self) -> String {
+        if self.conditions.is_empty() {
+            return String::new();
+        }
+
+        serde_json::to_string(&self.conditions).unwrap_or_default()
+    }
+
+    /// Apakah nilai kolom (saat insert / update) masih di dalam scope user
+    pub fn allows(&self, column: &str, value: &JsonValue) -> bool {
+        let value = match value {
+            JsonValue::String(s) => s.clone(),
+            JsonValue::Number(n) => n.to_string(),
+            _ => return !self.conditions.iter().any(|(c, _)| c.eq_ignore_ascii_case(column)),
+        };
+
+        self.conditions.iter()
+            .filter(|(c, _)| c.eq_ignore_ascii_case(column))
+            .all(|(_, values)| values.contains(&value))
+    }
+}
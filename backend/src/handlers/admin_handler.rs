@@ -0,0 +1,63 @@
+use actix_web::{get, post, web, HttpResponse, Scope};
+use bb8::Pool;
+use serde_json::json;
+
+use crate::{contexts::{auth::UserPermissions, cache::QUERY_CACHE, connection::ConnectionManager, error::AppError, model::{ActionResult, AuditLogParams, CacheInvalidateParams, PermissionInvalidateParams}}, services::{audit_service::AuditService, permission_service::PermissionService}};
+
+pub fn admin_scope() -> Scope {
+    web::scope("/admin")
+        .service(invalidate_cache)
+        .service(get_audit_logs)
+        .service(invalidate_permissions)
+}
+
+#[post("/cache/invalidate")]
+pub async fn invalidate_cache(params: web::Query<CacheInvalidateParams>, auth: UserPermissions) -> Result<HttpResponse, AppError> {
+
+    auth.require("admin")?;
+
+    let tablename: String = params.into_inner().tablename;
+
+    if tablename.trim().is_empty() {
+        return Err(AppError::BadRequest("Tablename is empty".to_string()));
+    }
+
+    let removed: usize = QUERY_CACHE.invalidate_view(&tablename);
+
+    let result: ActionResult<serde_json::Value, String> = ActionResult {
+        result: true,
+        message: format!("{} cache entries removed", removed),
+        data: Some(json!({ "tablename": tablename, "removed": removed })),
+        ..Default::default()
+    };
+
+    Ok(HttpResponse::Ok().json(result))
+}
+
+#[get("/audit")]
+pub async fn get_audit_logs(pool: web::Data<Pool<ConnectionManager>>, params: web::Query<AuditLogParams>, auth: UserPermissions) -> Result<HttpResponse, AppError> {
+
+    auth.require("admin")?;
+
+    let result: ActionResult<Vec<serde_json::Value>, String> = AuditService::get_logs(pool, params.into_inner()).await?;
+
+    Ok(HttpResponse::Ok().json(result))
+}
+
+#[post("/permissions/invalidate")]
+pub async fn invalidate_permissions(params: web::Query<PermissionInvalidateParams>, auth: UserPermissions) -> Result<HttpResponse, AppError> {
+
+    auth.require("admin")?;
+
+    let user_nid: Option<i32> = params.into_inner().user_nid;
+    let removed: usize = PermissionService::invalidate(user_nid);
+
+    let result: ActionResult<serde_json::Value, String> = ActionResult {
+        result: true,
+        message: format!("{} permission cache entries removed", removed),
+        data: Some(json!({ "user_nid": user_nid, "removed": removed })),
+        ..Default::default()
+    };
+
+    Ok(HttpResponse::Ok().json(result))
+}
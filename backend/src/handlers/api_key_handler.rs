@@ -0,0 +1,51 @@
+use actix_web::{get, post, web, HttpResponse, Scope};
+use bb8::Pool;
+use validator::Validate;
+
+use crate::{contexts::{auth::{AuditActor, UserPermissions}, connection::ConnectionManager, error::AppError, model::{ActionResult, ApiKeyCreated, ApiKeyInfo, CreateApiKeyRequest, RevokeApiKeyRequest}}, services::api_key_service::ApiKeyService};
+
+pub fn api_key_scope() -> Scope {
+    web::scope("/api-keys")
+        .service(get_api_keys)
+        .service(create_api_key)
+        .service(revoke_api_key)
+}
+
+/// Daftar API key milik user login (tanpa secret)
+#[get("")]
+pub async fn get_api_keys(pool: web::Data<Pool<ConnectionManager>>, auth: UserPermissions) -> Result<HttpResponse, AppError> {
+
+    auth.require_login()?;
+
+    let result: ActionResult<Vec<ApiKeyInfo>, String> = ApiKeyService::list(&pool, auth.claims.auth_usernid).await?;
+
+    Ok(HttpResponse::Ok().json(result))
+}
+
+#[post("/create")]
+pub async fn create_api_key(pool: web::Data<Pool<ConnectionManager>>, request: web::Json<CreateApiKeyRequest>, actor: AuditActor, auth: UserPermissions) -> Result<HttpResponse, AppError> {
+
+    auth.require_login()?;
+    request.validate()?;
+
+    let result: ActionResult<ApiKeyCreated, String> = ApiKeyService::create(&pool, &auth, request.into_inner(), &actor).await?;
+
+    Ok(HttpResponse::Ok().json(result))
+}
+
+#[post("/revoke")]
+pub async fn revoke_api_key(pool: web::Data<Pool<ConnectionManager>>, request: web::Json<RevokeApiKeyRequest>, actor: AuditActor, auth: UserPermissions) -> Result<HttpResponse, AppError> {
+
+    auth.require_login()?;
+    request.validate()?;
+
+    ApiKeyService::revoke(&pool, &auth, request.api_key_nid.unwrap_or_default(), &actor).await?;
+
+    let result: ActionResult<(), String> = ActionResult {
+        result: true,
+        message: "API key revoked".to_string(),
+        ..Default::default()
+    };
+
+    Ok(HttpResponse::Ok().json(result))
+}
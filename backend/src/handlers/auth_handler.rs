@@ -0,0 +1,294 @@
+use actix_web::{get, post, web, HttpRequest, HttpResponse, Scope};
+use bb8::Pool;
+use validator::Validate;
+
+use crate::{contexts::{auth::{decode_pending_two_factor, encode_pending_two_factor, refresh_cookie, token_cookie, two_factor_cookie, user_agent, AuditActor, UserPermissions, REFRESH_COOKIE, TWO_FACTOR_COOKIE}, connection::ConnectionManager, error::AppError, mailer::Mailer, model::{ActionResult, Claims, ForgotPasswordRequest, LoginRequest, ResetPasswordRequest, RevokeSessionRequest, SessionInfo, TotpBackupCodes, TotpEnrollment, TwoFactorCodeRequest, TwoFactorPending, UserAccessInfo, VerifyEmailRequest}}, services::{account_service::AccountService, credential_service::{CredentialService, LoginOutcome}, session_service::{IssuedTokens, SessionService}, totp_service::TotpService}};
+
+pub fn auth_scope() -> Scope {
+    web::scope("/auth")
+        .service(login)
+        .service(verify_two_factor)
+        .service(refresh)
+        .service(logout)
+        .service(check_session)
+        .service(get_sessions)
+        .service(revoke_session)
+        .service(enroll_two_factor)
+        .service(confirm_two_factor)
+        .service(disable_two_factor)
+        .service(forgot_password)
+        .service(reset_password)
+        .service(send_verification_email)
+        .service(verify_email)
+        .service(get_permissions)
+}
+
+/// Response berisi claims, access token & refresh token dikirim sebagai cookie HttpOnly
+fn token_response(tokens: IssuedTokens, message: &str) -> HttpResponse {
+    let max_age = tokens.claims.expired_token - chrono::Utc::now().timestamp();
+
+    let result: ActionResult<Claims, String> = ActionResult {
+        result: true,
+        message: message.to_string(),
+        data: Some(tokens.claims),
+        ..Default::default()
+    };
+
+    HttpResponse::Ok()
+        .cookie(token_cookie(tokens.access_token, max_age))
+        .cookie(refresh_cookie(tokens.refresh_token, tokens.refresh_max_age))
+        .json(result)
+}
+
+#[post("/login")]
+pub async fn login(req: HttpRequest, pool: web::Data<Pool<ConnectionManager>>, request: web::Json<LoginRequest>, actor: AuditActor) -> Result<HttpResponse, AppError> {
+
+    request.validate()?;
+
+    let request = request.into_inner();
+    let outcome = CredentialService::authenticate(
+        &pool,
+        request.email.as_deref().unwrap_or_default(),
+        request.password.as_deref().unwrap_or_default(),
+        &actor,
+    ).await?;
+
+    // Akun dengan 2FA belum mendapat sesi, hanya token sementara untuk `/auth/2fa/verify`
+    let claims = match outcome {
+        LoginOutcome::Authenticated(claims) => *claims,
+        LoginOutcome::TwoFactorRequired { user_nid, nonce } => {
+            let expires_in: i64 = std::env::var("TWO_FACTOR_PENDING_SECS").ok().and_then(|v| v.parse().ok()).unwrap_or(300);
+            let pending_token = encode_pending_two_factor(user_nid, &nonce, expires_in)?;
+
+            let result: ActionResult<TwoFactorPending, String> = ActionResult {
+                result: true,
+                message: "Two-factor authentication required".to_string(),
+                data: Some(TwoFactorPending { two_factor_required: true, expires_in }),
+                ..Default::default()
+            };
+
+            return Ok(HttpResponse::Ok().cookie(two_factor_cookie(pending_token, expires_in)).json(result));
+        }
+    };
+
+    let tokens = SessionService::create(&pool, claims, user_agent(&req)).await?;
+
+    Ok(token_response(tokens, "Login Success"))
+}
+
+/// Langkah kedua login untuk akun dengan 2FA: kode TOTP atau backup code
+#[post("/2fa/verify")]
+pub async fn verify_two_factor(req: HttpRequest, pool: web::Data<Pool<ConnectionManager>>, request: web::Json<TwoFactorCodeRequest>, actor: AuditActor) -> Result<HttpResponse, AppError> {
+
+    request.validate()?;
+
+    let (user_nid, nonce) = req.cookie(TWO_FACTOR_COOKIE)
+        .ok_or_else(|| AppError::Unauthorized("Two-factor login not started, please login first".to_string()))
+        .and_then(|cookie| decode_pending_two_factor(cookie.value()))?;
+
+    let code = request.into_inner().code.unwrap_or_default();
+    let claims = TotpService::verify_login(&pool, user_nid, &nonce, &code, &actor).await?;
+    let tokens = SessionService::create(&pool, claims, user_agent(&req)).await?;
+
+    let mut response = token_response(tokens, "Login Success");
+    response.add_removal_cookie(&two_factor_cookie(String::new(), 0))
+        .map_err(|e| AppError::Internal(format!("Failed to clear cookie: {}", e)))?;
+
+    Ok(response)
+}
+
+/// Tukar refresh token (cookie) dengan access token baru; refresh token ikut dirotasi
+#[post("/refresh")]
+pub async fn refresh(req: HttpRequest, pool: web::Data<Pool<ConnectionManager>>, actor: AuditActor) -> Result<HttpResponse, AppError> {
+
+    let refresh_token = req.cookie(REFRESH_COOKIE)
+        .map(|cookie| cookie.value().to_string())
+        .ok_or_else(|| AppError::Unauthorized("Refresh token not found".to_string()))?;
+
+    let tokens = SessionService::refresh(&pool, &refresh_token, &actor, user_agent(&req)).await?;
+
+    Ok(token_response(tokens, "Token refreshed"))
+}
+
+/// Cabut sesi saat ini dan hapus cookie, tetap berhasil walaupun access token sudah kedaluwarsa
+#[post("/logout")]
+pub async fn logout(req: HttpRequest, pool: web::Data<Pool<ConnectionManager>>, claims: Option<Claims>, actor: AuditActor) -> Result<HttpResponse, AppError> {
+
+    match (claims.and_then(|c| c.sid.map(|sid| (c.auth_usernid, sid))), req.cookie(REFRESH_COOKIE)) {
+        (Some((user_nid, sid)), _) => match SessionService::revoke(&pool, user_nid, &sid, &actor).await {
+            Ok(()) | Err(AppError::NotFound(_)) => {}
+            Err(e) => return Err(e),
+        },
+        (None, Some(cookie)) => SessionService::revoke_by_token(&pool, cookie.value()).await?,
+        (None, None) => {}
+    }
+
+    let result: ActionResult<(), String> = ActionResult {
+        result: true,
+        message: "Logout Success".to_string(),
+        ..Default::default()
+    };
+
+    Ok(HttpResponse::Ok()
+        .cookie(token_cookie(String::new(), 0))
+        .cookie(refresh_cookie(String::new(), 0))
+        .json(result))
+}
+
+#[get("/session")]
+pub async fn check_session(claims: Claims) -> Result<HttpResponse, AppError> {
+
+    let result: ActionResult<Claims, String> = ActionResult {
+        result: true,
+        message: "Session active".to_string(),
+        data: Some(claims),
+        ..Default::default()
+    };
+
+    Ok(HttpResponse::Ok().json(result))
+}
+
+/// Daftar sesi aktif user login (per perangkat)
+#[get("/sessions")]
+pub async fn get_sessions(pool: web::Data<Pool<ConnectionManager>>, claims: Claims) -> Result<HttpResponse, AppError> {
+
+    let result: ActionResult<Vec<SessionInfo>, String> = SessionService::list(&pool, claims.auth_usernid, claims.sid.as_deref()).await?;
+
+    Ok(HttpResponse::Ok().json(result))
+}
+
+#[post("/sessions/revoke")]
+pub async fn revoke_session(pool: web::Data<Pool<ConnectionManager>>, request: web::Json<RevokeSessionRequest>, claims: Claims, actor: AuditActor) -> Result<HttpResponse, AppError> {
+
+    request.validate()?;
+
+    let session_id = request.into_inner().session_id.unwrap_or_default();
+    SessionService::revoke(&pool, claims.auth_usernid, &session_id, &actor).await?;
+
+    let result: ActionResult<(), String> = ActionResult {
+        result: true,
+        message: "Session revoked".to_string(),
+        ..Default::default()
+    };
+
+    Ok(HttpResponse::Ok().json(result))
+}
+
+/// Mulai enrolment 2FA: secret & provisioning URI untuk aplikasi authenticator
+#[post("/2fa/enroll")]
+pub async fn enroll_two_factor(pool: web::Data<Pool<ConnectionManager>>, claims: Claims) -> Result<HttpResponse, AppError> {
+
+    let result: ActionResult<TotpEnrollment, String> = TotpService::enroll(&pool, &claims).await?;
+
+    Ok(HttpResponse::Ok().json(result))
+}
+
+/// Aktifkan 2FA dengan kode pertama dari aplikasi authenticator, response berisi backup code
+#[post("/2fa/confirm")]
+pub async fn confirm_two_factor(pool: web::Data<Pool<ConnectionManager>>, request: web::Json<TwoFactorCodeRequest>, claims: Claims, actor: AuditActor) -> Result<HttpResponse, AppError> {
+
+    request.validate()?;
+
+    let code = request.into_inner().code.unwrap_or_default();
+    let result: ActionResult<TotpBackupCodes, String> = TotpService::confirm(&pool, claims.auth_usernid, &code, &actor).await?;
+
+    Ok(HttpResponse::Ok().json(result))
+}
+
+#[post("/2fa/disable")]
+pub async fn disable_two_factor(pool: web::Data<Pool<ConnectionManager>>, request: web::Json<TwoFactorCodeRequest>, claims: Claims, actor: AuditActor) -> Result<HttpResponse, AppError> {
+
+    request.validate()?;
+
+    let code = request.into_inner().code.unwrap_or_default();
+    TotpService::disable(&pool, claims.auth_usernid, &code, &actor).await?;
+
+    let result: ActionResult<(), String> = ActionResult {
+        result: true,
+        message: "Two-factor authentication disabled".to_string(),
+        ..Default::default()
+    };
+
+    Ok(HttpResponse::Ok().json(result))
+}
+
+/// Kirim link reset password ke email, respons sama untuk email terdaftar maupun tidak
+#[post("/password/forgot")]
+pub async fn forgot_password(pool: web::Data<Pool<ConnectionManager>>, mailer: web::Data<dyn Mailer>, request: web::Json<ForgotPasswordRequest>, actor: AuditActor) -> Result<HttpResponse, AppError> {
+
+    request.validate()?;
+
+    let email = request.into_inner().email.unwrap_or_default();
+    let result: ActionResult<(), String> = AccountService::request_password_reset(&pool, mailer.into_inner(), &email, &actor).await?;
+
+    Ok(HttpResponse::Ok().json(result))
+}
+
+#[post("/password/reset")]
+pub async fn reset_password(pool: web::Data<Pool<ConnectionManager>>, request: web::Json<ResetPasswordRequest>, actor: AuditActor) -> Result<HttpResponse, AppError> {
+
+    request.validate()?;
+
+    let request = request.into_inner();
+    AccountService::reset_password(
+        &pool,
+        request.token.as_deref().unwrap_or_default(),
+        request.password.as_deref().unwrap_or_default(),
+        &actor,
+    ).await?;
+
+    let result: ActionResult<(), String> = ActionResult {
+        result: true,
+        message: "Password has been reset, please login again".to_string(),
+        ..Default::default()
+    };
+
+    Ok(HttpResponse::Ok().json(result))
+}
+
+/// Kirim ulang link verifikasi ke email user login
+#[post("/verify-email/send")]
+pub async fn send_verification_email(pool: web::Data<Pool<ConnectionManager>>, mailer: web::Data<dyn Mailer>, claims: Claims, actor: AuditActor) -> Result<HttpResponse, AppError> {
+
+    let result: ActionResult<(), String> = AccountService::send_verification(&pool, mailer.into_inner(), &claims, &actor).await?;
+
+    Ok(HttpResponse::Ok().json(result))
+}
+
+#[post("/verify-email")]
+pub async fn verify_email(pool: web::Data<Pool<ConnectionManager>>, request: web::Json<VerifyEmailRequest>, actor: AuditActor) -> Result<HttpResponse, AppError> {
+
+    request.validate()?;
+
+    AccountService::verify_email(&pool, request.token.as_deref().unwrap_or_default(), &actor).await?;
+
+    let result: ActionResult<(), String> = ActionResult {
+        result: true,
+        message: "Email verified".to_string(),
+        ..Default::default()
+    };
+
+    Ok(HttpResponse::Ok().json(result))
+}
+
+/// Role & permission user login, dipakai frontend untuk menyembunyikan menu / aksi
+#[get("/permissions")]
+pub async fn get_permissions(auth: UserPermissions) -> Result<HttpResponse, AppError> {
+
+    let mut permissions: Vec<String> = auth.access.permissions.iter().cloned().collect();
+    permissions.sort();
+
+    let result: ActionResult<UserAccessInfo, String> = ActionResult {
+        result: true,
+        message: "Permissions retrieved successfully".to_string(),
+        data: Some(UserAccessInfo {
+            auth_usernid: auth.claims.auth_usernid,
+            email: auth.claims.email.clone(),
+            roles: auth.access.roles.clone(),
+            permissions,
+        }),
+        ..Default::default()
+    };
+
+    Ok(HttpResponse::Ok().json(result))
+}
@@ -1,13 +1,8 @@
-
-use std::collections::HashMap;
-
-use actix_web::{get, post, web, HttpResponse, Responder, Scope};
+use actix_web::{get, post, web, HttpRequest, HttpResponse, Scope};
 use bb8::Pool;
-use bb8_tiberius::ConnectionManager;
-use serde_json::json;
 use validator::Validate;
 
-use crate::{contexts::model::{ActionResult, BarChartParams, BarChartRequest, DeleteBarChart}, services::{chart_service::ChartService, validation_service::validator::format_validation_errors}};
+use crate::{contexts::{auth::{AuditActor, UserPermissions}, connection::ConnectionManager, error::AppError, model::{ActionResult, BarChartParams, BarChartRequest, DeleteBarChart}}, services::{chart_live_service::ChartLiveService, chart_service::ChartService}};
 
 pub fn chart_scope() -> Scope {
     web::scope("/chart")
@@ -16,132 +11,86 @@ pub fn chart_scope() -> Scope {
         .service(get_chart_data)
         .service(delete_bar_chart)
         .service(update_bar_chart)
+        .service(chart_socket)
 }
 
 #[post("/create-bar")]
-async fn create_bar_chart(pool: web::Data<Pool<ConnectionManager>>, request: web::Json<BarChartRequest>) -> impl Responder {
-
-    if let Err(errors) = request.validate() {
-        let formatted_errors: HashMap<String, String> = format_validation_errors(&errors);
-        
-        let result: ActionResult<HashMap<String, String>, _> = ActionResult {
-            result: false,
-            message: "Validation failed".to_string(),
-            data: None,
-            error: Some(formatted_errors),
-        };
-
-        return HttpResponse::BadRequest().json(result);
-    }
+async fn create_bar_chart(pool: web::Data<Pool<ConnectionManager>>, request: web::Json<BarChartRequest>, actor: AuditActor, auth: UserPermissions) -> Result<HttpResponse, AppError> {
+
+    auth.require("chart:edit")?;
+    request.validate()?;
     
-    let result: ActionResult<(), _> = ChartService::save_bar_chart(pool, request.into_inner()).await;
-
-    match result {
-        response if response.error.is_some() => {
-            HttpResponse::InternalServerError().json(response)
-        }, // Jika error, HTTP 500
-        response if response.result => HttpResponse::Ok().json(response), // Jika berhasil, HTTP 200
-        response => HttpResponse::BadRequest().json(response), // Jika gagal, HTTP 400
-    }
+    let result: ActionResult<(), _> = ChartService::save_bar_chart(pool, request.into_inner(), &actor).await?;
+
+    Ok(HttpResponse::Ok().json(result))
 }
 
 #[post("/update-bar")]
-async fn update_bar_chart(pool: web::Data<Pool<ConnectionManager>>, request: web::Json<BarChartRequest>) -> impl Responder {
-
-    if let Err(errors) = request.validate() {
-        let formatted_errors: HashMap<String, String> = format_validation_errors(&errors);
-        
-        let result: ActionResult<HashMap<String, String>, _> = ActionResult {
-            result: false,
-            message: "Validation failed".to_string(),
-            data: None,
-            error: Some(formatted_errors),
-        };
-
-        return HttpResponse::BadRequest().json(result);
-    }
+async fn update_bar_chart(pool: web::Data<Pool<ConnectionManager>>, request: web::Json<BarChartRequest>, actor: AuditActor, auth: UserPermissions) -> Result<HttpResponse, AppError> {
+
+    auth.require("chart:edit")?;
+    request.validate()?;
     
-    let result: ActionResult<(), _> = ChartService::update_bar_chart(pool, request.into_inner()).await;
-
-    match result {
-        response if response.error.is_some() => {
-            HttpResponse::InternalServerError().json(response)
-        }, // Jika error, HTTP 500
-        response if response.result => HttpResponse::Ok().json(response), // Jika berhasil, HTTP 200
-        response => HttpResponse::BadRequest().json(response), // Jika gagal, HTTP 400
-    }
+    let result: ActionResult<(), _> = ChartService::update_bar_chart(pool, request.into_inner(), &actor).await?;
+
+    Ok(HttpResponse::Ok().json(result))
 }
 
 #[get("/data/{menu_id}")]
-pub async fn get_chart_data(pool: web::Data<Pool<ConnectionManager>>, menu_id: web::Path<String>) -> impl Responder {
+pub async fn get_chart_data(pool: web::Data<Pool<ConnectionManager>>, menu_id: web::Path<String>, auth: UserPermissions) -> Result<HttpResponse, AppError> {
 
     let params: String = menu_id.into_inner();
 
     // Cek apakah params kosong atau hanya spasi
     if params.trim().is_empty() {
-        return HttpResponse::BadRequest().json(json!({
-            "result": false,
-            "message": "Bad Request",
-            "error": "Menu ID is empty"
-        }));
+        return Err(AppError::BadRequest("Menu ID is empty".to_string()));
     }
 
-    let result: ActionResult<Vec<serde_json::Value>, String> = ChartService::get_chart_data(pool, params).await;
-
-    match result {
-        response if response.error.is_some() => {
-            HttpResponse::InternalServerError().json(response)
-        }, 
-        response if response.result => {
-            HttpResponse::Ok().json(response)
-        }, 
-        response => {
-            HttpResponse::BadRequest().json(response)
-        }
-    }
+    auth.require(&format!("menu:{}", params))?;
+
+    let result: ActionResult<Vec<serde_json::Value>, String> = ChartService::get_chart_data(pool, params).await?;
+
+    Ok(HttpResponse::Ok().json(result))
 }
 
 #[get("/bar")]
-pub async fn get_bar_chart(pool: web::Data<Pool<ConnectionManager>>, params: web::Query<BarChartParams>) -> impl Responder {
-
-    let result: ActionResult<Vec<serde_json::Value>, String> = ChartService::get_bar_chart(pool, params.into_inner()).await;
-
-    match result {
-        response if response.error.is_some() => {
-            HttpResponse::InternalServerError().json(response)
-        }, 
-        response if response.result => {
-            HttpResponse::Ok().json(response)
-        }, 
-        response => {
-            HttpResponse::BadRequest().json(response)
-        }
-    }
+pub async fn get_bar_chart(pool: web::Data<Pool<ConnectionManager>>, params: web::Query<BarChartParams>, auth: UserPermissions) -> Result<HttpResponse, AppError> {
+
+    auth.require(&format!("view:{}", params.tablename))?;
+    let scope = auth.row_scope(&params.tablename)?;
+    let masks = auth.column_masks(&params.tablename);
+    masks.require_unmasked(&params.column)?;
+
+    let mut params = params.into_inner();
+    params.filter = masks.strip_filter(params.filter);
+
+    let result: ActionResult<Vec<serde_json::Value>, String> = ChartService::get_bar_chart(pool, params, &scope).await?;
+
+    Ok(HttpResponse::Ok().json(result))
 }
 
 #[post("/delete-bar")]
-pub async fn delete_bar_chart(pool: web::Data<Pool<ConnectionManager>>, request: web::Json<DeleteBarChart>) -> impl Responder {
-
-    if let Err(errors) = request.validate() {
-        let formatted_errors: HashMap<String, String> = format_validation_errors(&errors);
-        
-        let result: ActionResult<HashMap<String, String>, _> = ActionResult {
-            result: false,
-            message: "Validation failed".to_string(),
-            data: None,
-            error: Some(formatted_errors),
-        };
-
-        return HttpResponse::BadRequest().json(result);
-    }
+pub async fn delete_bar_chart(pool: web::Data<Pool<ConnectionManager>>, request: web::Json<DeleteBarChart>, actor: AuditActor, auth: UserPermissions) -> Result<HttpResponse, AppError> {
 
-    let result: ActionResult<(), _> = ChartService::delete_bar_chart(pool, request.into_inner()).await;
+    auth.require("chart:edit")?;
+    request.validate()?;
 
-    match result {
-        response if response.error.is_some() => {
-            HttpResponse::InternalServerError().json(response)
-        }, // Jika error, HTTP 500
-        response if response.result => HttpResponse::Ok().json(response), // Jika berhasil, HTTP 200
-        response => HttpResponse::BadRequest().json(response), // Jika gagal, HTTP 400
-    }
-}
\ No newline at end of file
+    let result: ActionResult<(), _> = ChartService::delete_bar_chart(pool, request.into_inner(), &actor).await?;
+
+    Ok(HttpResponse::Ok().json(result))
+}
+
+/// WebSocket live chart: client kirim `{"action":"subscribe",...}`, server push agregasi yang berubah
+#[get("/ws")]
+pub async fn chart_socket(pool: web::Data<Pool<ConnectionManager>>, req: HttpRequest, body: web::Payload, auth: UserPermissions) -> Result<HttpResponse, AppError> {
+
+    let origin = req.headers().get("Origin").map(|value| value.to_str().unwrap_or_default());
+    ChartLiveService::check_origin(origin, req.connection_info().host())?;
+
+    let (response, session, stream) = actix_ws::handle(&req, body)
+        .map_err(|e| AppError::BadRequest(e.to_string()))?;
+
+    actix_web::rt::spawn(ChartLiveService::run_session(pool, session, stream, auth));
+
+    Ok(response)
+}
@@ -1,48 +1,185 @@
-use actix_web::{web, Responder, Scope, get, HttpResponse};
-use bb8::Pool;
-use bb8_tiberius::ConnectionManager;
+use actix_web::{web::{self, Bytes}, Scope, get, post, HttpRequest, HttpResponse};
+use validator::Validate;
+use futures::StreamExt;
 use serde_json::json;
+use bb8::Pool;
 
-use crate::{contexts::model::{ActionResult, HeaderParams, ResultList, TableDataParams}, services::data_service::DataService};
+use crate::{contexts::{auth::{AuditActor, UserPermissions}, connection::ConnectionManager, error::AppError, logger::write_log, model::{ActionResult, DistinctParams, ExportTableParams, HeaderParams, ResultList, RowRequest, TableDataParams, TableEventParams}, request_id::current_request_id}, services::{audit_service::{AuditEntry, AuditService}, data_service::DataService, row_service::RowService, setting_service::SettingService, table_event_service::TableEventService}};
 
 pub fn data_scope() -> Scope {
     web::scope("/data")
         .service(get_header)
         .service(get_table_data)
+        .service(get_table_events)
+        .service(export_table_data)
+        .service(get_distinct_values)
+        .service(insert_row)
+        .service(update_row)
+        .service(delete_row)
 }
 
 #[get("/header")]
-pub async fn get_header(pool: web::Data<Pool<ConnectionManager>>, params: web::Query<HeaderParams>) -> impl Responder {
-
-    let result: ActionResult<Vec<serde_json::Value>, String> = DataService::get_header(pool, params.into_inner().tablename).await;
-
-    match result {
-        response if response.error.is_some() => {
-            HttpResponse::InternalServerError().json(response)
-        }, 
-        response if response.result => {
-            HttpResponse::Ok().json(response)
-        }, 
-        response => {
-            HttpResponse::BadRequest().json(response)
-        }
-    }
+pub async fn get_header(pool: web::Data<Pool<ConnectionManager>>, params: web::Query<HeaderParams>, auth: UserPermissions) -> Result<HttpResponse, AppError> {
+
+    auth.require(&format!("view:{}", params.tablename))?;
+    let masks = auth.column_masks(&params.tablename);
+
+    let result: ActionResult<Vec<serde_json::Value>, String> = DataService::get_header(pool, params.into_inner().tablename, &masks).await?;
+
+    Ok(HttpResponse::Ok().json(result))
 }
 
 #[get("/get-table")]
-async fn get_table_data(params: web::Query<TableDataParams>, pool: web::Data<Pool<ConnectionManager>>) -> impl Responder {
+async fn get_table_data(params: web::Query<TableDataParams>, pool: web::Data<Pool<ConnectionManager>>, auth: UserPermissions) -> Result<HttpResponse, AppError> {
+
+    auth.require(&format!("view:{}", params.tablename))?;
+    let scope = auth.row_scope(&params.tablename)?;
+    let masks = auth.column_masks(&params.tablename);
+
+    let mut params = params.into_inner();
+    params.filter = masks.strip_filter(params.filter);
+
+    let data: ResultList = DataService::get_table_data(params, pool, &scope, &masks).await?;
+
+    Ok(HttpResponse::Ok().json(data))
+}
+
+#[get("/get-table/events")]
+async fn get_table_events(req: HttpRequest, params: web::Query<TableEventParams>, pool: web::Data<Pool<ConnectionManager>>, auth: UserPermissions) -> Result<HttpResponse, AppError> {
+
+    auth.require(&format!("view:{}", params.tablename))?;
+    let scope = auth.row_scope(&params.tablename)?;
+    let masks = auth.column_masks(&params.tablename);
+    // Nilai watermark & primary key dikirim mentah di `id:` dan bisa di-probe lewat Last-Event-ID
+    masks.require_unmasked(params.nidkey.as_deref().unwrap_or("AutoNID"))?;
+    masks.require_unmasked(params.watermark.as_deref().unwrap_or("LastUpdate"))?;
 
-    let data: Result<ResultList, Box<dyn std::error::Error>> = DataService::get_table_data(params.into_inner(), pool).await;
+    // EventSource mengirim Last-Event-ID otomatis saat reconnect
+    let last_event_id: Option<String> = req.headers()
+        .get("Last-Event-ID")
+        .and_then(|value| value.to_str().ok())
+        .map(|value| value.to_string());
 
-    match data {
-        Ok(response) => {
-            return HttpResponse::Ok().json(response);
-        },
+    let mut params = params.into_inner();
+    params.filter = masks.strip_filter(params.filter);
+
+    let events = TableEventService::subscribe(params, pool, last_event_id, &scope, masks).await?;
+
+    Ok(HttpResponse::Ok()
+        .content_type("text/event-stream")
+        .insert_header(("Cache-Control", "no-cache"))
+        .insert_header(("X-Accel-Buffering", "no"))
+        .streaming(events.map(Ok::<_, actix_web::Error>)))
+}
+
+#[get("/export")]
+async fn export_table_data(params: web::Query<ExportTableParams>, pool: web::Data<Pool<ConnectionManager>>, actor: AuditActor, auth: UserPermissions) -> Result<HttpResponse, AppError> {
+
+    auth.require(&format!("export:{}", params.tablename))?;
+    let scope = auth.row_scope(&params.tablename)?;
+    let masks = auth.column_masks(&params.tablename);
+
+    let mut params = params.into_inner();
+    params.filter = masks.strip_filter(params.filter);
+
+    let preferences = match params.format.as_deref() {
+        None | Some("ndjson") => None,
+        Some("csv") => Some(SettingService::preferences(&pool, auth.claims.auth_usernid).await?),
+        Some(format) => return Err(AppError::BadRequest(format!("Invalid export format '{}', use ndjson or csv", format))),
+    };
+    let content_type = match preferences {
+        Some(_) => "text/csv; charset=utf-8",
+        None => "application/x-ndjson",
+    };
+
+    AuditService::record(&pool, &actor, AuditEntry {
+        action: "export",
+        entity: &params.tablename,
+        entity_key: None,
+        before: None,
+        after: Some(json!({ "filter": params.filter, "sort": params.sort, "order": params.order, "format": params.format })),
+    }).await?;
+
+    let rows = DataService::export_table_data(params, pool, &scope, masks, preferences).await?;
+
+    // Error di tengah stream tidak bisa mengubah status lagi, jadi ditulis sebagai baris terakhir
+    let body = rows.map(|item| match item {
+        Ok(line) => Ok::<_, actix_web::Error>(line),
         Err(e) => {
-            return HttpResponse::InternalServerError().json(
-                json!({"error": e.to_string()})
-            );
-        },
-        
-    }
-}
\ No newline at end of file
+            write_log("ERROR", &format!("[{}] Export aborted: {}", e.code(), e));
+            let mut line = serde_json::to_vec(&json!({
+                "result": false,
+                "message": e.message(),
+                "error_code": e.code(),
+                "request_id": current_request_id()
+            })).unwrap_or_default();
+            line.push(b'\n');
+            Ok(Bytes::from(line))
+        }
+    });
+
+    Ok(HttpResponse::Ok()
+        .content_type(content_type)
+        .streaming(body))
+}
+
+#[get("/distinct")]
+async fn get_distinct_values(params: web::Query<DistinctParams>, pool: web::Data<Pool<ConnectionManager>>, auth: UserPermissions) -> Result<HttpResponse, AppError> {
+
+    auth.require(&format!("view:{}", params.tablename))?;
+    let scope = auth.row_scope(&params.tablename)?;
+    let masks = auth.column_masks(&params.tablename);
+    masks.require_unmasked(&params.column)?;
+
+    let mut params = params.into_inner();
+    params.filter = masks.strip_filter(params.filter);
+
+    let result: ActionResult<Vec<serde_json::Value>, String> = DataService::get_distinct_values(params, pool, &scope).await?;
+
+    Ok(HttpResponse::Ok().json(result))
+}
+
+#[post("/row/insert")]
+async fn insert_row(pool: web::Data<Pool<ConnectionManager>>, request: web::Json<RowRequest>, actor: AuditActor, auth: UserPermissions) -> Result<HttpResponse, AppError> {
+
+    request.validate()?;
+    auth.require(&format!("edit:{}", request.tablename.as_deref().unwrap_or_default()))?;
+    let scope = auth.row_scope(request.tablename.as_deref().unwrap_or_default())?;
+
+    let masks = auth.column_masks(request.tablename.as_deref().unwrap_or_default());
+
+    let mut result: ActionResult<serde_json::Value, String> = RowService::insert_row(pool, request.into_inner(), &actor, &scope).await?;
+    result.data = result.data.map(|row| masks.apply(row));
+
+    Ok(HttpResponse::Ok().json(result))
+}
+
+#[post("/row/update")]
+async fn update_row(pool: web::Data<Pool<ConnectionManager>>, request: web::Json<RowRequest>, actor: AuditActor, auth: UserPermissions) -> Result<HttpResponse, AppError> {
+
+    request.validate()?;
+    auth.require(&format!("edit:{}", request.tablename.as_deref().unwrap_or_default()))?;
+    let scope = auth.row_scope(request.tablename.as_deref().unwrap_or_default())?;
+
+    let masks = auth.column_masks(request.tablename.as_deref().unwrap_or_default());
+
+    let mut result: ActionResult<serde_json::Value, String> = RowService::update_row(pool, request.into_inner(), &actor, &scope).await?;
+    result.data = result.data.map(|row| masks.apply(row));
+
+    Ok(HttpResponse::Ok().json(result))
+}
+
+#[post("/row/delete")]
+async fn delete_row(pool: web::Data<Pool<ConnectionManager>>, request: web::Json<RowRequest>, actor: AuditActor, auth: UserPermissions) -> Result<HttpResponse, AppError> {
+
+    request.validate()?;
+    auth.require(&format!("edit:{}", request.tablename.as_deref().unwrap_or_default()))?;
+    let scope = auth.row_scope(request.tablename.as_deref().unwrap_or_default())?;
+
+    let masks = auth.column_masks(request.tablename.as_deref().unwrap_or_default());
+
+    let mut result: ActionResult<serde_json::Value, String> = RowService::delete_row(pool, request.into_inner(), &actor, &scope).await?;
+    result.data = result.data.map(|row| masks.apply(row));
+
+    Ok(HttpResponse::Ok().json(result))
+}
@@ -1,9 +1,8 @@
-use actix_web::{get, web, HttpResponse, Responder, Scope};
+use actix_web::{get, web, HttpResponse, Scope};
 use bb8::Pool;
-use bb8_tiberius::ConnectionManager;
 use serde::Deserialize;
 
-use crate::{contexts::model::{ActionResult, Company, Order}, services::generic_service::GenericService};
+use crate::{contexts::{auth::UserPermissions, connection::ConnectionManager, error::AppError, model::{ActionResult, Company, Order}}, services::generic_service::GenericService};
 
 pub fn generic_scope() -> Scope {
     web::scope("/generic")
@@ -12,21 +11,11 @@ pub fn generic_scope() -> Scope {
 }
 
 #[get("/company")]
-pub async fn get_company(pool: web::Data<Pool<ConnectionManager>>) -> impl Responder {
-
-    let result: ActionResult<Company, _> = GenericService::get_company(pool).await;
-
-    match result {
-        response if response.error.is_some() => {
-            HttpResponse::InternalServerError().json(response)
-        }, 
-        response if response.result => {
-            HttpResponse::Ok().json(response)
-        }, 
-        response => {
-            HttpResponse::BadRequest().json(response)
-        }
-    }
+pub async fn get_company(pool: web::Data<Pool<ConnectionManager>>, _auth: UserPermissions) -> Result<HttpResponse, AppError> {
+
+    let result: ActionResult<Company, _> = GenericService::get_company(pool).await?;
+
+    Ok(HttpResponse::Ok().json(result))
 }
 
 #[derive(Debug, Deserialize)]
@@ -39,19 +28,14 @@ pub struct OrderQueryParams {
 pub async fn get_orders(
     pool: web::Data<Pool<ConnectionManager>>,
     query: web::Query<OrderQueryParams>,
-) -> impl Responder {
+    auth: UserPermissions,
+) -> Result<HttpResponse, AppError> {
+    auth.require("view:CIFLookup")?;
+    let scope = auth.row_scope("CIFLookup")?;
+    let masks = auth.column_masks("CIFLookup");
+
     let result: ActionResult<Vec<Order>, _> =
-        GenericService::get_orders(pool, query.last_id, query.limit).await;
-
-    match result {
-        response if response.error.is_some() => {
-            HttpResponse::InternalServerError().json(response)
-        }
-        response if response.result => {
-            HttpResponse::Ok().json(response)
-        }
-        response => {
-            HttpResponse::BadRequest().json(response)
-        }
-    }
+        GenericService::get_orders(pool, query.last_id, query.limit, &scope, &masks).await?;
+
+    Ok(HttpResponse::Ok().json(result))
 }
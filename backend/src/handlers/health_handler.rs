@@ -0,0 +1,33 @@
+use actix_web::{get, web, HttpResponse, Responder, Scope};
+use serde_json::json;
+
+use crate::{contexts::{connection::DbPool, model::{ActionResult, ReadinessStatus}}, services::health_service::HealthService};
+
+pub fn health_scope() -> Scope {
+    web::scope("/health")
+        .service(liveness)
+        .service(readiness)
+}
+
+#[get("/live")]
+pub async fn liveness() -> impl Responder {
+    HttpResponse::Ok().json(json!({
+        "result": true,
+        "message": "Alive"
+    }))
+}
+
+#[get("/ready")]
+pub async fn readiness(pool: web::Data<DbPool>) -> impl Responder {
+
+    let result: ActionResult<ReadinessStatus, String> = HealthService::check_readiness(pool).await;
+
+    match result {
+        response if response.result => {
+            HttpResponse::Ok().json(response)
+        },
+        response => {
+            HttpResponse::ServiceUnavailable().json(response)
+        }
+    }
+}
@@ -0,0 +1,49 @@
+use actix_files::Files;
+use actix_web::{dev::HttpServiceFactory, get, http::header, middleware::DefaultHeaders, post, web, HttpRequest, HttpResponse, Scope};
+use bb8::Pool;
+use validator::Validate;
+
+use crate::{contexts::{auth::{encode_claims, token_cookie, AuditActor, TOKEN_COOKIE}, connection::ConnectionManager, error::AppError, model::{ActionResult, Claims, ProfileInfo, UpdateProfileRequest}}, services::{generic_service::GenericService, profile_service::{ProfileService, AVATAR_URL_PATH}}};
+
+/// Batas body JSON untuk upload avatar (gambar 5MB dalam base64)
+const PROFILE_JSON_LIMIT: usize = 8 * 1024 * 1024;
+
+pub fn profile_scope() -> Scope {
+    web::scope("/profile")
+        .app_data(web::JsonConfig::default().limit(PROFILE_JSON_LIMIT).error_handler(GenericService::json_error_handler))
+        .service(get_profile)
+        .service(update_profile)
+}
+
+/// File avatar statis. Nama file selalu baru setiap upload, jadi boleh di-cache selamanya.
+pub fn avatar_scope() -> impl HttpServiceFactory {
+    web::scope(AVATAR_URL_PATH)
+        .wrap(DefaultHeaders::new().add((header::CACHE_CONTROL, "public, max-age=31536000, immutable")))
+        .service(Files::new("", ProfileService::avatar_dir()))
+}
+
+#[get("")]
+pub async fn get_profile(pool: web::Data<Pool<ConnectionManager>>, claims: Claims) -> Result<HttpResponse, AppError> {
+
+    let result: ActionResult<ProfileInfo, String> = ProfileService::get(&pool, claims.auth_usernid).await?;
+
+    Ok(HttpResponse::Ok().json(result))
+}
+
+/// Update profil. Access token di cookie diterbitkan ulang supaya `picture` / `mobile_phone` di claims ikut berubah
+#[post("")]
+pub async fn update_profile(req: HttpRequest, pool: web::Data<Pool<ConnectionManager>>, request: web::Json<UpdateProfileRequest>, claims: Claims, actor: AuditActor) -> Result<HttpResponse, AppError> {
+
+    request.validate()?;
+
+    let result: ActionResult<ProfileInfo, String> = ProfileService::update(&pool, claims.auth_usernid, request.into_inner(), &actor).await?;
+
+    let mut response = HttpResponse::Ok();
+    if req.cookie(TOKEN_COOKIE).is_some() {
+        let refreshed = ProfileService::refreshed_claims(&pool, &claims).await?;
+        let max_age = refreshed.expired_token - chrono::Utc::now().timestamp();
+        response.cookie(token_cookie(encode_claims(&refreshed)?, max_age));
+    }
+
+    Ok(response.json(result))
+}
@@ -0,0 +1,40 @@
+use actix_web::{get, post, web, HttpResponse, Scope};
+use bb8::Pool;
+
+use crate::{contexts::{auth::{AuditActor, UserPermissions}, connection::ConnectionManager, error::AppError, model::{ActionResult, SettingSchema, UpdateSettingsRequest}, preferences::UserPreferences}, services::setting_service::SettingService};
+
+pub fn setting_scope() -> Scope {
+    web::scope("/settings")
+        .service(get_settings)
+        .service(get_setting_schema)
+        .service(update_settings)
+}
+
+/// Semua preferensi user login, key yang belum diatur berisi default
+#[get("")]
+pub async fn get_settings(pool: web::Data<Pool<ConnectionManager>>, auth: UserPermissions) -> Result<HttpResponse, AppError> {
+
+    auth.require_login()?;
+
+    let result: ActionResult<UserPreferences, String> = SettingService::get(&pool, auth.claims.auth_usernid).await?;
+
+    Ok(HttpResponse::Ok().json(result))
+}
+
+#[get("/schema")]
+pub async fn get_setting_schema() -> Result<HttpResponse, AppError> {
+
+    let result: ActionResult<Vec<SettingSchema>, String> = SettingService::schema();
+
+    Ok(HttpResponse::Ok().json(result))
+}
+
+#[post("")]
+pub async fn update_settings(pool: web::Data<Pool<ConnectionManager>>, request: web::Json<UpdateSettingsRequest>, actor: AuditActor, auth: UserPermissions) -> Result<HttpResponse, AppError> {
+
+    auth.require_login()?;
+
+    let result: ActionResult<UserPreferences, String> = SettingService::update(&pool, auth.claims.auth_usernid, request.into_inner(), &actor).await?;
+
+    Ok(HttpResponse::Ok().json(result))
+}
@@ -1,8 +1,8 @@
 use actix_cors::Cors;
-use actix_web::{ get, http::{self}, middleware::{self}, web::{self, route}, App, HttpServer};
-use contexts::{api_docs::ApiDoc, connection::{create_pool, DbPool}, logger::write_log};
-use handlers::{chart_handler::chart_scope, data_handler::data_scope, generic_handler::generic_scope};
-use services::generic_service::{self};
+use actix_web::{ get, http::{self}, middleware::{self}, web::{self, route}, App, HttpResponse, HttpServer, Responder};
+use contexts::{api_docs::ApiDoc, auth::{api_key_middleware, API_KEY_HEADER}, config::APP_CONFIG, connection::{create_pool, wait_for_database, DbPool}, logger::write_log, mailer::mailer_from_env, metrics::{metrics_middleware, METRICS}, rate_limit::{api_key_ip_limit_middleware, rate_limit_middleware}, request_id::{request_id_middleware, REQUEST_ID_HEADER}};
+use handlers::{api_key_handler::api_key_scope, profile_handler::{avatar_scope, profile_scope}, setting_handler::setting_scope, auth_handler::auth_scope, chart_handler::chart_scope, data_handler::data_scope, generic_handler::generic_scope, health_handler::health_scope, admin_handler::admin_scope};
+use services::{generic_service::{self}, profile_service::ProfileService};
 use utoipa::OpenApi;
 use utoipa_swagger_ui::SwaggerUi;
 
@@ -11,12 +11,29 @@ mod contexts {
     pub mod  model;
     pub mod logger;
     pub mod api_docs;
+    pub mod request_id;
+    pub mod metrics;
+    pub mod error;
+    pub mod config;
+    pub mod cache;
+    pub mod auth;
+    pub mod row_scope;
+    pub mod column_mask;
+    pub mod mailer;
+    pub mod rate_limit;
+    pub mod preferences;
 }
 
 mod handlers {
     pub mod generic_handler;
     pub mod data_handler;
     pub mod chart_handler;
+    pub mod health_handler;
+    pub mod admin_handler;
+    pub mod auth_handler;
+    pub mod api_key_handler;
+    pub mod profile_handler;
+    pub mod setting_handler;
 }
 
 mod services {
@@ -24,6 +41,20 @@ mod services {
     pub mod data_service;
     pub mod chart_service;
     pub mod validation_service;
+    pub mod health_service;
+    pub mod table_event_service;
+    pub mod chart_live_service;
+    pub mod metadata_service;
+    pub mod row_service;
+    pub mod audit_service;
+    pub mod permission_service;
+    pub mod credential_service;
+    pub mod session_service;
+    pub mod totp_service;
+    pub mod account_service;
+    pub mod api_key_service;
+    pub mod profile_service;
+    pub mod setting_service;
 }
 
 #[get("/")]
@@ -31,11 +62,37 @@ async fn health_check() -> String {
     format!("Welcome to the UBS trade dashboard!")
 }
 
+#[get("/metrics")]
+async fn metrics(pool: web::Data<DbPool>) -> impl Responder {
+    HttpResponse::Ok()
+        .content_type("text/plain; version=0.0.4")
+        .body(METRICS.render(&pool))
+}
+
 #[actix_web::main]
 async fn main() -> std::io::Result<()> {
     env_logger::init(); // Aktifkan logging
     dotenvy::dotenv().ok();
-    let db_pool: DbPool = create_pool("db12877").await.unwrap();
+
+    // Config dibaca sebelum server jalan, supaya config.json yang rusak menghentikan startup
+    once_cell::sync::Lazy::force(&APP_CONFIG);
+
+    let db_pool: DbPool = create_pool("db12877").await.map_err(|e| {
+        eprintln!("Failed to create database pool: {}", e);
+        std::io::Error::other(e.to_string())
+    })?;
+
+    if !wait_for_database(&db_pool).await {
+        write_log("WARN", "Database is not reachable, starting anyway. Check /health/ready");
+        println!("⚠️ Database is not reachable, check /health/ready");
+    }
+
+    let mailer = mailer_from_env();
+
+    // Folder avatar harus ada sebelum di-serve oleh actix-files
+    if let Err(e) = std::fs::create_dir_all(ProfileService::avatar_dir()) {
+        write_log("WARN", &format!("Failed to create avatar directory: {}", e));
+    }
 
     write_log("INFO", "Test log message: Logging is working");
     println!("🚀 Application started");
@@ -48,25 +105,42 @@ async fn main() -> std::io::Result<()> {
             .allowed_methods(vec!["GET", "POST"])
             .allowed_headers(vec![http::header::AUTHORIZATION, http::header::ACCEPT])
             .allowed_header(http::header::CONTENT_TYPE)
+            .allowed_header(REQUEST_ID_HEADER)
+            .allowed_header(API_KEY_HEADER)
+            .allowed_header("last-event-id")
+            .expose_headers(vec![REQUEST_ID_HEADER])
             .supports_credentials()
             .max_age(3600);
         App::new()
             .service(web::scope("/api/v1")
+            .service(auth_scope())
             .service(generic_scope())
             .service(data_scope())
             .service(chart_scope())
+            .service(admin_scope())
+            .service(api_key_scope())
+            .service(profile_scope())
+            .service(setting_scope())
         )
         .app_data(web::Data::new(db_pool.clone()))
+        .app_data(web::Data::from(mailer.clone()))
         .app_data(web::JsonConfig::default().error_handler(generic_service::GenericService::json_error_handler))
         .service(health_check)
+        .service(health_scope())
+        .service(metrics)
+        .service(avatar_scope())
         .service(
             SwaggerUi::new("/docs/{_:.*}")
                 .url("/api-docs/openapi.json", ApiDoc::openapi())
         )
         .default_service(route().to(generic_service::GenericService::not_found))
-        .wrap(middleware::Logger::default()) // Logging middleware
+        .wrap(middleware::from_fn(rate_limit_middleware)) // Rate limit & batas query bersamaan per user
+        .wrap(middleware::from_fn(api_key_middleware)) // Autentikasi header X-API-Key
+        .wrap(middleware::from_fn(api_key_ip_limit_middleware)) // Rate limit per IP sebelum API key dicek ke database
+        .wrap(middleware::from_fn(metrics_middleware)) // Prometheus metrics per route
+        .wrap(middleware::from_fn(request_id_middleware)) // Request ID untuk korelasi log
+        .wrap(middleware::Logger::new(r#"%a "%r" %s %b "%{Referer}i" "%{User-Agent}i" %T req_id=%{x-request-id}o"#)) // Logging middleware
         .wrap(middleware::NormalizePath::trim()) // 🔥 Normalisasi path (opsional)
-        .wrap(middleware::Logger::default())
         .wrap(cors)
     })
     .bind(("127.0.0.1", 8001))?
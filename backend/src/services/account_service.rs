@@ -0,0 +1,220 @@
+use std::{env, sync::Arc};
+
+use base64::{engine::general_purpose, Engine as _};
+use bb8::Pool;
+use bb8_tiberius::rt::Client;
+use rand::Rng;
+use serde_json::json;
+use sha2::{Digest, Sha256};
+
+use crate::contexts::{auth::AuditActor, connection::{ConnectionManager, Transaction}, error::AppError, logger::write_log, mailer::{EmailMessage, Mailer}, model::{ActionResult, Claims}};
+use super::{audit_service::{AuditEntry, AuditService}, credential_service::CredentialService, session_service::SessionService};
+
+const PASSWORD_RESET: &str = "password_reset";
+const VERIFY_EMAIL: &str = "verify_email";
+
+pub struct AccountService;
+
+impl AccountService {
+    /// URL frontend untuk link di email (`APP_BASE_URL`)
+    fn link(path: &str, token: &str) -> String {
+        let base_url = env::var("APP_BASE_URL").unwrap_or_else(|_| "http://localhost:5173".to_string());
+        format!("{}/{}?token={}", base_url.trim_end_matches('/'), path, token)
+    }
+
+    /// Umur token: `PASSWORD_RESET_TTL_SECS` (default 1 jam) / `EMAIL_VERIFY_TTL_SECS` (default 24 jam)
+    fn ttl_secs(purpose: &str) -> i32 {
+        match purpose {
+            PASSWORD_RESET => env::var("PASSWORD_RESET_TTL_SECS").ok().and_then(|v| v.parse().ok()).unwrap_or(3600),
+            _ => env::var("EMAIL_VERIFY_TTL_SECS").ok().and_then(|v| v.parse().ok()).unwrap_or(24 * 3600),
+        }
+    }
+
+    fn new_token() -> (String, String) {
+        let mut bytes = [0u8; 32];
+        rand::rng().fill(&mut bytes[..]);
+        let token = general_purpose::URL_SAFE_NO_PAD.encode(bytes);
+        let hash = Self::hash_token(&token);
+
+        (token, hash)
+    }
+
+    fn hash_token(token: &str) -> String {
+        Sha256::digest(token.trim().as_bytes()).iter().map(|b| format!("{:02x}", b)).collect()
+    }
+
+    /// Token baru untuk user, token lama dengan purpose sama langsung kedaluwarsa.
+    /// `None` jika alamat email sudah menerima `MAIL_RATE_LIMIT_PER_HOUR` (default 3) email dalam satu jam terakhir.
+    async fn issue_token(conn: &mut Client, user_nid: i32, email: &str, purpose: &str) -> Result<Option<String>, AppError> {
+        let limit: i32 = env::var("MAIL_RATE_LIMIT_PER_HOUR").ok().and_then(|v| v.parse().ok()).unwrap_or(3);
+
+        let sent: i32 = conn.query(
+            "SELECT COUNT(*) AS Sent FROM [dbo].[AuthToken] WHERE Email = @P1 AND Purpose = @P2 AND CreatedAt > DATEADD(hour, -1, GETDATE())",
+            &[&email, &purpose],
+        ).await?.into_row().await?
+            .and_then(|row| row.get("Sent"))
+            .unwrap_or_default();
+
+        if sent >= limit {
+            write_log("WARN", &format!("Mail rate limit reached for {} ({})", email, purpose));
+            return Ok(None);
+        }
+
+        let (token, token_hash) = Self::new_token();
+        conn.execute(
+            r#"UPDATE [dbo].[AuthToken] SET ExpiresAt = GETDATE()
+            WHERE AuthUserNID = @P1 AND Purpose = @P2 AND UsedAt IS NULL AND ExpiresAt > GETDATE();
+            INSERT INTO [dbo].[AuthToken] ([TokenHash],[AuthUserNID],[Email],[Purpose],[ExpiresAt])
+            VALUES (@P3,@P1,@P4,@P2,DATEADD(second, @P5, GETDATE()))"#,
+            &[&user_nid, &purpose, &token_hash, &email, &Self::ttl_secs(purpose)],
+        ).await?;
+
+        Ok(Some(token))
+    }
+
+    /// Kirim email di background supaya waktu respons tidak bergantung pada SMTP atau ada tidaknya akun
+    fn send_in_background(mailer: Arc<dyn Mailer>, message: EmailMessage) {
+        tokio::spawn(async move {
+            if let Err(e) = mailer.send(&message).await {
+                write_log("ERROR", &format!("[{}] Failed to send email to {}: {}", e.code(), message.to, e));
+            }
+        });
+    }
+
+    fn render(email: &str, subject: &str, template: &str, link: String, purpose: &str) -> Result<EmailMessage, AppError> {
+        let data = json!({
+            "app_name": env::var("APP_NAME").unwrap_or_else(|_| "UBS Trade Dashboard".to_string()),
+            "email": email,
+            "link": link,
+            "expires_minutes": Self::ttl_secs(purpose) / 60,
+        });
+
+        EmailMessage::from_template(email, subject, template, &data)
+    }
+
+    /// Kirim link reset password. Respons selalu sama, baik email terdaftar maupun tidak.
+    pub async fn request_password_reset(connection: &Pool<ConnectionManager>, mailer: Arc<dyn Mailer>, email: &str, actor: &AuditActor) -> Result<ActionResult<(), String>, AppError> {
+        let mut conn = connection.get().await?;
+        let user = conn.query(
+            "SELECT AuthUserNID, Email FROM [dbo].[AuthUser] WHERE Email = @P1 AND DisabledLogin = 0",
+            &[&email],
+        ).await?.into_row().await?
+            .map(|row| (row.get::<i32, _>("AuthUserNID").unwrap_or_default(), row.get::<&str, _>("Email").unwrap_or_default().to_string()));
+
+        if let Some((user_nid, email)) = user {
+            if let Some(token) = Self::issue_token(&mut conn, user_nid, &email, PASSWORD_RESET).await? {
+                let message = Self::render(&email, "Reset password", "password_reset", Self::link("reset-password", &token), PASSWORD_RESET)?;
+                Self::send_in_background(mailer, message);
+
+                let actor = AuditActor { user_nid: Some(user_nid), email: Some(email.clone()), ip_address: actor.ip_address.clone() };
+                AuditService::write(&mut conn, &actor, AuditEntry { action: "password_reset_request", entity: "AuthUser", entity_key: Some(user_nid.to_string()), before: None, after: None }).await?;
+            }
+        }
+
+        Ok(ActionResult {
+            result: true,
+            message: "If the email is registered, a password reset link has been sent".to_string(),
+            ..Default::default()
+        })
+    }
+
+    /// Ganti password dengan token reset (sekali pakai). Semua sesi user dicabut dan penguncian akun dibuka.
+    pub async fn reset_password(connection: &Pool<ConnectionManager>, token: &str, password: &str, actor: &AuditActor) -> Result<(), AppError> {
+        let token_hash = Self::hash_token(token);
+        let password_hash = CredentialService::hash_blocking(password).await?;
+
+        let trans = Transaction::begin(connection).await?;
+        {
+            let mut conn_guard = trans.conn.lock().await;
+            let conn = conn_guard.as_mut()
+                .ok_or_else(|| AppError::Internal("Failed to get connection from pool".to_string()))?;
+
+            let (user_nid, email) = Self::consume_token(conn, &token_hash, PASSWORD_RESET).await?
+                .ok_or_else(|| AppError::BadRequest("Reset link is invalid or has expired".to_string()))?;
+
+            // Link reset yang berhasil dipakai sekaligus membuktikan kepemilikan email
+            conn.execute(
+                r#"UPDATE [dbo].[AuthUser] SET PasswordHash = @P2, FailedLoginCount = 0, LockedUntil = NULL, LastUpdate = GETDATE(),
+                    EmailVerified = 1, EmailVerifiedAt = COALESCE(EmailVerifiedAt, GETDATE())
+                WHERE AuthUserNID = @P1;
+                UPDATE [dbo].[AuthToken] SET ExpiresAt = GETDATE()
+                WHERE AuthUserNID = @P1 AND Purpose = @P3 AND UsedAt IS NULL AND ExpiresAt > GETDATE()"#,
+                &[&user_nid, &password_hash, &PASSWORD_RESET],
+            ).await?;
+            SessionService::revoke_all(conn, user_nid, PASSWORD_RESET).await?;
+
+            let actor = AuditActor { user_nid: Some(user_nid), email: Some(email), ip_address: actor.ip_address.clone() };
+            AuditService::write(conn, &actor, AuditEntry { action: "password_reset", entity: "AuthUser", entity_key: Some(user_nid.to_string()), before: None, after: None }).await?;
+        }
+        trans.commit().await?;
+
+        Ok(())
+    }
+
+    /// Tandai token terpakai jika masih berlaku, hasilnya pemilik token
+    async fn consume_token(conn: &mut Client, token_hash: &str, purpose: &str) -> Result<Option<(i32, String)>, AppError> {
+        let row = conn.query(
+            r#"UPDATE [dbo].[AuthToken] SET UsedAt = GETDATE()
+            OUTPUT inserted.AuthUserNID, inserted.Email
+            WHERE TokenHash = @P1 AND Purpose = @P2 AND UsedAt IS NULL AND ExpiresAt > GETDATE()"#,
+            &[&token_hash, &purpose],
+        ).await?.into_row().await?;
+
+        Ok(row.map(|row| (row.get::<i32, _>("AuthUserNID").unwrap_or_default(), row.get::<&str, _>("Email").unwrap_or_default().to_string())))
+    }
+
+    /// Kirim link verifikasi ke email user login
+    pub async fn send_verification(connection: &Pool<ConnectionManager>, mailer: Arc<dyn Mailer>, claims: &Claims, actor: &AuditActor) -> Result<ActionResult<(), String>, AppError> {
+        let mut conn = connection.get().await?;
+        let verified = conn.query("SELECT EmailVerified FROM [dbo].[AuthUser] WHERE AuthUserNID = @P1", &[&claims.auth_usernid])
+            .await?.into_row().await?
+            .and_then(|row| row.get::<bool, _>("EmailVerified"))
+            .ok_or_else(|| AppError::NotFound("User not found".to_string()))?;
+
+        if verified {
+            return Err(AppError::Conflict("Email is already verified".to_string()));
+        }
+
+        let token = Self::issue_token(&mut conn, claims.auth_usernid, &claims.email, VERIFY_EMAIL).await?
+            .ok_or_else(|| AppError::TooManyRequests("Too many verification emails requested, try again later".to_string(), 3600))?;
+        let message = Self::render(&claims.email, "Verifikasi email", "verify_email", Self::link("verify-email", &token), VERIFY_EMAIL)?;
+        Self::send_in_background(mailer, message);
+
+        AuditService::write(&mut conn, actor, AuditEntry { action: "verify_email_request", entity: "AuthUser", entity_key: Some(claims.auth_usernid.to_string()), before: None, after: None }).await?;
+
+        Ok(ActionResult {
+            result: true,
+            message: "Verification email has been sent".to_string(),
+            ..Default::default()
+        })
+    }
+
+    /// Verifikasi email dengan token. Ditolak jika email user sudah berubah sejak token dikirim.
+    pub async fn verify_email(connection: &Pool<ConnectionManager>, token: &str, actor: &AuditActor) -> Result<(), AppError> {
+        let token_hash = Self::hash_token(token);
+
+        let trans = Transaction::begin(connection).await?;
+        {
+            let mut conn_guard = trans.conn.lock().await;
+            let conn = conn_guard.as_mut()
+                .ok_or_else(|| AppError::Internal("Failed to get connection from pool".to_string()))?;
+
+            let invalid = || AppError::BadRequest("Verification link is invalid or has expired".to_string());
+            let (user_nid, email) = Self::consume_token(conn, &token_hash, VERIFY_EMAIL).await?.ok_or_else(invalid)?;
+
+            let affected = conn.execute(
+                "UPDATE [dbo].[AuthUser] SET EmailVerified = 1, EmailVerifiedAt = GETDATE() WHERE AuthUserNID = @P1 AND Email = @P2",
+                &[&user_nid, &email],
+            ).await?.total();
+            if affected == 0 {
+                return Err(invalid());
+            }
+
+            let actor = AuditActor { user_nid: Some(user_nid), email: Some(email), ip_address: actor.ip_address.clone() };
+            AuditService::write(conn, &actor, AuditEntry { action: "email_verified", entity: "AuthUser", entity_key: Some(user_nid.to_string()), before: None, after: None }).await?;
+        }
+        trans.commit().await?;
+
+        Ok(())
+    }
+}
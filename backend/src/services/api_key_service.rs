@@ -0,0 +1,198 @@
+use std::{collections::HashMap, env, sync::{Arc, Mutex}, time::{Duration, Instant}};
+
+use base64::{engine::general_purpose, Engine as _};
+use bb8::Pool;
+use once_cell::sync::Lazy;
+use rand::Rng;
+use sha2::{Digest, Sha256};
+use tiberius::Row;
+
+use crate::contexts::{auth::{AuditActor, UserPermissions}, connection::ConnectionManager, error::AppError, model::{ActionResult, ApiKeyCreated, ApiKeyInfo, Claims, CreateApiKeyRequest}};
+use super::{audit_service::{AuditEntry, AuditService}, credential_service::CredentialService, permission_service::UserAccess};
+
+const KEY_PREFIX: &str = "ubs_";
+const PREFIX_ALPHABET: &[u8] = b"abcdefghijklmnopqrstuvwxyz0123456789";
+
+/// Pemilik dan scope API key yang sudah diverifikasi, disimpan di request extensions oleh `api_key_middleware`
+pub struct ApiKeyIdentity {
+    pub api_key_nid: i32,
+    /// Claims pemilik key, dipakai untuk row filter dan audit
+    pub claims: Claims,
+    /// Permission yang diizinkan untuk key ini, akses efektif = permission pemilik ∩ scopes
+    pub scopes: Arc<UserAccess>,
+}
+
+/// (waktu verifikasi, identitas) per hash key
+type KeyCache = HashMap<String, (Instant, Arc<ApiKeyIdentity>)>;
+
+/// Cache key yang valid supaya tidak query ke database di setiap request.
+/// `LastUsedAt` ikut diperbarui setiap cache di-refresh. Di deployment multi-instance, key yang dicabut
+/// tetap diterima instance lain paling lama `API_KEY_CACHE_TTL_SECS`.
+static KEY_CACHE: Lazy<Mutex<KeyCache>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+pub struct ApiKeyService;
+
+impl ApiKeyService {
+    /// Key baru `ubs_<prefix>_<secret>`: (key, prefix, hash)
+    fn generate() -> (String, String, String) {
+        let mut rng = rand::rng();
+        let prefix: String = (0..8).map(|_| PREFIX_ALPHABET[rng.random_range(0..PREFIX_ALPHABET.len())] as char).collect();
+
+        let mut secret = [0u8; 32];
+        rng.fill(&mut secret[..]);
+        let key = format!("{}{}_{}", KEY_PREFIX, prefix, general_purpose::URL_SAFE_NO_PAD.encode(secret));
+        let hash = Self::hash_key(&key);
+
+        (key, prefix, hash)
+    }
+
+    fn hash_key(key: &str) -> String {
+        Sha256::digest(key.trim().as_bytes()).iter().map(|b| format!("{:02x}", b)).collect()
+    }
+
+    fn parse_scopes(scopes: &str) -> Vec<String> {
+        scopes.split(';').map(str::trim).filter(|s| !s.is_empty()).map(str::to_string).collect()
+    }
+
+    fn info_from_row(row: &Row) -> ApiKeyInfo {
+        ApiKeyInfo {
+            api_key_nid: row.get("ApiKeyNID").unwrap_or_default(),
+            name: row.get::<&str, _>("Name").unwrap_or_default().to_string(),
+            prefix: row.get::<&str, _>("Prefix").unwrap_or_default().to_string(),
+            scopes: Self::parse_scopes(row.get::<&str, _>("Scopes").unwrap_or_default()),
+            created_at: row.get("CreatedAt"),
+            expires_at: row.get("ExpiresAt"),
+            last_used_at: row.get("LastUsedAt"),
+            last_used_ip: row.get::<&str, _>("LastUsedIp").map(str::to_string),
+            revoked_at: row.get("RevokedAt"),
+        }
+    }
+
+    /// Verifikasi key dari header `X-API-Key`. Key yang dicabut / kedaluwarsa / pemiliknya nonaktif ditolak.
+    pub async fn authenticate(connection: &Pool<ConnectionManager>, key: &str, ip_address: Option<String>) -> Result<Arc<ApiKeyIdentity>, AppError> {
+        let invalid = || AppError::Unauthorized("Invalid API key".to_string());
+        if !key.starts_with(KEY_PREFIX) {
+            return Err(invalid());
+        }
+
+        let key_hash = Self::hash_key(key);
+        let ttl = Duration::from_secs(env::var("API_KEY_CACHE_TTL_SECS").ok().and_then(|v| v.parse().ok()).unwrap_or(60));
+        if let Some((verified_at, identity)) = KEY_CACHE.lock().unwrap().get(&key_hash) {
+            if verified_at.elapsed() < ttl {
+                return Ok(identity.clone());
+            }
+        }
+
+        let mut conn = connection.get().await?;
+        let row = conn.query(
+            r#"UPDATE [dbo].[ApiKey] SET LastUsedAt = GETDATE(), LastUsedIp = @P2
+            OUTPUT inserted.ApiKeyNID, inserted.AuthUserNID, inserted.Prefix, inserted.Scopes
+            WHERE KeyHash = @P1 AND RevokedAt IS NULL AND (ExpiresAt IS NULL OR ExpiresAt > GETDATE())"#,
+            &[&key_hash, &ip_address],
+        ).await?.into_row().await?.ok_or_else(invalid)?;
+
+        let api_key_nid: i32 = row.get("ApiKeyNID").unwrap_or_default();
+        let prefix = row.get::<&str, _>("Prefix").unwrap_or_default().trim().to_string();
+        let scopes = UserAccess {
+            roles: Vec::new(),
+            permissions: Self::parse_scopes(row.get::<&str, _>("Scopes").unwrap_or_default())
+                .into_iter().map(|s| s.to_lowercase()).collect(),
+        };
+
+        let mut claims = CredentialService::load_claims(&mut conn, row.get("AuthUserNID").unwrap_or_default(), ip_address).await?;
+        claims.app_name = Some(format!("api-key:{}", prefix));
+
+        let identity = Arc::new(ApiKeyIdentity { api_key_nid, claims, scopes: Arc::new(scopes) });
+        let mut cache = KEY_CACHE.lock().unwrap();
+        cache.retain(|_, (verified_at, _)| verified_at.elapsed() < ttl);
+        cache.insert(key_hash, (Instant::now(), identity.clone()));
+
+        Ok(identity)
+    }
+
+    /// Buat key untuk user login. Setiap scope harus dimiliki user sendiri, dan `admin` tidak bisa didelegasikan.
+    pub async fn create(connection: &Pool<ConnectionManager>, auth: &UserPermissions, request: CreateApiKeyRequest, actor: &AuditActor) -> Result<ActionResult<ApiKeyCreated, String>, AppError> {
+        let scopes: Vec<String> = request.scopes.iter().map(|s| s.trim().to_string()).filter(|s| !s.is_empty()).collect();
+
+        if scopes.is_empty() {
+            return Err(AppError::BadRequest("At least one scope is required".to_string()));
+        }
+        for scope in &scopes {
+            if scope.eq_ignore_ascii_case("admin") || !scope.contains(':') || scope.contains(';') {
+                return Err(AppError::BadRequest(format!("Invalid scope '{}'", scope)));
+            }
+            auth.require(scope)?;
+        }
+
+        let default_days: i32 = env::var("API_KEY_DEFAULT_DAYS").ok().and_then(|v| v.parse().ok()).unwrap_or(90);
+        let expires_in_days = request.expires_in_days.unwrap_or(default_days);
+        let name = request.name.unwrap_or_default().trim().to_string();
+        let (key, prefix, key_hash) = Self::generate();
+
+        let mut conn = connection.get().await?;
+        let row = conn.query(
+            r#"INSERT INTO [dbo].[ApiKey] ([AuthUserNID],[Name],[Prefix],[KeyHash],[Scopes],[ExpiresAt])
+            OUTPUT inserted.ApiKeyNID, inserted.Name, inserted.Prefix, inserted.Scopes, inserted.CreatedAt,
+                inserted.ExpiresAt, inserted.LastUsedAt, inserted.LastUsedIp, inserted.RevokedAt
+            VALUES (@P1,@P2,@P3,@P4,@P5,DATEADD(day, @P6, GETDATE()))"#,
+            &[&auth.claims.auth_usernid, &name, &prefix, &key_hash, &scopes.join(";"), &expires_in_days],
+        ).await?.into_row().await?
+            .ok_or_else(|| AppError::Internal("Failed to create API key".to_string()))?;
+
+        let info = Self::info_from_row(&row);
+        AuditService::write(&mut conn, actor, AuditEntry {
+            action: "api_key_create",
+            entity: "ApiKey",
+            entity_key: Some(info.api_key_nid.to_string()),
+            before: None,
+            after: Some(serde_json::json!({ "name": info.name, "prefix": info.prefix, "scopes": info.scopes })),
+        }).await?;
+
+        Ok(ActionResult {
+            result: true,
+            message: "API key created, store it now because it will not be shown again".to_string(),
+            data: Some(ApiKeyCreated { api_key: key, info }),
+            ..Default::default()
+        })
+    }
+
+    pub async fn list(connection: &Pool<ConnectionManager>, user_nid: i32) -> Result<ActionResult<Vec<ApiKeyInfo>, String>, AppError> {
+        let mut conn = connection.get().await?;
+        let rows = conn.query(
+            r#"SELECT ApiKeyNID, Name, Prefix, Scopes, CreatedAt, ExpiresAt, LastUsedAt, LastUsedIp, RevokedAt
+            FROM [dbo].[ApiKey]
+            WHERE AuthUserNID = @P1
+            ORDER BY CreatedAt DESC"#,
+            &[&user_nid],
+        ).await?.into_first_result().await?;
+
+        Ok(ActionResult {
+            result: true,
+            message: "API keys retrieved successfully".to_string(),
+            data: Some(rows.iter().map(Self::info_from_row).collect()),
+            ..Default::default()
+        })
+    }
+
+    /// Cabut key milik sendiri, atau key siapa saja untuk user dengan permission `admin`
+    pub async fn revoke(connection: &Pool<ConnectionManager>, auth: &UserPermissions, api_key_nid: i32, actor: &AuditActor) -> Result<(), AppError> {
+        let owner_filter: Option<i32> = match auth.has("admin") {
+            true => None,
+            false => Some(auth.claims.auth_usernid),
+        };
+
+        let mut conn = connection.get().await?;
+        let affected = conn.execute(
+            r#"UPDATE [dbo].[ApiKey] SET RevokedAt = GETDATE()
+            WHERE ApiKeyNID = @P1 AND (@P2 IS NULL OR AuthUserNID = @P2) AND RevokedAt IS NULL"#,
+            &[&api_key_nid, &owner_filter],
+        ).await?.total();
+
+        if affected == 0 {
+            return Err(AppError::NotFound(format!("API key {} not found", api_key_nid)));
+        }
+
+        KEY_CACHE.lock().unwrap().retain(|_, (_, identity)| identity.api_key_nid != api_key_nid);
+        AuditService::write(&mut conn, actor, AuditEntry { action: "api_key_revoke", entity: "ApiKey", entity_key: Some(api_key_nid.to_string()), before: None, after: None }).await
+    }
+}
@@ -0,0 +1,126 @@
+use std::fmt::Write;
+
+use actix_web::web;
+use bb8::Pool;
+use bb8_tiberius::rt::Client;
+use chrono::NaiveDate;
+use serde_json::Value as JsonValue;
+use tiberius::ToSql;
+
+use crate::contexts::{auth::AuditActor, connection::{query_cancelled, query_timeout, ConnectionManager}, error::AppError, model::{ActionResult, AuditLogParams}, request_id::current_request_id};
+use super::data_service::DataService;
+
+/// Satu baris audit: aksi terhadap entity (view / table) beserta data sebelum dan sesudahnya
+pub struct AuditEntry<'a> {
+    pub action: &'a str,
+    pub entity: &'a str,
+    pub entity_key: Option<String>,
+    pub before: Option<JsonValue>,
+    pub after: Option<JsonValue>,
+}
+
+pub struct AuditService;
+
+impl AuditService {
+    /// Tulis audit memakai koneksi yang sedang dipakai, sehingga ikut transaksi write-nya
+    pub async fn write(conn: &mut Client, actor: &AuditActor, entry: AuditEntry<'_>) -> Result<(), AppError> {
+        let before = entry.before.map(|v| v.to_string());
+        let after = entry.after.map(|v| v.to_string());
+        let request_id = current_request_id();
+
+        conn.execute(
+            r#"INSERT INTO [dbo].[AuditLog]
+            ([UserNID],[Email],[IPAddress],[Action],[Entity],[EntityKey],[BeforeData],[AfterData],[RequestID])
+            VALUES
+            (@P1,@P2,@P3,@P4,@P5,@P6,@P7,@P8,@P9)"#,
+            &[
+                &actor.user_nid, &actor.email, &actor.ip_address, &entry.action, &entry.entity,
+                &entry.entity_key, &before, &after, &request_id
+            ],
+        ).await?;
+
+        Ok(())
+    }
+
+    /// Tulis audit di luar transaksi (export / download)
+    pub async fn record(connection: &Pool<ConnectionManager>, actor: &AuditActor, entry: AuditEntry<'_>) -> Result<(), AppError> {
+        let mut conn = connection.get().await?;
+        Self::write(&mut conn, actor, entry).await
+    }
+
+    pub async fn get_logs(connection: web::Data<Pool<ConnectionManager>>, params: AuditLogParams) -> Result<ActionResult<Vec<JsonValue>, String>, AppError> {
+        let mut result: ActionResult<Vec<JsonValue>, String> = ActionResult::default();
+
+        let mut query = String::from(
+            "SELECT [AuditLogNID], [CreatedAt], [UserNID], [Email], [IPAddress], [Action], [Entity], [EntityKey], [BeforeData], [AfterData], [RequestID] FROM [dbo].[AuditLog] WHERE 1=1"
+        );
+        let mut query_params: Vec<Box<dyn ToSql>> = Vec::new();
+
+        if let Some(user_nid) = params.user_nid {
+            query_params.push(Box::new(user_nid));
+            let _ = write!(query, " AND [UserNID] = @P{}", query_params.len());
+        }
+        if let Some(email) = params.email.filter(|v| !v.trim().is_empty()) {
+            query_params.push(Box::new(email));
+            let _ = write!(query, " AND [Email] = @P{}", query_params.len());
+        }
+        if let Some(entity) = params.entity.filter(|v| !v.trim().is_empty()) {
+            query_params.push(Box::new(entity));
+            let _ = write!(query, " AND [Entity] = @P{}", query_params.len());
+        }
+        if let Some(action) = params.action.filter(|v| !v.trim().is_empty()) {
+            query_params.push(Box::new(action));
+            let _ = write!(query, " AND [Action] = @P{}", query_params.len());
+        }
+        if let Some(from) = params.from.filter(|v| !v.trim().is_empty()) {
+            let from = NaiveDate::parse_from_str(from.trim(), "%Y-%m-%d")
+                .map_err(|_| AppError::BadRequest("Invalid 'from' date, expected YYYY-MM-DD".to_string()))?;
+            query_params.push(Box::new(from));
+            let _ = write!(query, " AND [CreatedAt] >= @P{}", query_params.len());
+        }
+        if let Some(to) = params.to.filter(|v| !v.trim().is_empty()) {
+            let to = NaiveDate::parse_from_str(to.trim(), "%Y-%m-%d")
+                .map_err(|_| AppError::BadRequest("Invalid 'to' date, expected YYYY-MM-DD".to_string()))?;
+            query_params.push(Box::new(to.succ_opt().unwrap_or(to)));
+            let _ = write!(query, " AND [CreatedAt] < @P{}", query_params.len());
+        }
+
+        let limit = params.limit.unwrap_or(100).clamp(1, 1000);
+        let _ = write!(
+            query,
+            " ORDER BY [CreatedAt] DESC, [AuditLogNID] DESC OFFSET {} ROWS FETCH NEXT {} ROWS ONLY",
+            params.offset.max(0), limit
+        );
+
+        let timeout = query_timeout("AUDIT");
+        let mut conn = connection.get().await?;
+
+        conn.mark_in_flight();
+        let param_refs: Vec<&dyn ToSql> = query_params.iter().map(|p| p.as_ref()).collect();
+        let rows = tokio::time::timeout(timeout, async {
+            conn.query(query, &param_refs).await?.into_first_result().await
+        }).await.map_err(|_| query_cancelled(timeout))??;
+        conn.mark_done();
+
+        result.data = Some(rows.iter().map(Self::log_to_json).collect());
+        result.result = true;
+        result.message = "Data retrieved successfully".to_string();
+
+        Ok(result)
+    }
+
+    fn log_to_json(row: &tiberius::Row) -> JsonValue {
+        let mut json = DataService::row_to_json(row);
+
+        // `AuditLogNID` bigint dan data before / after disimpan sebagai text JSON
+        if let Some(obj) = json.as_object_mut() {
+            obj.insert("AuditLogNID".to_string(), row.get::<i64, _>("AuditLogNID").into());
+            for key in ["BeforeData", "AfterData"] {
+                let parsed = row.get::<&str, _>(key).and_then(|v| serde_json::from_str::<JsonValue>(v).ok());
+                obj.insert(key.to_string(), parsed.unwrap_or(JsonValue::Null));
+            }
+        }
+
+        json
+    }
+}
@@ -0,0 +1,260 @@
+use std::{collections::HashMap, env, sync::{Arc, Mutex}, time::Duration};
+
+use actix_web::web;
+use actix_ws::{CloseCode, CloseReason, Message, MessageStream, Session};
+use bb8::Pool;
+use futures::StreamExt;
+use once_cell::sync::Lazy;
+use serde_json::{json, Value as JsonValue};
+use tokio::{sync::{mpsc, watch}, task::JoinHandle, time::MissedTickBehavior};
+
+use crate::contexts::{auth::UserPermissions, cache::{CacheKey, QUERY_CACHE}, connection::ConnectionManager, error::AppError, logger::write_log, row_scope::RowScope, model::{BarChartParams, ChartSocketMessage}};
+use super::{chart_service::ChartService, permission_service::PermissionService, session_service::SessionService};
+
+type ChartSender = Arc<watch::Sender<Option<JsonValue>>>;
+
+/// Satu refresher per (view, filter, kolom), dipakai bersama oleh semua subscriber
+static LIVE_CHARTS: Lazy<Mutex<HashMap<CacheKey, ChartSender>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+pub struct ChartLiveService;
+
+impl ChartLiveService {
+    /// Jalankan satu sesi WebSocket sampai client menutup koneksi.
+    /// Akses dicek ulang setiap interval refresh: koneksi ditutup saat access token kedaluwarsa,
+    /// sesi dicabut, atau permission untuk chart yang sedang di-subscribe hilang.
+    pub async fn run_session(connection: web::Data<Pool<ConnectionManager>>, mut session: Session, mut stream: MessageStream, mut auth: UserPermissions) {
+        let (tx, mut rx) = mpsc::channel::<String>(32);
+        let mut forwarders: Vec<JoinHandle<()>> = Vec::new();
+        let mut subscription: Option<(String, String)> = None;
+
+        let mut auth_check = tokio::time::interval(Self::refresh_interval());
+        auth_check.set_missed_tick_behavior(MissedTickBehavior::Skip);
+        let expires_in = Duration::from_secs((auth.claims.exp as i64 - chrono::Utc::now().timestamp()).max(0) as u64);
+        let expired = tokio::time::sleep(expires_in);
+        tokio::pin!(expired);
+
+        loop {
+            tokio::select! {
+                _ = &mut expired => {
+                    Self::abort_all(&mut forwarders);
+                    let _ = session.close(Some(CloseReason { code: CloseCode::Policy, description: Some("Access token expired".to_string()) })).await;
+                    return;
+                }
+                _ = auth_check.tick() => {
+                    if let Err(e) = Self::recheck_access(&connection, &mut auth, subscription.as_ref()).await {
+                        write_log("WARN", &format!("[{}] Chart socket closed for user {}: {}", e.code(), auth.claims.auth_usernid, e));
+                        Self::abort_all(&mut forwarders);
+                        let _ = session.close(Some(CloseReason { code: CloseCode::Policy, description: Some(e.message().to_string()) })).await;
+                        return;
+                    }
+                }
+                msg = stream.next() => match msg {
+                    Some(Ok(Message::Text(text))) => {
+                        Self::abort_all(&mut forwarders);
+
+                        let reply = match serde_json::from_str::<ChartSocketMessage>(&text) {
+                            Ok(ChartSocketMessage::Subscribe { menu_id, tablename, filter, chart_ids }) => {
+                                subscription = Some((menu_id.clone(), tablename.clone()));
+                                match Self::subscribe(&connection, &auth, menu_id, tablename, filter, chart_ids, &tx).await {
+                                    Ok((handles, charts)) => {
+                                        forwarders = handles;
+                                        json!({ "type": "subscribed", "charts": charts })
+                                    }
+                                    Err(e) => {
+                                        subscription = None;
+                                        write_log("WARN", &format!("[{}] Chart subscribe failed: {}", e.code(), e));
+                                        json!({ "type": "error", "message": e.message(), "error_code": e.code() })
+                                    }
+                                }
+                            }
+                            Ok(ChartSocketMessage::Unsubscribe) => {
+                                subscription = None;
+                                json!({ "type": "unsubscribed" })
+                            }
+                            Err(e) => json!({ "type": "error", "message": "Bad Request", "error": e.to_string(), "error_code": "BAD_REQUEST" }),
+                        };
+
+                        if session.text(reply.to_string()).await.is_err() {
+                            break;
+                        }
+                    }
+                    Some(Ok(Message::Ping(bytes))) => {
+                        if session.pong(&bytes).await.is_err() {
+                            break;
+                        }
+                    }
+                    Some(Ok(Message::Close(reason))) => {
+                        Self::abort_all(&mut forwarders);
+                        let _ = session.close(reason).await;
+                        return;
+                    }
+                    Some(Ok(_)) => {}
+                    Some(Err(_)) | None => break,
+                },
+                Some(text) = rx.recv() => {
+                    if session.text(text).await.is_err() {
+                        break;
+                    }
+                }
+            }
+        }
+
+        Self::abort_all(&mut forwarders);
+        let _ = session.close(None).await;
+    }
+
+    /// Handshake diautentikasi dengan cookie, jadi halaman dari origin lain tidak boleh membuka socket.
+    /// Origin harus sama dengan host request atau terdaftar di `WS_ALLOWED_ORIGINS` (dipisah koma).
+    pub fn check_origin(origin: Option<&str>, host: &str) -> Result<(), AppError> {
+        let Some(origin) = origin else {
+            return Ok(());
+        };
+
+        let origin = origin.trim_end_matches('/');
+        let same_host = origin.split_once("://").is_some_and(|(_, origin_host)| origin_host.eq_ignore_ascii_case(host));
+        let allowed = env::var("WS_ALLOWED_ORIGINS").unwrap_or_default()
+            .split(',')
+            .any(|allowed| allowed.trim().trim_end_matches('/').eq_ignore_ascii_case(origin));
+
+        match same_host || allowed {
+            true => Ok(()),
+            false => Err(AppError::Forbidden(format!("Origin '{}' is not allowed", origin))),
+        }
+    }
+
+    fn refresh_interval() -> Duration {
+        let interval_secs: u64 = env::var("CHART_REFRESH_INTERVAL_SECS").ok().and_then(|v| v.parse().ok()).unwrap_or(30);
+        Duration::from_secs(interval_secs.max(1))
+    }
+
+    /// Sesi yang dicabut (logout / revoke) dan permission terbaru dari database (cache `PermissionService`)
+    async fn recheck_access(connection: &Pool<ConnectionManager>, auth: &mut UserPermissions, subscription: Option<&(String, String)>) -> Result<(), AppError> {
+        if auth.claims.sid.as_deref().is_some_and(SessionService::is_revoked) {
+            return Err(AppError::Unauthorized("Session has been revoked".to_string()));
+        }
+
+        auth.access = PermissionService::access_for(connection, auth.claims.auth_usernid).await?;
+        if let Some((menu_id, tablename)) = subscription {
+            auth.require(&format!("menu:{}", menu_id))?;
+            auth.require(&format!("view:{}", tablename))?;
+        }
+
+        Ok(())
+    }
+
+    fn abort_all(forwarders: &mut Vec<JoinHandle<()>>) {
+        for handle in forwarders.drain(..) {
+            handle.abort();
+        }
+    }
+
+    /// Ambil chart milik menu lalu buat satu forwarder per chart ke channel sesi
+    async fn subscribe(connection: &web::Data<Pool<ConnectionManager>>, auth: &UserPermissions, menu_id: String, tablename: String, filter: Option<String>, chart_ids: Option<Vec<String>>, tx: &mpsc::Sender<String>) -> Result<(Vec<JoinHandle<()>>, Vec<JsonValue>), AppError> {
+        if menu_id.trim().is_empty() || tablename.trim().is_empty() {
+            return Err(AppError::BadRequest("menu_id and tablename are required".to_string()));
+        }
+
+        auth.require(&format!("menu:{}", menu_id))?;
+        auth.require(&format!("view:{}", tablename))?;
+        let scope = auth.row_scope(&tablename)?;
+        let masks = auth.column_masks(&tablename);
+        let filter = masks.strip_filter(filter);
+
+        let charts = ChartService::get_chart_data(connection.clone(), menu_id).await?.data.unwrap_or_default();
+        let mut handles = Vec::new();
+        let mut subscribed = Vec::new();
+
+        for chart in charts {
+            let chart_id = chart.get("ChartID").and_then(|v| v.as_str()).unwrap_or_default().to_string();
+            let chart_name = chart.get("ChartName").cloned().unwrap_or(JsonValue::Null);
+            let column = chart.get("ListColumn").and_then(|v| v.as_str()).unwrap_or_default().to_string();
+
+            // Chart pada kolom yang di-mask tidak dikirim karena group by membuka nilai aslinya
+            if column.is_empty() || masks.is_masked(&column) || chart_ids.as_ref().is_some_and(|ids| !ids.contains(&chart_id)) {
+                continue;
+            }
+
+            let params = BarChartParams { tablename: tablename.clone(), column: column.clone(), filter: filter.clone() };
+            let mut rx = Self::watch(connection.get_ref(), params, scope.clone());
+            let tx = tx.clone();
+            let header = json!({ "type": "chart", "chart_id": chart_id, "chart_name": chart_name, "column": column });
+
+            subscribed.push(json!({ "chart_id": chart_id, "chart_name": chart_name, "column": column }));
+            handles.push(tokio::spawn(async move {
+                loop {
+                    let payload = rx.borrow_and_update().clone();
+                    if let Some(JsonValue::Object(payload)) = payload {
+                        let mut message = header.clone();
+                        if let Some(message) = message.as_object_mut() {
+                            message.extend(payload);
+                        }
+                        if tx.send(message.to_string()).await.is_err() {
+                            return;
+                        }
+                    }
+                    if rx.changed().await.is_err() {
+                        return;
+                    }
+                }
+            }));
+        }
+
+        Ok((handles, subscribed))
+    }
+
+    /// Receiver untuk satu chart, refresher dijalankan jika belum ada.
+    /// Subscriber hanya berbagi refresher dengan user yang row scope-nya sama.
+    fn watch(pool: &Pool<ConnectionManager>, params: BarChartParams, scope: RowScope) -> watch::Receiver<Option<JsonValue>> {
+        let key = CacheKey::new(&params.tablename, params.filter.as_deref(), &format!("bar:{}", params.column)).scoped(&scope);
+        let mut charts = LIVE_CHARTS.lock().unwrap();
+
+        if let Some(sender) = charts.get(&key) {
+            return sender.subscribe();
+        }
+
+        let (sender, rx) = watch::channel(None);
+        let sender = Arc::new(sender);
+        charts.insert(key.clone(), sender.clone());
+        tokio::spawn(Self::refresh(pool.clone(), key, params, scope, sender));
+
+        rx
+    }
+
+    /// Hitung ulang agregasi per interval, hanya kirim ke subscriber jika hasilnya berubah.
+    /// Berhenti sendiri saat tidak ada subscriber lagi.
+    async fn refresh(pool: Pool<ConnectionManager>, key: CacheKey, params: BarChartParams, scope: RowScope, sender: ChartSender) {
+        let mut interval = tokio::time::interval(Self::refresh_interval());
+        interval.set_missed_tick_behavior(MissedTickBehavior::Skip);
+
+        loop {
+            interval.tick().await;
+
+            {
+                let mut charts = LIVE_CHARTS.lock().unwrap();
+                if sender.receiver_count() == 0 {
+                    charts.remove(&key);
+                    return;
+                }
+            }
+
+            let payload = match ChartService::query_bar_chart(&pool, &params, &scope).await {
+                Ok(data) => {
+                    QUERY_CACHE.insert(key.clone(), data.clone());
+                    json!({ "data": data })
+                }
+                Err(e) => {
+                    write_log("ERROR", &format!("[{}] Live chart refresh failed for '{}' ({}): {}", e.code(), params.tablename, params.column, e));
+                    json!({ "error": { "message": e.message(), "error_code": e.code() } })
+                }
+            };
+
+            sender.send_if_modified(|current| {
+                if current.as_ref() == Some(&payload) {
+                    return false;
+                }
+                *current = Some(payload);
+                true
+            });
+        }
+    }
+}
@@ -1,278 +1,233 @@
-use std::collections::HashMap;
+use std::{collections::HashMap, time::Instant};
 
 use actix_web::web;
 use bb8::Pool;
-use bb8_tiberius::ConnectionManager;
-use crate::contexts::{connection::Transaction, model::{ActionResult, BarChartParams, BarChartRequest, DeleteBarChart}};
-use super::data_service::DataService;
+use bb8_tiberius::rt::Client;
+use serde_json::json;
+use crate::contexts::{auth::AuditActor, cache::{cached, CacheKey}, connection::{query_cancelled, query_timeout, ConnectionManager, Transaction}, error::AppError, metrics::METRICS, row_scope::{is_identifier, RowScope}, model::{ActionResult, BarChartParams, BarChartRequest, DeleteBarChart}};
+use super::{audit_service::{AuditEntry, AuditService}, data_service::DataService};
 
 pub struct ChartService;
 
 impl ChartService {
-    pub async fn get_chart_data(connection: web::Data<bb8::Pool<ConnectionManager>>, menu_id: String) -> ActionResult<Vec<serde_json::Value>, String> {
+    pub async fn get_chart_data(connection: web::Data<bb8::Pool<ConnectionManager>>, menu_id: String) -> Result<ActionResult<Vec<serde_json::Value>, String>, AppError> {
         let mut result: ActionResult<Vec<serde_json::Value>, String> = ActionResult::default();
     
-        match connection.clone().get().await {
-            Ok(mut conn) => {
-                let menu_id_param = format!("{}", menu_id);
-    
-                let query = r#"SELECT * FROM [BarChart] WHERE MenuID = @P1"#;
-    
-                match conn.query(query, &[&menu_id_param]).await {
-                    Ok(rows) => {      
-    
-                        let data = rows.into_results().await.unwrap();
-
-                        let row_data = data.into_iter()
-                        .flat_map(|r| r.into_iter())
-                        .map(|row| DataService::row_to_json(&row));
-                        
-                        result.data = Some(row_data.collect());
-                    
-                        result.result = true;
-                        result.message = "Data retrieved successfully".to_string();
-                        return result;
-                    }
-                    Err(e) => {
-                        result.message = "Internal Server Error".to_string();
-                        result.error = Some(e.to_string());
-                        return result;
-                    }
-                }
-            }
-            Err(e) => {
-                result.message = "Database connection failed".to_string();
-                result.error = Some(e.to_string());
-                return result;
-            }
-        }
+        let mut conn = connection.get().await?;
+        let query = r#"SELECT * FROM [BarChart] WHERE MenuID = @P1"#;
+
+        let data = conn.query(query, &[&menu_id]).await?.into_results().await?;
+
+        let row_data = data.into_iter()
+            .flat_map(|r| r.into_iter())
+            .map(|row| DataService::row_to_json(&row));
+
+        result.data = Some(row_data.collect());
+        result.result = true;
+        result.message = "Data retrieved successfully".to_string();
+
+        Ok(result)
     }    
 
     // #region BAR CHART SERVICE
-    pub async fn get_bar_chart(connection: web::Data<bb8::Pool<ConnectionManager>>, params: BarChartParams) -> ActionResult<Vec<serde_json::Value>, String> {
+    pub async fn get_bar_chart(connection: web::Data<bb8::Pool<ConnectionManager>>, params: BarChartParams, scope: &RowScope) -> Result<ActionResult<Vec<serde_json::Value>, String>, AppError> {
         let mut result: ActionResult<Vec<serde_json::Value>, String> = ActionResult::default();
-        let mut q_and_where = String::from(" WHERE 1=1 ");
 
-        match connection.clone().get().await {
-            
-            Ok(mut conn) => {
-                if let Some(filter) = &params.filter {
-                    // println!("Filter: {}", filter);
-                    if filter != "{filter:undefined}" {
-                        match serde_json::from_str::<HashMap<String, String>>(filter) {
-                            Ok(filter_name) => {
-                                if !filter_name.is_empty() {
-                                    let q_and_where_result = DataService::get_query_table_where(q_and_where.clone(), filter_name);
-            
-                                    q_and_where = q_and_where_result.clone();
-                                }
-                            },
-                            Err(e) => {
-                                result.message = "Invalid filter".to_string();
-                                result.error = Some(e.to_string());
-                                return result;
-                            }
-                        }
-                    }
-                }
-    
-                let query = format!(
-                    "SELECT {} AS Value, COUNT(*) as Count FROM {} {} GROUP BY {}",
-                    &params.column, &params.tablename, &q_and_where, &params.column
-                );
-                
-                // println!("Query: {}, filter: {}", query, params.filter.unwrap());
-    
-                match conn.query(query, &[]).await {
-                    Ok(rows) => {      
-    
-                        let data = rows.into_results().await.unwrap();
-
-                        let row_data = data.into_iter()
-                        .flat_map(|r| r.into_iter())
-                        .map(|row| DataService::row_to_json(&row));
-                        
-                        result.data = Some(row_data.collect());
-                    
-                        result.result = true;
-                        result.message = "Data retrieved successfully".to_string();
-                        return result;
-                    }
-                    Err(e) => {
-                        result.message = "Internal Server Error".to_string();
-                        result.error = Some(e.to_string());
-                        return result;
-                    }
+        let key = CacheKey::new(&params.tablename, params.filter.as_deref(), &format!("bar:{}", params.column)).scoped(scope);
+        let data = cached(key, Self::query_bar_chart(&connection, &params, scope)).await?;
+
+        result.data = serde_json::from_value(data).ok();
+        result.result = true;
+        result.message = "Data retrieved successfully".to_string();
+
+        Ok(result)
+    }
+
+    /// Hitung agregasi bar chart langsung ke database (tanpa cache)
+    pub async fn query_bar_chart(connection: &Pool<ConnectionManager>, params: &BarChartParams, scope: &RowScope) -> Result<serde_json::Value, AppError> {
+        if !is_identifier(&params.column) {
+            return Err(AppError::BadRequest(format!("Invalid column name '{}'", params.column)));
+        }
+        if !is_identifier(&params.tablename) {
+            return Err(AppError::BadRequest(format!("Invalid view name '{}'", params.tablename)));
+        }
+
+        let mut q_and_where = scope.where_clause();
+
+        if let Some(filter) = &params.filter {
+            // println!("Filter: {}", filter);
+            if filter != "{filter:undefined}" {
+                let filter_name = serde_json::from_str::<HashMap<String, String>>(filter)
+                    .map_err(|e| AppError::BadRequest(format!("Invalid filter: {}", e)))?;
+
+                if !filter_name.is_empty() {
+                    q_and_where = DataService::get_query_table_where(q_and_where.clone(), filter_name);
                 }
             }
-            Err(e) => {
-                result.message = "Database connection failed".to_string();
-                result.error = Some(e.to_string());
-                return result;
-            }
         }
+
+        let query = format!(
+            "SELECT {} AS Value, COUNT(*) as Count FROM [{}] {} GROUP BY {}",
+            &params.column, &params.tablename, &q_and_where, &params.column
+        );
+
+        // println!("Query: {}, filter: {}", query, params.filter.unwrap());
+        let timeout = query_timeout("BAR_CHART");
+        let started = Instant::now();
+        let mut conn = connection.get().await?;
+
+        conn.mark_in_flight();
+        let data = tokio::time::timeout(timeout, async {
+            conn.query(query, &[]).await?.into_results().await
+        }).await.map_err(|_| query_cancelled(timeout))??;
+        conn.mark_done();
+        METRICS.observe_query("get_bar_chart", &params.tablename, started.elapsed());
+
+        let row_data: Vec<serde_json::Value> = data.into_iter()
+            .flat_map(|r| r.into_iter())
+            .map(|row| DataService::row_to_json(&row))
+            .collect();
+
+        Ok(serde_json::Value::Array(row_data))
     }
 
-    pub async fn save_bar_chart(connection: web::Data<Pool<ConnectionManager>>, request: BarChartRequest) -> ActionResult<(), String> {
+    pub async fn save_bar_chart(connection: web::Data<Pool<ConnectionManager>>, request: BarChartRequest, actor: &AuditActor) -> Result<ActionResult<(), String>, AppError> {
         let mut result: ActionResult<(), String> = ActionResult::default();
             
-        match Transaction::begin(&connection).await {
-            Ok(trans) => {
-
-                let joined_list_column = request.list_column;
-                let chart_id = request.chart_name.clone().unwrap_or_default().replace(" ", "-").to_lowercase();
-
-                match trans.conn.lock().await.as_mut() {
-                    Some(conn) => {
-                        
-                        match conn.execute(
-                            r#"INSERT INTO [dbo].[BarChart] 
-                            ([ChartID],[ChartName],[MenuID],[ListColumn])
-                            VALUES
-                            (@P1,@P2,@P3,@P4)"#,
-                            &[
-                                &chart_id, &request.chart_name, &request.menu_id, &joined_list_column
-                            ],
-                        ).await {
-                            Ok(query_result) => {
-                                let rows = query_result.rows_affected();
-                                if rows.iter().any(|row| row > &0) {
-                                    result.result = true;
-                                    result.message = format!("{} BarChart saved successfully", rows.iter().count());
-                                } else {
-                                    result.result = false;
-                                    result.message = "No BarChart found to save".to_string();
-                                }
-
-                            }
-                            Err(err) => {
-                                result.message = "Failed".to_string();
-                                result.error = Some(format!("Failed to delete BarChart: {}", err));
-                                return result;
-                            }
-                        }                        
-                    }
-                    None => {
-                        result.error = Some("Failed to get connection from pool".into());
-                        return result;
-                    }
-                }
-    
-                if let Err(err) = trans.commit().await {
-                    result.error = Some(format!("Failed to commit transaction: {:?}", err));
-                    return result;
-                }
-            }
-            Err(err) => {
-                result.error = Some(format!("Failed to start transaction: {:?}", err));
+        let trans = Transaction::begin(&connection).await?;
+
+        let joined_list_column = request.list_column;
+        let chart_id = request.chart_name.clone().unwrap_or_default().replace(" ", "-").to_lowercase();
+
+        {
+            let mut conn_guard = trans.conn.lock().await;
+            let conn = conn_guard.as_mut()
+                .ok_or_else(|| AppError::Internal("Failed to get connection from pool".to_string()))?;
+
+            let query_result = conn.execute(
+                r#"INSERT INTO [dbo].[BarChart] 
+                ([ChartID],[ChartName],[MenuID],[ListColumn])
+                VALUES
+                (@P1,@P2,@P3,@P4)"#,
+                &[
+                    &chart_id, &request.chart_name, &request.menu_id, &joined_list_column
+                ],
+            ).await?;
+
+            let rows = query_result.rows_affected();
+            if !rows.iter().any(|row| row > &0) {
+                return Err(AppError::BadRequest("No BarChart found to save".to_string()));
             }
+
+            AuditService::write(conn, actor, AuditEntry {
+                action: "insert",
+                entity: "BarChart",
+                entity_key: Some(chart_id.clone()),
+                before: None,
+                after: Some(json!({ "ChartID": chart_id, "ChartName": request.chart_name, "MenuID": request.menu_id, "ListColumn": joined_list_column })),
+            }).await?;
+
+            result.result = true;
+            result.message = format!("{} BarChart saved successfully", rows.len());
         }
-    
-        result
+
+        trans.commit().await?;
+
+        Ok(result)
     }
 
-    pub async fn update_bar_chart(connection: web::Data<Pool<ConnectionManager>>, request: BarChartRequest) -> ActionResult<(), String> {
+    pub async fn update_bar_chart(connection: web::Data<Pool<ConnectionManager>>, request: BarChartRequest, actor: &AuditActor) -> Result<ActionResult<(), String>, AppError> {
         let mut result: ActionResult<(), String> = ActionResult::default();
             
-        match Transaction::begin(&connection).await {
-            Ok(trans) => {
-
-                let joined_list_column = request.list_column;
-                let chart_id = request.chart_name.clone().unwrap_or_default().replace(" ", "-").to_lowercase();
-
-                match trans.conn.lock().await.as_mut() {
-                    Some(conn) => {
-                        
-                        match conn.execute(
-                            r#"UPDATE [dbo].[BarChart] SET 
-                            [ChartName] = @P2, [MenuID] = @P3, [ListColumn] = @P4 WHERE [ChartID] = @P1"#,
-                            &[
-                                &chart_id, &request.chart_name, &request.menu_id, &joined_list_column
-                            ],
-                        ).await {
-                            Ok(query_result) => {
-                                let rows = query_result.rows_affected();
-                                if rows.iter().any(|row| row > &0) {
-                                    result.result = true;
-                                    result.message = format!("{} BarChart updated successfully", rows.iter().count());
-                                } else {
-                                    result.result = false;
-                                    result.message = "No BarChart found to update".to_string();
-                                }
-
-                            }
-                            Err(err) => {
-                                result.message = "Failed".to_string();
-                                result.error = Some(format!("Failed to delete BarChart: {}", err));
-                                return result;
-                            }
-                        }                        
-                    }
-                    None => {
-                        result.error = Some("Failed to get connection from pool".into());
-                        return result;
-                    }
-                }
-    
-                if let Err(err) = trans.commit().await {
-                    result.error = Some(format!("Failed to commit transaction: {:?}", err));
-                    return result;
-                }
-            }
-            Err(err) => {
-                result.error = Some(format!("Failed to start transaction: {:?}", err));
+        let trans = Transaction::begin(&connection).await?;
+
+        let joined_list_column = request.list_column;
+        let chart_id = request.chart_name.clone().unwrap_or_default().replace(" ", "-").to_lowercase();
+
+        {
+            let mut conn_guard = trans.conn.lock().await;
+            let conn = conn_guard.as_mut()
+                .ok_or_else(|| AppError::Internal("Failed to get connection from pool".to_string()))?;
+
+            let before = Self::get_bar_chart_row(conn, &chart_id, None).await?;
+
+            let query_result = conn.execute(
+                r#"UPDATE [dbo].[BarChart] SET 
+                [ChartName] = @P2, [MenuID] = @P3, [ListColumn] = @P4 WHERE [ChartID] = @P1"#,
+                &[
+                    &chart_id, &request.chart_name, &request.menu_id, &joined_list_column
+                ],
+            ).await?;
+
+            let rows = query_result.rows_affected();
+            if !rows.iter().any(|row| row > &0) {
+                return Err(AppError::NotFound("No BarChart found to update".to_string()));
             }
+
+            AuditService::write(conn, actor, AuditEntry {
+                action: "update",
+                entity: "BarChart",
+                entity_key: Some(chart_id.clone()),
+                before,
+                after: Some(json!({ "ChartID": chart_id, "ChartName": request.chart_name, "MenuID": request.menu_id, "ListColumn": joined_list_column })),
+            }).await?;
+
+            result.result = true;
+            result.message = format!("{} BarChart updated successfully", rows.len());
         }
-    
-        result
+
+        trans.commit().await?;
+
+        Ok(result)
     }
 
-    pub async fn delete_bar_chart(connection: web::Data<Pool<ConnectionManager>>, request: DeleteBarChart) -> ActionResult<(), String> {
+    pub async fn delete_bar_chart(connection: web::Data<Pool<ConnectionManager>>, request: DeleteBarChart, actor: &AuditActor) -> Result<ActionResult<(), String>, AppError> {
         let mut result: ActionResult<(), String> = ActionResult::default();
             
-        match Transaction::begin(&connection).await {
-            Ok(trans) => {
-                match trans.conn.lock().await.as_mut() {
-                    Some(conn) => {
-                        match conn.execute(
-                            r#"DELETE FROM [dbo].[BarChart] WHERE ChartID = @P1 AND MenuID = @P2"#,
-                            &[&request.chart_id, &request.menu_id],
-                        ).await {
-                            Ok(query_result) => {
-                                let rows = query_result.rows_affected();
-                                if rows.iter().any(|row| row > &0) {
-                                    result.result = true;
-                                    result.message = format!("{} BarChart(s) deleted successfully", rows.iter().count());
-                                } else {
-                                    result.result = false;
-                                    result.message = "No BarChart found to delete".to_string();
-                                }
-
-                            }
-                            Err(err) => {
-                                result.message = "Failed".to_string();
-                                result.error = Some(format!("Failed to delete BarChart: {}", err));
-                                return result;
-                            }
-                        }                        
-                    }
-                    None => {
-                        result.error = Some("Failed to get connection from pool".into());
-                        return result;
-                    }
-                }
-    
-                if let Err(err) = trans.commit().await {
-                    result.error = Some(format!("Failed to commit transaction: {:?}", err));
-                    return result;
-                }
-            }
-            Err(err) => {
-                result.error = Some(format!("Failed to start transaction: {:?}", err));
+        let trans = Transaction::begin(&connection).await?;
+
+        {
+            let mut conn_guard = trans.conn.lock().await;
+            let conn = conn_guard.as_mut()
+                .ok_or_else(|| AppError::Internal("Failed to get connection from pool".to_string()))?;
+
+            let before = Self::get_bar_chart_row(conn, request.chart_id.as_deref().unwrap_or_default(), request.menu_id.as_deref()).await?;
+
+            let query_result = conn.execute(
+                r#"DELETE FROM [dbo].[BarChart] WHERE ChartID = @P1 AND MenuID = @P2"#,
+                &[&request.chart_id, &request.menu_id],
+            ).await?;
+
+            let rows = query_result.rows_affected();
+            if !rows.iter().any(|row| row > &0) {
+                return Err(AppError::NotFound("No BarChart found to delete".to_string()));
             }
+
+            AuditService::write(conn, actor, AuditEntry {
+                action: "delete",
+                entity: "BarChart",
+                entity_key: request.chart_id.clone(),
+                before,
+                after: None,
+            }).await?;
+
+            result.result = true;
+            result.message = format!("{} BarChart(s) deleted successfully", rows.len());
         }
+
+        trans.commit().await?;
+
+        Ok(result)
+    }
     
-        result
+    /// Data BarChart sebelum diubah, untuk audit log
+    async fn get_bar_chart_row(conn: &mut Client, chart_id: &str, menu_id: Option<&str>) -> Result<Option<serde_json::Value>, AppError> {
+        let row = conn.query(
+            r#"SELECT TOP 1 * FROM [dbo].[BarChart] WHERE ChartID = @P1 AND (@P2 IS NULL OR MenuID = @P2)"#,
+            &[&chart_id, &menu_id],
+        ).await?.into_row().await?;
+
+        Ok(row.map(|row| DataService::row_to_json(&row)))
     }
     
     // #endregion
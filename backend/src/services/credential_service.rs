@@ -0,0 +1,363 @@
+use std::env;
+
+use base64::{engine::general_purpose, Engine as _};
+use bb8::Pool;
+use chrono::{Duration, NaiveDateTime, Utc};
+use once_cell::sync::Lazy;
+use rand::Rng;
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use bb8_tiberius::rt::Client;
+use tiberius::Row;
+
+use crate::contexts::{auth::AuditActor, connection::ConnectionManager, error::AppError, logger::write_log, model::Claims};
+use super::{audit_service::{AuditEntry, AuditService}, totp_service::TotpService};
+
+/// Algoritma hash password saat ini, disimpan sebagai prefix hash
+const PASSWORD_ALGORITHM: &str = "pbkdf2-sha256";
+const SALT_LENGTH: usize = 16;
+
+/// Kolom `AuthUser` yang dibutuhkan untuk login dan membuat claims
+const USER_COLUMNS: &str = "AuthUserNID, Email, MobilePhone, PasswordHash, DisabledLogin, LockedUntil, \
+    Picture, CompanyID, CompName, Branches, RegisterDate, GETDATE() AS Now";
+
+/// Hash untuk email yang tidak terdaftar, supaya waktu respons login sama dengan user yang ada
+static DUMMY_HASH: Lazy<String> = Lazy::new(|| CredentialService::hash_password("dummy-password"));
+
+/// Hasil login dengan password
+pub enum LoginOutcome {
+    Authenticated(Box<Claims>),
+    /// Password benar tapi akun memakai 2FA, `nonce` mengikat token sementara ke percobaan login ini
+    TwoFactorRequired { user_nid: i32, nonce: String },
+}
+
+/// Hasil verifikasi password terhadap hash yang tersimpan
+pub enum PasswordCheck {
+    Invalid,
+    /// `needs_rehash` jika hash dibuat dengan parameter yang lebih lemah dari konfigurasi saat ini
+    Valid { needs_rehash: bool },
+}
+
+pub struct CredentialService;
+
+impl CredentialService {
+    /// Jumlah iterasi PBKDF2 untuk hash baru (`PASSWORD_PBKDF2_ITERATIONS`, default 600.000)
+    fn iterations() -> u32 {
+        env::var("PASSWORD_PBKDF2_ITERATIONS").ok().and_then(|v| v.parse().ok()).unwrap_or(600_000).max(1)
+    }
+
+    /// Hash password dengan salt acak: `pbkdf2-sha256$<iterasi>$<salt>$<hash>`
+    pub fn hash_password(password: &str) -> String {
+        let mut salt = [0u8; SALT_LENGTH];
+        rand::rng().fill(&mut salt[..]);
+
+        let iterations = Self::iterations();
+        let hash = Self::pbkdf2_sha256(password.as_bytes(), &salt, iterations);
+
+        format!(
+            "{}${}${}${}",
+            PASSWORD_ALGORITHM,
+            iterations,
+            general_purpose::STANDARD_NO_PAD.encode(salt),
+            general_purpose::STANDARD_NO_PAD.encode(hash)
+        )
+    }
+
+    /// Verifikasi password dengan parameter yang tersimpan di hash, dibandingkan constant-time
+    pub fn verify_password(password: &str, stored: &str) -> PasswordCheck {
+        let parts: Vec<&str> = stored.split('$').collect();
+        let [algorithm, iterations, salt, hash] = parts.as_slice() else {
+            return PasswordCheck::Invalid;
+        };
+
+        let (Some(iterations), Ok(salt), Ok(expected)) = (
+            iterations.parse::<u32>().ok().filter(|i| *i > 0),
+            general_purpose::STANDARD_NO_PAD.decode(salt),
+            general_purpose::STANDARD_NO_PAD.decode(hash),
+        ) else {
+            return PasswordCheck::Invalid;
+        };
+
+        if *algorithm != PASSWORD_ALGORITHM {
+            return PasswordCheck::Invalid;
+        }
+
+        let actual = Self::pbkdf2_sha256(password.as_bytes(), &salt, iterations);
+        if !Self::constant_time_eq(&actual, &expected) {
+            return PasswordCheck::Invalid;
+        }
+
+        PasswordCheck::Valid { needs_rehash: iterations < Self::iterations() || salt.len() < SALT_LENGTH }
+    }
+
+    /// PBKDF2-HMAC-SHA256 (RFC 8018) dengan satu blok output 32 byte
+    fn pbkdf2_sha256(password: &[u8], salt: &[u8], iterations: u32) -> [u8; 32] {
+        // Key HMAC di-set sekali lalu state-nya di-clone di setiap iterasi
+        let key = Hmac::<Sha256>::new_from_slice(password).expect("HMAC accepts keys of any length");
+        let prf = |data: &[&[u8]]| -> [u8; 32] {
+            let mut mac = key.clone();
+            data.iter().for_each(|part| mac.update(part));
+            mac.finalize().into_bytes().into()
+        };
+
+        let mut block = prf(&[salt, &1u32.to_be_bytes()]);
+        let mut output = block;
+        for _ in 1..iterations {
+            block = prf(&[&block]);
+            output.iter_mut().zip(block).for_each(|(o, b)| *o ^= b);
+        }
+
+        output
+    }
+
+    pub fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+        a.len() == b.len() && a.iter().zip(b).fold(0u8, |diff, (x, y)| diff | (x ^ y)) == 0
+    }
+
+    /// Hash / verifikasi berjalan ratusan milidetik, jadi dijalankan di luar worker async
+    async fn verify_blocking(password: &str, stored: &str) -> Result<PasswordCheck, AppError> {
+        let (password, stored) = (password.to_string(), stored.to_string());
+        tokio::task::spawn_blocking(move || Self::verify_password(&password, &stored))
+            .await
+            .map_err(|e| AppError::Internal(format!("Password verification failed: {}", e)))
+    }
+
+    pub async fn hash_blocking(password: &str) -> Result<String, AppError> {
+        let password = password.to_string();
+        tokio::task::spawn_blocking(move || Self::hash_password(&password))
+            .await
+            .map_err(|e| AppError::Internal(format!("Password hashing failed: {}", e)))
+    }
+
+    /// Login dengan email & password, lihat `register_failed_attempt` untuk penguncian akun.
+    /// Akun `DisabledLogin` selalu ditolak. Status akun baru dilaporkan setelah password benar,
+    /// supaya respons dan waktunya tidak membedakan email terdaftar, nonaktif atau terkunci.
+    /// Untuk akun dengan 2FA, hitungan gagal, `LastLogin` dan audit login baru diproses di `TotpService::verify_login`.
+    pub async fn authenticate(connection: &Pool<ConnectionManager>, email: &str, password: &str, actor: &AuditActor) -> Result<LoginOutcome, AppError> {
+        let mut conn = connection.get().await?;
+        let query = format!("SELECT {} FROM [dbo].[AuthUser] WHERE Email = @P1", USER_COLUMNS);
+        let row = conn.query(query, &[&email]).await?.into_row().await?;
+        drop(conn);
+
+        let Some(row) = row else {
+            let _ = Self::verify_blocking(password, &DUMMY_HASH).await?;
+            Self::record_failure(connection, actor, None, email, "login_failed").await;
+            return Err(AppError::Unauthorized("Invalid email or password".to_string()));
+        };
+
+        let user_nid: i32 = row.get("AuthUserNID").unwrap_or_default();
+        let now: Option<NaiveDateTime> = row.get("Now");
+        let disabled = row.get::<bool, _>("DisabledLogin").unwrap_or(false);
+        let locked = Self::ensure_not_locked(row.get("LockedUntil"), now);
+
+        // Hash kosong / format lama tetap diverifikasi penuh (terhadap DUMMY_HASH) supaya waktunya sama
+        let stored = row.get::<&str, _>("PasswordHash")
+            .filter(|hash| hash.starts_with(PASSWORD_ALGORITHM))
+            .map(str::to_string);
+        let check = Self::verify_blocking(password, stored.as_deref().unwrap_or(&DUMMY_HASH)).await?;
+        let needs_rehash = match (check, &stored) {
+            (PasswordCheck::Valid { needs_rehash }, Some(_)) => needs_rehash,
+            _ => {
+                // Akun yang sedang terkunci / nonaktif tidak menambah hitungan, jadi masa kunci tidak diperpanjang
+                if disabled || locked.is_err() {
+                    Self::record_failure(connection, actor, Some(user_nid), email, "login_failed").await;
+                } else {
+                    Self::register_failed_attempt(connection, actor, user_nid, email, "login_failed").await?;
+                }
+                return Err(AppError::Unauthorized("Invalid email or password".to_string()));
+            }
+        };
+
+        if disabled {
+            return Err(AppError::Forbidden("Account is disabled".to_string()));
+        }
+        locked?;
+
+        // Hash ulang dengan parameter terbaru selagi password asli tersedia
+        let new_hash = match needs_rehash {
+            true => Some(Self::hash_blocking(password).await?),
+            false => None,
+        };
+
+        let mut conn = connection.get().await?;
+        if new_hash.is_some() {
+            conn.execute("UPDATE [dbo].[AuthUser] SET PasswordHash = @P2 WHERE AuthUserNID = @P1", &[&user_nid, &new_hash]).await?;
+            write_log("INFO", &format!("Password hash upgraded for user {}", user_nid));
+        }
+
+        if let Some(nonce) = TotpService::begin_login(&mut conn, user_nid).await? {
+            return Ok(LoginOutcome::TwoFactorRequired { user_nid, nonce });
+        }
+
+        Self::complete_login(&mut conn, user_nid).await?;
+
+        let claims = Self::claims_from_row(&row, actor.ip_address.clone());
+        let actor = AuditActor { user_nid: Some(user_nid), email: Some(claims.email.clone()), ip_address: actor.ip_address.clone() };
+        AuditService::write(&mut conn, &actor, AuditEntry { action: "login", entity: "AuthUser", entity_key: Some(user_nid.to_string()), before: None, after: None }).await?;
+
+        Ok(LoginOutcome::Authenticated(Box::new(claims)))
+    }
+
+    /// Login berhasil: reset hitungan gagal dan catat `LastLogin`
+    pub async fn complete_login(conn: &mut Client, user_nid: i32) -> Result<(), AppError> {
+        conn.execute(
+            "UPDATE [dbo].[AuthUser] SET FailedLoginCount = 0, LockedUntil = NULL, LastLogin = GETDATE() WHERE AuthUserNID = @P1",
+            &[&user_nid],
+        ).await?;
+
+        Ok(())
+    }
+
+    /// Claims terbaru user (saat refresh token), ditolak jika akun sudah dinonaktifkan
+    pub async fn load_claims(conn: &mut Client, user_nid: i32, ip_address: Option<String>) -> Result<Claims, AppError> {
+        let query = format!("SELECT {} FROM [dbo].[AuthUser] WHERE AuthUserNID = @P1", USER_COLUMNS);
+        let row = conn.query(query, &[&user_nid]).await?.into_row().await?
+            .ok_or_else(|| AppError::Unauthorized("User not found".to_string()))?;
+
+        if row.get::<bool, _>("DisabledLogin").unwrap_or(false) {
+            return Err(AppError::Forbidden("Account is disabled".to_string()));
+        }
+
+        Ok(Self::claims_from_row(&row, ip_address))
+    }
+
+    /// Tolak login selama `LockedUntil` masih di masa depan
+    pub fn ensure_not_locked(locked_until: Option<NaiveDateTime>, now: Option<NaiveDateTime>) -> Result<(), AppError> {
+        if let (Some(locked_until), Some(now)) = (locked_until, now) {
+            if locked_until > now {
+                let minutes = (locked_until - now).num_minutes() + 1;
+                return Err(AppError::Forbidden(format!("Account is locked, try again in {} minutes", minutes)));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Catat kegagalan login (password atau kode 2FA). Gagal berturut-turut sebanyak `LOGIN_MAX_ATTEMPTS`
+    /// (default 5) mengunci akun selama `LOGIN_LOCKOUT_SECS` (default 900).
+    pub async fn register_failed_attempt(connection: &Pool<ConnectionManager>, actor: &AuditActor, user_nid: i32, email: &str, action: &str) -> Result<(), AppError> {
+        let max_attempts: i32 = env::var("LOGIN_MAX_ATTEMPTS").ok().and_then(|v| v.parse().ok()).unwrap_or(5);
+        let lockout_secs: i32 = env::var("LOGIN_LOCKOUT_SECS").ok().and_then(|v| v.parse().ok()).unwrap_or(900);
+
+        let mut conn = connection.get().await?;
+        conn.execute(
+            r#"UPDATE [dbo].[AuthUser] SET
+                LockedUntil = CASE WHEN FailedLoginCount + 1 >= @P2 THEN DATEADD(second, @P3, GETDATE()) ELSE LockedUntil END,
+                FailedLoginCount = CASE WHEN FailedLoginCount + 1 >= @P2 THEN 0 ELSE FailedLoginCount + 1 END
+            WHERE AuthUserNID = @P1"#,
+            &[&user_nid, &max_attempts, &lockout_secs],
+        ).await?;
+        drop(conn);
+
+        Self::record_failure(connection, actor, Some(user_nid), email, action).await;
+        Ok(())
+    }
+
+    async fn record_failure(connection: &Pool<ConnectionManager>, actor: &AuditActor, user_nid: Option<i32>, email: &str, action: &str) {
+        let actor = AuditActor { user_nid, email: Some(email.to_string()), ip_address: actor.ip_address.clone() };
+        let entry = AuditEntry { action, entity: "AuthUser", entity_key: user_nid.map(|id| id.to_string()), before: None, after: None };
+
+        if let Err(e) = AuditService::record(connection, &actor, entry).await {
+            write_log("ERROR", &format!("[{}] Failed to record login failure: {}", e.code(), e));
+        }
+    }
+
+    /// Claims access token dari baris `AuthUser`, berlaku singkat selama `JWT_EXPIRES_SECS` (default 15 menit).
+    /// Sesi yang lebih panjang dipegang refresh token, lihat `SessionService`.
+    fn claims_from_row(row: &Row, ip_address: Option<String>) -> Claims {
+        let expires_secs: i64 = env::var("JWT_EXPIRES_SECS").ok().and_then(|v| v.parse().ok()).unwrap_or(15 * 60);
+        let expired_at = Utc::now() + Duration::seconds(expires_secs);
+
+        Claims {
+            result: true,
+            auth_usernid: row.get("AuthUserNID").unwrap_or_default(),
+            email: row.get::<&str, _>("Email").unwrap_or_default().to_string(),
+            mobile_phone: row.get::<&str, _>("MobilePhone").unwrap_or_default().to_string(),
+            disabled_login: false,
+            expired_token: expired_at.timestamp(),
+            expired_date: expired_at.to_rfc3339(),
+            register_date: row.get::<NaiveDateTime, _>("RegisterDate").map(|d| d.and_utc()).unwrap_or_else(Utc::now),
+            exp: expired_at.timestamp() as usize,
+            picture: row.get::<&str, _>("Picture").map(str::to_string),
+            comp_name: row.get::<&str, _>("CompName").map(str::to_string),
+            ip_address,
+            app_name: env::var("APP_NAME").ok(),
+            company_id: row.get::<&str, _>("CompanyID").map(str::to_string),
+            branches: row.get::<&str, _>("Branches")
+                .map(|b| b.split(';').map(str::trim).filter(|b| !b.is_empty()).map(str::to_string).collect())
+                .unwrap_or_default(),
+            sid: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hex(bytes: &[u8]) -> String {
+        bytes.iter().map(|b| format!("{:02x}", b)).collect()
+    }
+
+    fn stored_hash(password: &str, salt: &[u8], iterations: u32) -> String {
+        let hash = CredentialService::pbkdf2_sha256(password.as_bytes(), salt, iterations);
+        format!("{}${}${}${}", PASSWORD_ALGORITHM, iterations, general_purpose::STANDARD_NO_PAD.encode(salt), general_purpose::STANDARD_NO_PAD.encode(hash))
+    }
+
+    /// Test vector PBKDF2-HMAC-SHA256 (RFC 7914 bagian 11 dan draft-josefsson-pbkdf2-test-vectors), 32 byte pertama
+    #[test]
+    fn pbkdf2_sha256_matches_published_vectors() {
+        let vectors: [(&str, &str, u32, &str); 5] = [
+            ("password", "salt", 1, "120fb6cffcf8b32c43e7225256c4f837a86548c92ccc35480805987cb70be17b"),
+            ("password", "salt", 2, "ae4d0c95af6b46d32d0adff928f06dd02a303f8ef3c251dfd6e2d85a95474c43"),
+            ("password", "salt", 4096, "c5e478d59288c841aa530db6845c4c8d962893a001ce4e11a4963873aa98134a"),
+            ("passwordPASSWORDpassword", "saltSALTsaltSALTsaltSALTsaltSALTsalt", 4096, "348c89dbcbd32b2f32d814b8116e84cf2b17347ebc1800181c4e2a1fb8dd53e1"),
+            ("passwd", "salt", 1, "55ac046e56e3089fec1691c22544b605f94185216dde0465e68b9d57c20dacbc"),
+        ];
+
+        for (password, salt, iterations, expected) in vectors {
+            let actual = CredentialService::pbkdf2_sha256(password.as_bytes(), salt.as_bytes(), iterations);
+            assert_eq!(hex(&actual), expected, "password={} salt={} c={}", password, salt, iterations);
+        }
+    }
+
+    #[test]
+    fn constant_time_eq_compares_length_and_content() {
+        assert!(CredentialService::constant_time_eq(b"secret", b"secret"));
+        assert!(CredentialService::constant_time_eq(b"", b""));
+        assert!(!CredentialService::constant_time_eq(b"secret", b"secreT"));
+        assert!(!CredentialService::constant_time_eq(b"secret", b"secret!"));
+        assert!(!CredentialService::constant_time_eq(b"secret", b""));
+    }
+
+    #[test]
+    fn verify_password_accepts_matching_hash() {
+        let stored = stored_hash("correct horse", &[7u8; SALT_LENGTH], 10);
+
+        assert!(matches!(CredentialService::verify_password("correct horse", &stored), PasswordCheck::Valid { needs_rehash: true }));
+        assert!(matches!(CredentialService::verify_password("wrong horse", &stored), PasswordCheck::Invalid));
+    }
+
+    #[test]
+    fn verify_password_rejects_malformed_hashes() {
+        let salt = general_purpose::STANDARD_NO_PAD.encode([7u8; SALT_LENGTH]);
+        let hash = general_purpose::STANDARD_NO_PAD.encode(CredentialService::pbkdf2_sha256(b"password", &[7u8; SALT_LENGTH], 10));
+
+        let malformed = [
+            String::new(),
+            "password".to_string(),
+            format!("{}$10${}", PASSWORD_ALGORITHM, salt),
+            format!("{}$10${}${}$extra", PASSWORD_ALGORITHM, salt, hash),
+            format!("bcrypt$10${}${}", salt, hash),
+            format!("{}$0${}${}", PASSWORD_ALGORITHM, salt, hash),
+            format!("{}$-1${}${}", PASSWORD_ALGORITHM, salt, hash),
+            format!("{}$abc${}${}", PASSWORD_ALGORITHM, salt, hash),
+            format!("{}$10$!!!${}", PASSWORD_ALGORITHM, hash),
+            format!("{}$10${}$!!!", PASSWORD_ALGORITHM, salt),
+        ];
+
+        for stored in malformed {
+            assert!(matches!(CredentialService::verify_password("password", &stored), PasswordCheck::Invalid), "stored={}", stored);
+        }
+    }
+}
@@ -1,110 +1,423 @@
 use std::collections::HashMap; 
+use std::env;
 use serde_json::{json, Value as JsonValue};
 use actix_web::web;
 use bb8::Pool;
-use bb8_tiberius::ConnectionManager;
 use chrono::{NaiveDate, NaiveDateTime};
-use tiberius::{numeric::Numeric, ColumnType, Row};
+use tiberius::{numeric::Numeric, ColumnType, QueryItem, Row, ToSql};
+use base64::{engine::general_purpose, Engine as _};
 use std::fmt::Write;
+use std::time::Instant;
+use actix_web::web::Bytes;
+use futures::{Stream, StreamExt};
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
+
+use crate::contexts::{cache::{cached, CacheKey}, column_mask::ColumnMasks, config::APP_CONFIG, connection::{query_cancelled, query_timeout, ConnectionManager, DbConnection}, error::AppError, logger::write_log, metrics::METRICS, request_id::{current_request_id, scope_request_id}, row_scope::{is_identifier, RowScope}, model::{ActionResult, DistinctParams, ExportTableParams, QueryClass, ResultList, TableCursor, TableDataParams}, preferences::UserPreferences};
+use super::metadata_service::MetadataService;
+/// Jumlah baris yang boleh menunggu di buffer export sebelum pembacaan dari database ditahan
+const EXPORT_CHANNEL_SIZE: usize = 64;
+/// Format datetime di cursor keyset / watermark, presisi penuh sampai nanodetik
+const CURSOR_DATETIME_FORMAT: &str = "%Y-%m-%dT%H:%M:%S%.f";
 
-use crate::contexts::{logger::write_log, model::{ActionResult, QueryClass, ResultList, TableDataParams}};
 pub struct DataService;
 
 impl DataService {
-    pub async fn get_header(connection: web::Data<Pool<ConnectionManager>>, tablename: String) -> ActionResult<Vec<serde_json::Value>, String> {
+    pub async fn get_header(connection: web::Data<Pool<ConnectionManager>>, tablename: String, masks: &ColumnMasks) -> Result<ActionResult<Vec<serde_json::Value>, String>, AppError> {
         let mut result: ActionResult<Vec<serde_json::Value>, String> = ActionResult::default();
-    
-        match connection.clone().get().await {
-            Ok(mut conn) => {
-                let sql = r#"
-                    DECLARE @Result NVARCHAR(MAX);
-                    EXEC dbo.Web_CreateTableObject @ViewName = @P1, @Result = @Result OUTPUT;
-                    SELECT @Result AS Result;
-                "#;
-    
-                match conn.query(sql, &[&tablename]).await {
-                    Ok(rows) => {
-                        let all_results = rows.into_results().await.unwrap();
-    
-                        // Ambil isi @Result dari SELECT terakhir
-                        let output_result = all_results.last()
-                            .and_then(|set| set.first())
-                            .and_then(|row| row.get::<&str, _>("Result"))
-                            .unwrap_or("");
-    
-                        // Parse isi @Result ke Vec<serde_json::Value>
-                        match serde_json::from_str::<Vec<serde_json::Value>>(output_result) {
-                            Ok(parsed_json) => {
-                                result.data = Some(parsed_json);
-                                result.result = true;
-                                result.message = "Data retrieved successfully".to_string();
-                            }
-                            Err(e) => {
-                                result.message = "Failed to parse JSON".to_string();
-                                result.error = Some(e.to_string());
-                            }
-                        }
-    
-                        return result;
-                    }
-                    Err(e) => {
-                        result.message = "Query failed".to_string();
-                        result.error = Some(e.to_string());
-                        return result;
-                    }
-                }
+
+        // Metadata kolom dari INFORMATION_SCHEMA, procedure hanya dipakai jika dikonfigurasi
+        let columns = MetadataService::get_columns_cached(&connection, &tablename).await;
+
+        let mut columns = match columns {
+            Ok(JsonValue::Array(columns)) if !columns.is_empty() => columns,
+            Ok(_) if !APP_CONFIG.metadata.procedure_fallback => {
+                return Err(AppError::NotFound(format!("View '{}' not found", tablename)));
             }
+            Err(e) if !APP_CONFIG.metadata.procedure_fallback => return Err(e),
+            Ok(_) => Self::get_header_procedure(&connection, &tablename).await?,
             Err(e) => {
-                result.message = "Connection failed".to_string();
-                result.error = Some(e.to_string());
-                return result;
+                write_log("WARN", &format!("[{}] Column metadata failed for '{}', using Web_CreateTableObject: {}", e.code(), tablename, e));
+                Self::get_header_procedure(&connection, &tablename).await?
             }
-        }
+        };
+
+        masks.mark_header(&mut columns);
+
+        result.data = Some(columns);
+        result.result = true;
+        result.message = "Data retrieved successfully".to_string();
+
+        Ok(result)
+    }
+
+    /// Definisi kolom lama dari procedure `dbo.Web_CreateTableObject`
+    async fn get_header_procedure(connection: &Pool<ConnectionManager>, tablename: &str) -> Result<Vec<serde_json::Value>, AppError> {
+        let mut conn = connection.get().await?;
+        let sql = r#"
+            DECLARE @Result NVARCHAR(MAX);
+            EXEC dbo.Web_CreateTableObject @ViewName = @P1, @Result = @Result OUTPUT;
+            SELECT @Result AS Result;
+        "#;
+
+        let timeout = query_timeout("HEADER");
+        conn.mark_in_flight();
+        let all_results = tokio::time::timeout(timeout, async {
+            conn.query(sql, &[&tablename]).await?.into_results().await
+        }).await.map_err(|_| query_cancelled(timeout))??;
+        conn.mark_done();
+
+        // Ambil isi @Result dari SELECT terakhir
+        let output_result = all_results.last()
+            .and_then(|set| set.first())
+            .and_then(|row| row.get::<&str, _>("Result"))
+            .unwrap_or("");
+
+        // Parse isi @Result ke Vec<serde_json::Value>
+        serde_json::from_str::<Vec<serde_json::Value>>(output_result)
+            .map_err(|e| AppError::Internal(format!("Failed to parse header JSON for '{}': {}", tablename, e)))
     }
     
 
-    pub async fn get_table_data(allparams: TableDataParams, connection: web::Data<Pool<ConnectionManager>>) -> Result<ResultList, Box<dyn std::error::Error>> {
+    pub async fn get_table_data(allparams: TableDataParams, connection: web::Data<Pool<ConnectionManager>>, scope: &RowScope, masks: &ColumnMasks) -> Result<ResultList, AppError> {
+        Self::check_sort(allparams.sort.as_deref(), allparams.order.as_deref(), allparams.nidkey.as_deref(), masks)?;
+        // Nilai key ikut tersimpan di cursor
+        if allparams.cursor.is_some() {
+            masks.require_unmasked(allparams.nidkey.as_deref().unwrap_or("AutoNID"))?;
+        }
+
         let mut result = ResultList {
             totalNotFiltered: 0,
             total: 0,
             rows: vec![],
+            next_cursor: None,
         };
     
-        let query = Self::get_query_table(allparams.clone(), false);
+        // Mode keyset: query tanpa OFFSET, ORDER BY + kondisi cursor ditambahkan di get_query_table_keyset
+        let query = Self::get_query_table(allparams.clone(), allparams.cursor.is_some(), scope);
+        let timeout = query_timeout("GET_TABLE");
+        let started = Instant::now();
     
         let mut client = connection.get().await?;
+        client.mark_in_flight();
+
+        // Jika timeout atau client disconnect, future di-drop dan koneksi ditutup oleh pool
+        tokio::time::timeout(timeout, async {
+            if !allparams.tablename.is_empty() {
+                let key = CacheKey::new(&allparams.tablename, allparams.filter.as_deref(), "count_all").scoped(scope);
+                let total = cached(key, Self::query_count(&mut client, &query.query_total_all)).await?;
+                result.totalNotFiltered = total.as_i64().unwrap_or(0) as i32;
+            }
+        
+            // Hitung total data yang sesuai filter
+            if let Some(filter) = &allparams.filter {
+                if filter != "{filter:undefined}" {
+                    let key = CacheKey::new(&allparams.tablename, Some(filter), "count_filtered").scoped(scope);
+                    let total = cached(key, Self::query_count(&mut client, &query.query_total_with_filter)).await?;
+                    result.total = total.as_i64().unwrap_or(0) as i32;
+                }
+            } else {
+                result.total = result.totalNotFiltered;
+            }
+        
+            let (sql, params) = match &allparams.cursor {
+                Some(cursor) => Self::get_query_table_keyset(&allparams, &query.query, cursor)?,
+                None => (query.query.clone(), vec![]),
+            };
+
+            write_log("INFO", &format!("Query: {}", sql));
+        
+            let param_refs: Vec<&dyn ToSql> = params.iter().map(|p| p.as_ref()).collect();
+            let mut rows: Vec<Row> = client.query(sql, &param_refs).await?.into_results().await?
+                .into_iter()
+                .flatten()
+                .collect();
+
+            // Cursor dibuat dari nilai asli (sebelum masking, tanpa pembulatan desimal)
+            if allparams.cursor.is_some() {
+                result.next_cursor = Self::next_cursor(&allparams, &mut rows)?;
+            }
+
+            result.rows = rows.iter()
+                .map(|row| masks.apply(Self::row_to_json(row)))  // 🔥 Ubah `Row` ke JSON
+                .collect();
+
+            Ok::<(), AppError>(())
+        }).await.map_err(|_| query_cancelled(timeout))??;
+
+        client.mark_done();
+        METRICS.observe_query("get_table_data", &allparams.tablename, started.elapsed());
     
-        if !allparams.tablename.is_empty() {
-            let row: Option<Row> = client.query(query.query_total_all.clone(), &[]).await?.into_row().await?;
-            if let Some(r) = row {
-                result.totalNotFiltered = r.try_get::<i32, _>(0)?.unwrap_or(0);
+        Ok(result)
+    }
+    
+    /// Nilai unik satu kolom (beserta jumlah baris) untuk filter select.
+    /// Kolom dengan nilai unik di atas `DISTINCT_MAX_CARDINALITY` (default 200) ditolak.
+    pub async fn get_distinct_values(params: DistinctParams, connection: web::Data<Pool<ConnectionManager>>, scope: &RowScope) -> Result<ActionResult<Vec<JsonValue>, String>, AppError> {
+        let mut result: ActionResult<Vec<JsonValue>, String> = ActionResult::default();
+
+        if !is_identifier(&params.column) {
+            return Err(AppError::BadRequest(format!("Invalid column name '{}'", params.column)));
+        }
+
+        let max_cardinality: i32 = env::var("DISTINCT_MAX_CARDINALITY").ok().and_then(|v| v.parse().ok()).unwrap_or(200);
+        let limit = params.limit.unwrap_or(50).clamp(1, 500);
+
+        // Filter kolom itu sendiri diabaikan supaya dropdown tetap menampilkan semua pilihan
+        let mut q_where = scope.where_clause();
+        if let Some(filter) = params.filter.as_deref().filter(|f| *f != "{filter:undefined}") {
+            let mut filter_name = serde_json::from_str::<HashMap<String, String>>(filter)
+                .map_err(|e| AppError::BadRequest(format!("Invalid filter: {}", e)))?;
+            filter_name.retain(|key, _| !key.eq_ignore_ascii_case(&params.column));
+
+            if !filter_name.is_empty() {
+                q_where = Self::get_query_table_where(q_where, filter_name);
             }
         }
-    
-        // Hitung total data yang sesuai filter
-        if let Some(filter) = &allparams.filter {
+
+        let timeout = query_timeout("DISTINCT");
+
+        let count_key = CacheKey::new(&params.tablename, params.filter.as_deref(), &format!("distinct_count:{}", params.column)).scoped(scope);
+        let cardinality = cached(count_key, async {
+            let query = format!("SELECT COUNT(DISTINCT [{}]) FROM [{}] {}", params.column, params.tablename, q_where);
+            let mut conn = connection.get().await?;
+
+            conn.mark_in_flight();
+            let row = tokio::time::timeout(timeout, async {
+                conn.query(query, &[]).await?.into_row().await
+            }).await.map_err(|_| query_cancelled(timeout))??;
+            conn.mark_done();
+
+            Ok::<_, AppError>(json!(row.and_then(|r| r.get::<i32, _>(0)).unwrap_or(0)))
+        }).await?.as_i64().unwrap_or(0);
+
+        if cardinality > max_cardinality as i64 {
+            return Err(AppError::BadRequest(format!(
+                "Column '{}' has too many distinct values ({} > {}), use a text filter instead",
+                params.column, cardinality, max_cardinality
+            )));
+        }
+
+        let mut query = format!(
+            "SELECT TOP ({}) CAST([{}] AS nvarchar(4000)) AS Value, COUNT(*) AS Count FROM [{}] {}",
+            limit, params.column, params.tablename, q_where
+        );
+        let search = params.search.as_deref().map(str::trim).filter(|s| !s.is_empty())
+            .map(|s| format!("%{}%", s.replace('[', "[[]").replace('%', "[%]").replace('_', "[_]")));
+        if search.is_some() {
+            let _ = write!(query, " AND CAST([{}] AS nvarchar(4000)) LIKE @P1", params.column);
+        }
+        let _ = write!(query, " GROUP BY [{0}] ORDER BY COUNT(*) DESC, [{0}]", params.column);
+
+        let started = Instant::now();
+        let mut conn = connection.get().await?;
+
+        conn.mark_in_flight();
+        let rows = tokio::time::timeout(timeout, async {
+            match &search {
+                Some(search) => conn.query(query, &[search]).await?.into_first_result().await,
+                None => conn.query(query, &[]).await?.into_first_result().await,
+            }
+        }).await.map_err(|_| query_cancelled(timeout))??;
+        conn.mark_done();
+        METRICS.observe_query("get_distinct_values", &params.tablename, started.elapsed());
+
+        result.data = Some(rows.iter().map(Self::row_to_json).collect());
+        result.result = true;
+        result.message = "Data retrieved successfully".to_string();
+
+        Ok(result)
+    }
+
+    /// Export baris sebagai NDJSON, atau CSV dengan angka / tanggal diformat sesuai `preferences` jika diisi.
+    /// Query berjalan di task terpisah dengan koneksi milik sendiri; channel yang terbatas
+    /// membuat pembacaan dari SQL Server ikut berhenti jika client membaca lebih lambat.
+    pub async fn export_table_data(params: ExportTableParams, connection: web::Data<Pool<ConnectionManager>>, scope: &RowScope, masks: ColumnMasks, preferences: Option<UserPreferences>) -> Result<impl Stream<Item = Result<Bytes, AppError>>, AppError> {
+        Self::check_sort(params.sort.as_deref(), params.order.as_deref(), None, &masks)?;
+
+        let query = Self::get_query_export(&params, scope);
+        let pool = connection.get_ref().clone();
+        let (tx, mut rx) = mpsc::channel::<Result<Bytes, AppError>>(EXPORT_CHANNEL_SIZE);
+
+        tokio::spawn(scope_request_id(current_request_id(), async move {
+            let started = Instant::now();
+
+            let mut conn = match pool.get_owned().await {
+                Ok(conn) => conn,
+                Err(e) => {
+                    let _ = tx.send(Err(e.into())).await;
+                    return;
+                }
+            };
+
+            // Jika client disconnect, send gagal dan koneksi (masih in-flight) ditutup oleh pool
+            conn.mark_in_flight();
+            write_log("INFO", &format!("Export query: {}", query));
+
+            let mut stream = match conn.query(query, &[]).await {
+                Ok(stream) => stream,
+                Err(e) => {
+                    let _ = tx.send(Err(e.into())).await;
+                    return;
+                }
+            };
+
+            let mut csv_columns: Vec<(String, ColumnType)> = Vec::new();
+
+            while let Some(item) = stream.next().await {
+                match item {
+                    // Header CSV dari metadata, jadi export tanpa baris tetap punya header
+                    Ok(QueryItem::Metadata(metadata)) => {
+                        let Some(preferences) = &preferences else {
+                            continue;
+                        };
+                        if !csv_columns.is_empty() {
+                            continue;
+                        }
+
+                        // Urutan kolom dari query, tanpa kolom yang dihapus oleh masking
+                        csv_columns = metadata.columns().iter()
+                            .filter(|col| !masks.is_hidden(col.name()))
+                            .map(|col| (col.name().to_string(), col.column_type()))
+                            .collect();
+                        let header = Self::csv_line(csv_columns.iter().map(|(name, _)| name.clone()), preferences.csv_delimiter());
+
+                        if tx.send(Ok(Bytes::from(header))).await.is_err() {
+                            return;
+                        }
+                    }
+                    Ok(QueryItem::Row(row)) => {
+                        let json = masks.apply(Self::row_to_json(&row));
+                        let line = match &preferences {
+                            None => {
+                                let mut line = serde_json::to_vec(&json).unwrap_or_default();
+                                line.push(b'\n');
+                                line
+                            }
+                            Some(preferences) => {
+                                let values = csv_columns.iter().map(|(name, column_type)| Self::format_csv_value(&json[name.as_str()], column_type, preferences));
+                                Self::csv_line(values, preferences.csv_delimiter()).into_bytes()
+                            }
+                        };
+
+                        if tx.send(Ok(Bytes::from(line))).await.is_err() {
+                            return;
+                        }
+                    }
+                    Err(e) => {
+                        let _ = tx.send(Err(e.into())).await;
+                        return;
+                    }
+                }
+            }
+
+            drop(stream);
+            conn.mark_done();
+            METRICS.observe_query("export_table_data", &params.tablename, started.elapsed());
+        }));
+
+        // Tunggu item pertama supaya error awal (view tidak ada, dll) tetap dikembalikan sebagai ActionResult
+        match rx.recv().await {
+            Some(Err(e)) => Err(e),
+            first => Ok(futures::stream::iter(first).chain(ReceiverStream::new(rx))),
+        }
+    }
+
+    /// Satu baris CSV (RFC 4180), nilai yang berisi pemisah / kutip / baris baru diberi tanda kutip.
+    /// Nilai yang diawali `=`, `+`, `-`, `@`, tab atau carriage return diberi prefix `'` supaya tidak
+    /// dijalankan sebagai formula oleh Excel, kecuali angka negatif hasil `format_number`.
+    fn csv_line(values: impl Iterator<Item = String>, delimiter: char) -> String {
+        let is_negative_number = |value: &str| value.strip_prefix('-').is_some_and(|rest| {
+            rest.starts_with(|c: char| c.is_ascii_digit()) && rest.chars().all(|c| c.is_ascii_digit() || matches!(c, '.' | ',' | ' '))
+        });
+
+        let mut line = values
+            .map(|value| match value.starts_with(['=', '+', '-', '@', '\t', '\r']) && !is_negative_number(&value) {
+                true => format!("'{}", value),
+                false => value,
+            })
+            .map(|value| match value.contains([delimiter, '"', '\n', '\r']) {
+                true => format!("\"{}\"", value.replace('"', "\"\"")),
+                false => value,
+            })
+            .collect::<Vec<_>>()
+            .join(&delimiter.to_string());
+        line.push_str("\r\n");
+        line
+    }
+
+    /// Nilai CSV sesuai preferensi user. Kolom integer tidak diberi pemisah ribuan karena biasanya berupa ID / kode.
+    fn format_csv_value(value: &JsonValue, column_type: &ColumnType, preferences: &UserPreferences) -> String {
+        match (value, column_type) {
+            (JsonValue::Null, _) => String::new(),
+            (JsonValue::Bool(b), _) => preferences.format_bool(*b).to_string(),
+            (JsonValue::Number(n), ColumnType::Numericn | ColumnType::Decimaln | ColumnType::Floatn | ColumnType::Float4 | ColumnType::Float8 | ColumnType::Money | ColumnType::Money4) => {
+                n.as_f64().map(|n| preferences.format_number(n)).unwrap_or_else(|| n.to_string())
+            }
+            (JsonValue::String(s), ColumnType::Datetimen | ColumnType::Datetime | ColumnType::Datetime2 | ColumnType::Datetime4) => {
+                NaiveDateTime::parse_from_str(s, "%Y-%m-%dT%H:%M:%S%.f")
+                    .map(|dt| preferences.format_datetime(dt))
+                    .unwrap_or_else(|_| s.clone())
+            }
+            (JsonValue::String(s), _) => s.clone(),
+            (other, _) => other.to_string(),
+        }
+    }
+
+    fn get_query_export(params: &ExportTableParams, scope: &RowScope) -> String {
+        let mut q_and_where = scope.where_clause();
+        let mut q_order_by = String::new();
+
+        if let Some(filter) = &params.filter {
             if filter != "{filter:undefined}" {
-                let row: Option<Row> = client.query(query.query_total_with_filter.clone(), &[]).await?.into_row().await?;
-                if let Some(r) = row {
-                    result.total = r.try_get::<i32, _>(0)?.unwrap_or(0);
+                if let Ok(filter_name) = serde_json::from_str::<HashMap<String, String>>(filter) {
+                    if !filter_name.is_empty() {
+                        q_and_where = Self::get_query_table_where(q_and_where, filter_name);
+                    }
                 }
             }
-        } else {
-            result.total = result.totalNotFiltered;
         }
-    
-        write_log("INFO", &format!("Query: {}", query.query));
-    
-        let rows = client.query(query.query.clone(), &[]).await?.into_results().await?;
-        result.rows = rows.into_iter()
-            .flat_map(|r| r.into_iter())
-            .map(|row| Self::row_to_json(&row))  // 🔥 Ubah `Row` ke JSON
-            .collect();
-    
-        Ok(result)
+
+        if let Some(sort) = &params.sort {
+            let _ = write!(q_order_by, " ORDER BY {} {}", sort, params.order.clone().unwrap_or_default());
+        }
+
+        format!("SELECT * FROM [{}] {} {}", params.tablename, q_and_where, q_order_by)
+    }
+
+    /// Jalankan `SELECT count(*)` dan kembalikan hasilnya sebagai JSON untuk disimpan di cache
+    async fn query_count(client: &mut DbConnection, sql: &str) -> Result<JsonValue, AppError> {
+        let row: Option<Row> = client.query(sql, &[]).await?.into_row().await?;
+        let total = match row {
+            Some(r) => r.try_get::<i32, _>(0)?.unwrap_or(0),
+            None => 0,
+        };
+
+        Ok(json!(total))
     }
     
-    fn get_query_table(allparams: TableDataParams, bypass_skip: bool) -> QueryClass {
+    /// Sort, order dan primary key disisipkan langsung ke ORDER BY, jadi harus berupa nama kolom.
+    /// Sort pada kolom yang di-mask ditolak karena urutan baris membocorkan nilai aslinya.
+    fn check_sort(sort: Option<&str>, order: Option<&str>, nidkey: Option<&str>, masks: &ColumnMasks) -> Result<(), AppError> {
+        if let Some(sort) = sort.filter(|s| !s.is_empty()) {
+            if !is_identifier(sort) {
+                return Err(AppError::BadRequest(format!("Invalid sort column '{}'", sort)));
+            }
+            masks.require_unmasked(sort)?;
+        }
+
+        if let Some(order) = order.filter(|o| !o.is_empty() && !o.eq_ignore_ascii_case("asc") && !o.eq_ignore_ascii_case("desc")) {
+            return Err(AppError::BadRequest(format!("Invalid order '{}'", order)));
+        }
+
+        if let Some(nidkey) = nidkey.filter(|k| !is_identifier(k)) {
+            return Err(AppError::BadRequest(format!("Invalid key column '{}'", nidkey)));
+        }
+
+        Ok(())
+    }
+
+    fn get_query_table(allparams: TableDataParams, bypass_skip: bool, scope: &RowScope) -> QueryClass {
         let mut result = QueryClass {
             query: String::new(),
             query_total_all: String::new(),
@@ -117,10 +430,10 @@ impl DataService {
     
         let tablename = format!("[{}]", allparams.tablename);
         let mut query_total_all = format!("SELECT count(*) as total FROM {}", tablename);
-        let mut q_and_where = String::from(" WHERE 1=1 ");
+        let mut q_and_where = scope.where_clause();
         let mut q_order_by = String::new();
         let mut q_skip_row = String::new();
-        let mut q_and_where_for_total_with_filter = String::from(" WHERE 1=1 ");
+        let mut q_and_where_for_total_with_filter = scope.where_clause();
     
         // Gunakan `nidkey` sebagai primary key jika tersedia
         let q_primary_key = allparams.nidkey.clone().unwrap_or_else(|| "AutoNID".to_string());
@@ -176,8 +489,162 @@ impl DataService {
         result
     }
 
+    /// Kolom sort dan arah order untuk mode keyset. Tanpa `sort`, urut berdasarkan primary key DESC
+    fn keyset_sort(allparams: &TableDataParams) -> Result<(Option<String>, String), AppError> {
+        let default_order = if allparams.sort.is_some() { "ASC" } else { "DESC" };
+        let order = allparams.order.clone().unwrap_or_else(|| default_order.to_string()).to_uppercase();
+
+        if order != "ASC" && order != "DESC" {
+            return Err(AppError::BadRequest(format!("Invalid order '{}'", order)));
+        }
+
+        Ok((allparams.sort.clone().filter(|s| !s.is_empty()), order))
+    }
+
+    /// Tambahkan kondisi cursor, ORDER BY (sort + primary key) dan FETCH limit+1 ke query utama.
+    /// Nilai cursor dikirim sebagai parameter, bukan disisipkan ke SQL.
+    fn get_query_table_keyset(allparams: &TableDataParams, base_query: &str, cursor: &str) -> Result<(String, Vec<Box<dyn ToSql>>), AppError> {
+        let q_primary_key = allparams.nidkey.clone().unwrap_or_else(|| "AutoNID".to_string());
+        let (sort, order) = Self::keyset_sort(allparams)?;
+        let mut query = base_query.to_string();
+        let mut params: Vec<Box<dyn ToSql>> = Vec::new();
+
+        if !cursor.is_empty() {
+            let decoded = Self::decode_cursor(cursor)?;
+            if decoded.sort != sort.clone().unwrap_or_default() || decoded.order != order {
+                return Err(AppError::BadRequest("Cursor does not match the current sort. Reload the first page".to_string()));
+            }
+
+            let cmp = if order == "ASC" { ">" } else { "<" };
+            params.push(Self::json_to_sql_param(&decoded.key)?);
+
+            match (&sort, &decoded.value) {
+                (None, _) => {
+                    let _ = write!(query, " AND {} {} @P1", q_primary_key, cmp);
+                }
+                // NULL ada di urutan paling awal pada ASC dan paling akhir pada DESC
+                (Some(sort), JsonValue::Null) if order == "ASC" => {
+                    let _ = write!(query, " AND (({0} IS NULL AND {1} > @P1) OR {0} IS NOT NULL)", sort, q_primary_key);
+                }
+                (Some(sort), JsonValue::Null) => {
+                    let _ = write!(query, " AND ({0} IS NULL AND {1} < @P1)", sort, q_primary_key);
+                }
+                (Some(sort), value) => {
+                    params.push(Self::json_to_sql_param(value)?);
+                    let _ = write!(query, " AND ({0} {2} @P2 OR ({0} = @P2 AND {1} {2} @P1)", sort, q_primary_key, cmp);
+                    if order == "DESC" {
+                        let _ = write!(query, " OR {} IS NULL", sort);
+                    }
+                    query.push(')');
+                }
+            }
+        }
+
+        match &sort {
+            Some(sort) => { let _ = write!(query, " ORDER BY {} {}, {} {}", sort, order, q_primary_key, order); }
+            None => { let _ = write!(query, " ORDER BY {} {}", q_primary_key, order); }
+        }
+
+        // Ambil 1 baris ekstra untuk tahu apakah masih ada halaman berikutnya
+        let _ = write!(query, " OFFSET 0 ROWS FETCH NEXT {} ROWS ONLY", allparams.limit + 1);
+
+        Ok((query, params))
+    }
+
+    /// Buang baris ekstra dan buat cursor dari baris terakhir, `None` jika sudah halaman terakhir
+    fn next_cursor(allparams: &TableDataParams, rows: &mut Vec<Row>) -> Result<Option<String>, AppError> {
+        if rows.len() <= allparams.limit.max(0) as usize {
+            return Ok(None);
+        }
+        rows.truncate(allparams.limit.max(0) as usize);
+
+        let q_primary_key = allparams.nidkey.clone().unwrap_or_else(|| "AutoNID".to_string());
+        let (sort, order) = Self::keyset_sort(allparams)?;
+        let last = match rows.last() {
+            Some(last) => last,
+            None => return Ok(None),
+        };
+
+        let key = Self::cursor_value(last, &q_primary_key)
+            .filter(|key| !key.is_null())
+            .ok_or_else(|| {
+                AppError::BadRequest(format!("Primary key column '{}' not found in '{}', pass nidkey", q_primary_key, allparams.tablename))
+            })?;
+        let value = match &sort {
+            Some(sort) => Self::cursor_value(last, sort).unwrap_or(JsonValue::Null),
+            None => JsonValue::Null,
+        };
+
+        let cursor = TableCursor { sort: sort.unwrap_or_default(), order, value, key };
+        let encoded = serde_json::to_vec(&cursor).map_err(|e| AppError::Internal(e.to_string()))?;
+
+        Ok(Some(general_purpose::URL_SAFE_NO_PAD.encode(encoded)))
+    }
+
+    /// Nilai kolom untuk cursor keyset. Decimal / numeric disimpan sebagai teks dengan scale aslinya,
+    /// supaya perbandingan `sort = @P2` di SQL Server tidak memakai nilai f64 yang sudah dibulatkan.
+    /// Datetime disimpan sebagai `{"datetime": ...}` supaya di-bind kembali sebagai `NaiveDateTime`,
+    /// karena teks dengan pecahan detik lebih dari 3 digit tidak bisa dikonversi ke `datetime`.
+    pub fn cursor_value(row: &Row, column: &str) -> Option<JsonValue> {
+        let index = row.columns().iter().position(|col| col.name().eq_ignore_ascii_case(column))?;
+
+        match row.columns()[index].column_type() {
+            ColumnType::Numericn | ColumnType::Decimaln => Some(
+                row.try_get::<Numeric, _>(index).ok().flatten()
+                    .map(|numeric| json!(numeric.to_string()))
+                    .unwrap_or(JsonValue::Null),
+            ),
+            ColumnType::Datetime | ColumnType::Datetime4 | ColumnType::Datetimen | ColumnType::Datetime2 => Some(
+                row.try_get::<NaiveDateTime, _>(index).ok().flatten()
+                    .map(|datetime| json!({ "datetime": datetime.format(CURSOR_DATETIME_FORMAT).to_string() }))
+                    .unwrap_or(JsonValue::Null),
+            ),
+            // Nilai asli tanpa pembulatan lewat teks, supaya `real` sama persis dengan nilai di database
+            ColumnType::Float4 | ColumnType::Float8 | ColumnType::Floatn | ColumnType::Money | ColumnType::Money4 => Some(
+                row.try_get::<f64, _>(index).ok().flatten()
+                    .or_else(|| row.try_get::<f32, _>(index).ok().flatten().map(f64::from))
+                    .map(|value| json!(value))
+                    .unwrap_or(JsonValue::Null),
+            ),
+            _ => Self::row_to_json(row).get(row.columns()[index].name()).cloned(),
+        }
+    }
+
+    fn decode_cursor(cursor: &str) -> Result<TableCursor, AppError> {
+        general_purpose::URL_SAFE_NO_PAD.decode(cursor)
+            .ok()
+            .and_then(|bytes| serde_json::from_slice::<TableCursor>(&bytes).ok())
+            .ok_or_else(|| AppError::BadRequest("Invalid cursor".to_string()))
+    }
+
+    /// Nilai cursor sebagai parameter SQL. Teks yang dibandingkan dengan kolom decimal dikonversi
+    /// oleh SQL Server ke tipe kolom, jadi desimal dari `cursor_value` tetap presisi.
+    pub fn json_to_sql_param(value: &JsonValue) -> Result<Box<dyn ToSql>, AppError> {
+        if let Some(datetime) = value.get("datetime").and_then(|v| v.as_str()) {
+            return NaiveDateTime::parse_from_str(datetime, CURSOR_DATETIME_FORMAT)
+                .map(|datetime| Box::new(datetime) as Box<dyn ToSql>)
+                .map_err(|_| AppError::BadRequest("Invalid cursor".to_string()));
+        }
+
+        match value {
+            JsonValue::Number(n) if n.is_i64() => Ok(Box::new(n.as_i64().unwrap_or_default())),
+            JsonValue::Number(n) => Ok(Box::new(n.as_f64().unwrap_or_default())),
+            JsonValue::String(s) => Ok(Box::new(s.clone())),
+            JsonValue::Bool(b) => Ok(Box::new(*b)),
+            _ => Err(AppError::BadRequest("Invalid cursor".to_string())),
+        }
+    }
+
+    /// Tambahkan filter dari client ke WHERE. Key yang bukan nama kolom diabaikan dan
+    /// nilai di-escape, supaya filter tidak bisa keluar dari kondisi row-level security.
     pub fn get_query_table_where(mut fquery: String, filter_name: HashMap<String, String>) -> String {
         for (key, value) in filter_name {
+            if !is_identifier(&key) {
+                write_log("WARN", &format!("Ignoring invalid filter column '{}'", key));
+                continue;
+            }
+
+            let value = value.replace('\'', "''");
             if let Ok(temp_date) = NaiveDate::parse_from_str(&value, "%Y-%m-%d") {
                 if key.ends_with("Date") {
                     let next_date = temp_date.succ_opt().unwrap_or(temp_date);
@@ -208,6 +675,21 @@ impl DataService {
         fquery
     }
 
+    /// Nilai kolom tinyint / smallint / int / bigint. `Intn` bisa berisi salah satunya tergantung lebar kolom.
+    fn int_value(row: &Row, index: usize) -> Option<i64> {
+        row.try_get::<i64, _>(index).ok().flatten()
+            .or_else(|| row.try_get::<i32, _>(index).ok().flatten().map(i64::from))
+            .or_else(|| row.try_get::<i16, _>(index).ok().flatten().map(i64::from))
+            .or_else(|| row.try_get::<u8, _>(index).ok().flatten().map(i64::from))
+    }
+
+    /// Nilai kolom float / real / money. Kolom `real` dibaca sebagai f32 lalu lewat teks,
+    /// supaya 0.1 tidak menjadi 0.10000000149011612.
+    fn float_value(row: &Row, index: usize) -> Option<f64> {
+        row.try_get::<f64, _>(index).ok().flatten()
+            .or_else(|| row.try_get::<f32, _>(index).ok().flatten().and_then(|value| value.to_string().parse().ok()))
+    }
+
     pub fn row_to_json(row: &Row) -> JsonValue {
         let mut json_obj = serde_json::Map::new();
 
@@ -225,12 +707,8 @@ impl DataService {
                         json_obj.insert(col_name.to_string(), json!(null));
                     }
                 },
-                ColumnType::Int4 | ColumnType::Int8 | ColumnType::Intn => {
-                    if let Ok(value) = row.try_get::<i32, _>(i) {
-                        json_obj.insert(col_name.to_string(), json!(value));
-                    } else {
-                        json_obj.insert(col_name.to_string(), json!(null));
-                    }
+                ColumnType::Int1 | ColumnType::Int2 | ColumnType::Int4 | ColumnType::Int8 | ColumnType::Intn => {
+                    json_obj.insert(col_name.to_string(), json!(Self::int_value(row, i)));
                 },
                 ColumnType::Bit => {
                     if let Ok(value) = row.try_get::<bool, _>(i) {
@@ -239,7 +717,7 @@ impl DataService {
                         json_obj.insert(col_name.to_string(), json!(null));
                     }
                 },
-                ColumnType::Datetimen => {
+                ColumnType::Datetime | ColumnType::Datetime4 | ColumnType::Datetimen | ColumnType::Datetime2 => {
                     if let Ok(value) = row.try_get::<NaiveDateTime, _>(i) {
                         json_obj.insert(col_name.to_string(), json!(value));
                     } else {
@@ -253,7 +731,10 @@ impl DataService {
                         json_obj.insert(col_name.to_string(), json!(null));
                     }
                 },
-                ColumnType::Numericn => {
+                ColumnType::Float4 | ColumnType::Float8 | ColumnType::Floatn | ColumnType::Money | ColumnType::Money4 => {
+                    json_obj.insert(col_name.to_string(), json!(Self::float_value(row, i)));
+                },
+                ColumnType::Numericn | ColumnType::Decimaln => {
                     if let Ok(Some(numeric)) = row.try_get::<Numeric, _>(i) {
                         let raw_value = numeric.value();
                         let scale = numeric.scale();
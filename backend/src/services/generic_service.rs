@@ -1,160 +1,107 @@
-use actix_web::{error, web, HttpRequest, HttpResponse, Responder, Result};
+use actix_web::{error, web, HttpRequest, HttpResponse};
 use bb8::Pool;
-use bb8_tiberius::ConnectionManager;
 use futures::StreamExt;
 use serde_json::json;
 use tiberius::{QueryItem, QueryStream};
 
-use crate::contexts::model::{ActionResult, Company, Order};
+use crate::contexts::{column_mask::ColumnMasks, connection::ConnectionManager, error::AppError, model::{ActionResult, Company, Order}, row_scope::RowScope};
 
 pub struct GenericService;
 
 impl GenericService {
-    pub async fn get_company(connection: web::Data<Pool<ConnectionManager>>) -> ActionResult<Company, String> {
+    pub async fn get_company(connection: web::Data<Pool<ConnectionManager>>) -> Result<ActionResult<Company, String>, AppError> {
         let mut result = ActionResult::default();
 
-        match connection.clone().get().await {
-            Ok(mut conn) => {
-                let query_result: Result<QueryStream, _> = conn
-                    .query("SELECT CompanyID, CompanyName FROM Company", &[])
-                    .await;
-                match query_result {
-                    Ok(rows) => {
-                        if let Ok(Some(row)) = rows.into_row().await {
-                            result.result = true;
-                            result.message = "Company name".to_string();
-                            result.data = Some(Company {
-                                company_id: row
-                                    .get::<&str, _>("CompanyID")
-                                    .map_or_else(|| "".to_string(), |s| s.to_string()),
-                                company_name: row
-                                    .get::<&str, _>("CompanyName")
-                                    .map_or_else(|| "".to_string(), |s| s.to_string()),
-                            });
-                            return result;
-                        } else {
-                            result.message = "No company found".to_string();
-                            return result;
-                        }
-                    }
-                    Err(e) => {
-                        result.message = "Internal Server Error".to_string();
-                        result.error = Some(e.to_string());
-                        return result;
-                    }
-                }
-            }
-            Err(e) => {
-                result.error = Some(e.to_string());
-                return result;
-            }
-        }
+        let mut conn = connection.get().await?;
+        let rows: QueryStream = conn
+            .query("SELECT CompanyID, CompanyName FROM Company", &[])
+            .await?;
+
+        let row = rows.into_row().await?
+            .ok_or_else(|| AppError::NotFound("No company found".to_string()))?;
+
+        result.result = true;
+        result.message = "Company name".to_string();
+        result.data = Some(Company {
+            company_id: row
+                .get::<&str, _>("CompanyID")
+                .map_or_else(|| "".to_string(), |s| s.to_string()),
+            company_name: row
+                .get::<&str, _>("CompanyName")
+                .map_or_else(|| "".to_string(), |s| s.to_string()),
+        });
+
+        Ok(result)
     }
 
-    pub async fn get_orders(pool: web::Data<Pool<ConnectionManager>>, last_id: Option<i64>, limit: Option<i32>) -> ActionResult<Vec<Order>, String> {
+    pub async fn get_orders(pool: web::Data<Pool<ConnectionManager>>, last_id: Option<i64>, limit: Option<i32>, scope: &RowScope, masks: &ColumnMasks) -> Result<ActionResult<Vec<Order>, String>, AppError> {
         let mut result = ActionResult::<Vec<Order>, String>::default();
 
         let limit = limit.unwrap_or(50).min(10000);
         let last_id = last_id.unwrap_or(0);
 
-        match pool.clone().get().await {
-            Ok(mut conn) => {
-                let query = r#"
-                    SELECT TOP (@P1) CIFLookupNID, CIFLookupID, CAST(EndIncome AS float) as EndIncome, LastUpdate
-                    FROM [CIFLookup]
-                    WHERE CIFLookupNID > @P2
-                    ORDER BY CIFLookupNID ASC
-                "#;
-
-                match conn.query(query, &[&limit, &last_id]).await {
-                    Ok(mut rows) => {
-                        let mut data = Vec::new();
-
-                        while let Some(row_result) = rows.next().await {
-                            match row_result {
-                                Ok(QueryItem::Row(row)) => {
-                                    data.push(Order {
-                                        id: row.get::<i32, _>("CIFLookupNID").unwrap_or_default(),
-                                        customer_name: row
-                                            .get::<&str, _>("CIFLookupID")
-                                            .unwrap_or_default()
-                                            .to_string(),
-                                        total_price: row.get::<f64, _>("EndIncome").unwrap_or(0.0),
-                                        created_at: row
-                                            .get::<chrono::NaiveDateTime, _>("LastUpdate")
-                                            .unwrap(),
-                                    });
-                                }
-                                Ok(_) => continue, // Misal QueryItem::Done atau lainnya
-                                Err(e) => {
-                                    result.message = "Error reading row".to_string();
-                                    result.error = Some(e.to_string());
-                                    return result;
-                                }
-                            }
-                        }
-
-                        result.result = true;
-                        result.message = "List orders".to_string();
-                        result.data = Some(data);
-                    }
-                    Err(e) => {
-                        result.message = "Failed to query data".to_string();
-                        result.error = Some(e.to_string());
-                    }
+        let mut conn = pool.get().await?;
+        let query = format!(r#"
+            SELECT TOP (@P1) CIFLookupNID, CIFLookupID, CAST(EndIncome AS float) as EndIncome, LastUpdate
+            FROM [CIFLookup]
+            WHERE CIFLookupNID > @P2{}
+            ORDER BY CIFLookupNID ASC
+        "#, scope.and_clause());
+
+        let mut rows = conn.query(query, &[&limit, &last_id]).await?;
+        let mut data = Vec::new();
+
+        while let Some(row_result) = rows.next().await {
+            match row_result? {
+                QueryItem::Row(row) => {
+                    // Kolom sensitif CIFLookup ikut aturan masking view
+                    let masked = masks.apply(json!({
+                        "CIFLookupID": row.get::<&str, _>("CIFLookupID"),
+                        "EndIncome": row.get::<f64, _>("EndIncome"),
+                    }));
+
+                    data.push(Order {
+                        id: row.get::<i32, _>("CIFLookupNID").unwrap_or_default(),
+                        customer_name: masked["CIFLookupID"].as_str().unwrap_or_default().to_string(),
+                        total_price: masked["EndIncome"].as_f64().unwrap_or(0.0),
+                        created_at: row
+                            .get::<chrono::NaiveDateTime, _>("LastUpdate")
+                            .unwrap_or_default(),
+                    });
                 }
-            }
-            Err(e) => {
-                result.message = "Failed to connect to DB".to_string();
-                result.error = Some(e.to_string());
+                _ => continue, // Misal QueryItem::Metadata atau lainnya
             }
         }
 
-        result
+        result.result = true;
+        result.message = "List orders".to_string();
+        result.data = Some(data);
+
+        Ok(result)
     }
 
-    pub async fn not_found(req: HttpRequest) -> impl Responder {
+    pub async fn not_found(req: HttpRequest) -> Result<HttpResponse, AppError> {
 
         if req.path() == "/docs" {
-            return HttpResponse::Found()
+            return Ok(HttpResponse::Found()
             .append_header(("Location", "/docs/index.html"))
-            .finish()
+            .finish())
         }
 
-        HttpResponse::NotFound().json({
-            json!({
-                "result": false,
-                "message": "Not Found",
-                "error": format!("Url '{}' not found. Please check the URL.", req.path())
-            })
-        })
+        Err(AppError::NotFound(format!("Url '{}' not found. Please check the URL.", req.path())))
     }
 
     pub fn json_error_handler(
         err: error::JsonPayloadError,
         _req: &actix_web::HttpRequest,
     ) -> actix_web::Error {
-        let error_message = format!("Json deserialize error: {}", err);
-
-        let result = ActionResult::<String, _> {
-            // <- Ubah dari ActionResult<()> ke ActionResult<String>
-            result: false,
-            message: "Invalid Request".to_string(),
-            error: Some(error_message), // <- Sekarang cocok karena `data: Option<String>`
-            data: None,
-        };
-
-        error::InternalError::from_response(err, HttpResponse::BadRequest().json(result)).into()
+        AppError::BadRequest(err.to_string()).into()
     }
 
     /// Helper untuk validasi path parameter yang harus berupa integer
-    pub fn parse_param<T: std::str::FromStr>(param: &str) -> Result<T, HttpResponse> {
+    pub fn parse_param<T: std::str::FromStr>(param: &str) -> Result<T, AppError> {
         param.parse::<T>().map_err(|_| {
-            HttpResponse::BadRequest().json(json!({
-                "result": false,
-                "message": "Bad Request",
-                "error": format!("Invalid parameter '{}'. Please provide a valid {}", param, std::any::type_name::<T>())
-            }))
+            AppError::BadRequest(format!("Invalid parameter '{}'. Please provide a valid {}", param, std::any::type_name::<T>()))
         })
     }
 }
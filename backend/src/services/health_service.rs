@@ -0,0 +1,49 @@
+use std::{env, time::{Duration, Instant}};
+
+use actix_web::web;
+
+use crate::contexts::{error::AppError, logger::write_log, connection::{ping_database, DbPool, POOL_MAX_SIZE}, model::{ActionResult, PoolStatus, ReadinessStatus}};
+
+pub struct HealthService;
+
+impl HealthService {
+    pub async fn check_readiness(connection: web::Data<DbPool>) -> ActionResult<ReadinessStatus, String> {
+        let mut result: ActionResult<ReadinessStatus, String> = ActionResult::default();
+
+        let timeout_ms: u64 = env::var("HEALTH_READY_TIMEOUT_MS").ok().and_then(|v| v.parse().ok()).unwrap_or(3000);
+        let started = Instant::now();
+        let ping = ping_database(&connection, Duration::from_millis(timeout_ms)).await;
+
+        let state = connection.state();
+        let mut status = ReadinessStatus {
+            database: "up".to_string(),
+            latency_ms: started.elapsed().as_millis(),
+            pool: PoolStatus {
+                connections: state.connections,
+                idle_connections: state.idle_connections,
+                max_size: POOL_MAX_SIZE,
+                get_waited: state.statistics.get_waited,
+                get_timed_out: state.statistics.get_timed_out,
+            },
+        };
+
+        match ping {
+            Ok(_) => {
+                result.result = true;
+                result.message = "Ready".to_string();
+            }
+            Err(e) => {
+                status.database = "down".to_string();
+                result.message = "Database unavailable".to_string();
+                // Detail error (nama server, dll) hanya ke log
+                write_log("ERROR", &format!("Readiness check failed: {}", e));
+                let error = AppError::Unavailable("Database is not reachable".to_string());
+                result.error = Some("Database is not reachable".to_string());
+                result.error_code = Some(error.code().to_string());
+            }
+        }
+
+        result.data = Some(status);
+        result
+    }
+}
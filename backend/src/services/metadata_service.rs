@@ -0,0 +1,204 @@
+use std::{collections::HashMap, time::Instant};
+
+use bb8::Pool;
+use serde_json::{json, Map, Value as JsonValue};
+use tiberius::Row;
+
+use crate::contexts::{cache::{cached, CacheKey}, config::APP_CONFIG, connection::{query_cancelled, query_timeout, ConnectionManager}, error::AppError, metrics::METRICS};
+
+/// Override kolom dari table konfigurasi (`metadata.column_config_table`)
+struct ColumnOverride {
+    title: Option<String>,
+    sortable: Option<bool>,
+    filter_control: Option<String>,
+    visible: Option<bool>,
+}
+
+pub struct MetadataService;
+
+impl MetadataService {
+    /// Definisi kolom bootstrap-table dari INFORMATION_SCHEMA + sys.columns.
+    /// Return list kosong jika view tidak ditemukan.
+    pub async fn get_columns(pool: &Pool<ConnectionManager>, tablename: &str) -> Result<Vec<JsonValue>, AppError> {
+        let sql = r#"
+            SELECT
+                CAST(c.COLUMN_NAME AS nvarchar(128)) AS ColumnName,
+                CAST(c.DATA_TYPE AS nvarchar(128)) AS DataType,
+                CAST(CASE WHEN c.IS_NULLABLE = 'YES' THEN 1 ELSE 0 END AS bit) AS IsNullable,
+                CAST(c.CHARACTER_MAXIMUM_LENGTH AS int) AS MaxLength,
+                CAST(c.NUMERIC_PRECISION AS int) AS NumericPrecision,
+                CAST(c.NUMERIC_SCALE AS int) AS NumericScale,
+                CAST(CASE WHEN pk.column_id IS NULL THEN 0 ELSE 1 END AS bit) AS IsPrimaryKey,
+                CAST(sc.is_identity AS bit) AS IsIdentity,
+                CAST(sc.is_computed AS bit) AS IsComputed,
+                CAST(CASE WHEN c.COLUMN_DEFAULT IS NULL THEN 0 ELSE 1 END AS bit) AS HasDefault
+            FROM INFORMATION_SCHEMA.COLUMNS c
+            JOIN sys.columns sc ON sc.object_id = OBJECT_ID(@P1) AND sc.name = c.COLUMN_NAME
+            LEFT JOIN (
+                SELECT ic.object_id, ic.column_id
+                FROM sys.index_columns ic
+                JOIN sys.indexes i ON i.object_id = ic.object_id AND i.index_id = ic.index_id AND i.is_primary_key = 1
+            ) pk ON pk.object_id = sc.object_id AND pk.column_id = sc.column_id
+            WHERE OBJECT_ID(QUOTENAME(c.TABLE_SCHEMA) + '.' + QUOTENAME(c.TABLE_NAME)) = OBJECT_ID(@P1)
+            ORDER BY c.ORDINAL_POSITION
+        "#;
+
+        let timeout = query_timeout("HEADER");
+        let started = Instant::now();
+        let mut conn = pool.get().await?;
+
+        conn.mark_in_flight();
+        let rows = tokio::time::timeout(timeout, async {
+            conn.query(sql, &[&tablename]).await?.into_first_result().await
+        }).await.map_err(|_| query_cancelled(timeout))??;
+        conn.mark_done();
+        drop(conn);
+
+        // View yang tidak ada tidak dicatat, supaya nama dari client tidak menambah label metric
+        if rows.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        METRICS.observe_query("get_columns", tablename, started.elapsed());
+
+        let overrides = Self::get_overrides(pool, tablename).await?;
+
+        Ok(rows.iter().map(|row| Self::column_definition(row, overrides.get(&Self::text(row, "ColumnName").to_lowercase()))).collect())
+    }
+
+    /// `get_columns` lewat query cache (key agregasi `header`)
+    pub async fn get_columns_cached(pool: &Pool<ConnectionManager>, tablename: &str) -> Result<JsonValue, AppError> {
+        cached(CacheKey::new(tablename, None, "header"), async {
+            Self::get_columns(pool, tablename).await.map(JsonValue::Array)
+        }).await
+    }
+
+    /// Baca override dari table konfigurasi, diabaikan jika table belum dibuat
+    async fn get_overrides(pool: &Pool<ConnectionManager>, tablename: &str) -> Result<HashMap<String, ColumnOverride>, AppError> {
+        let config_table = APP_CONFIG.metadata.column_config_table.trim();
+        if config_table.is_empty() {
+            return Ok(HashMap::new());
+        }
+
+        let sql = format!(
+            r#"
+            IF OBJECT_ID(@P2) IS NOT NULL
+                SELECT
+                    CAST(ColumnName AS nvarchar(128)) AS ColumnName,
+                    CAST(Title AS nvarchar(256)) AS Title,
+                    CAST(Sortable AS bit) AS Sortable,
+                    CAST(FilterControl AS nvarchar(32)) AS FilterControl,
+                    CAST(Visible AS bit) AS Visible
+                FROM {} WHERE ViewName = @P1
+            "#,
+            config_table
+        );
+
+        let timeout = query_timeout("HEADER");
+        let mut conn = pool.get().await?;
+
+        conn.mark_in_flight();
+        let rows = tokio::time::timeout(timeout, async {
+            conn.query(sql, &[&tablename, &config_table]).await?.into_first_result().await
+        }).await.map_err(|_| query_cancelled(timeout))??;
+        conn.mark_done();
+
+        Ok(rows.iter()
+            .map(|row| {
+                (Self::text(row, "ColumnName").to_lowercase(), ColumnOverride {
+                    title: row.get::<&str, _>("Title").map(|v| v.to_string()),
+                    sortable: row.get::<bool, _>("Sortable"),
+                    filter_control: row.get::<&str, _>("FilterControl").map(|v| v.to_string()),
+                    visible: row.get::<bool, _>("Visible"),
+                })
+            })
+            .collect())
+    }
+
+    fn column_definition(row: &Row, column_override: Option<&ColumnOverride>) -> JsonValue {
+        let field = Self::text(row, "ColumnName");
+        let data_type = Self::text(row, "DataType").to_lowercase();
+        let max_length = row.get::<i32, _>("MaxLength");
+
+        let mut column = Map::new();
+        column.insert("field".to_string(), json!(field));
+        column.insert("title".to_string(), json!(Self::title_from_name(field)));
+        column.insert("sortable".to_string(), json!(Self::is_sortable(&data_type, max_length)));
+        column.insert("filterControl".to_string(), json!(Self::filter_control(&data_type)));
+        column.insert("dataType".to_string(), json!(data_type));
+        column.insert("nullable".to_string(), json!(row.get::<bool, _>("IsNullable").unwrap_or(true)));
+        column.insert("primaryKey".to_string(), json!(row.get::<bool, _>("IsPrimaryKey").unwrap_or(false)));
+        column.insert("identity".to_string(), json!(row.get::<bool, _>("IsIdentity").unwrap_or(false)));
+        column.insert("computed".to_string(), json!(row.get::<bool, _>("IsComputed").unwrap_or(false)));
+        column.insert("hasDefault".to_string(), json!(row.get::<bool, _>("HasDefault").unwrap_or(false)));
+        column.insert("maxLength".to_string(), json!(max_length));
+        column.insert("precision".to_string(), json!(row.get::<i32, _>("NumericPrecision")));
+        column.insert("scale".to_string(), json!(row.get::<i32, _>("NumericScale")));
+
+        if let Some(column_override) = column_override {
+            if let Some(title) = &column_override.title {
+                column.insert("title".to_string(), json!(title));
+            }
+            if let Some(sortable) = column_override.sortable {
+                column.insert("sortable".to_string(), json!(sortable));
+            }
+            if let Some(filter_control) = &column_override.filter_control {
+                column.insert("filterControl".to_string(), json!(filter_control));
+            }
+            if let Some(visible) = column_override.visible {
+                column.insert("visible".to_string(), json!(visible));
+            }
+        }
+
+        JsonValue::Object(column)
+    }
+
+    fn text<'a>(row: &'a Row, column: &str) -> &'a str {
+        row.get::<&str, _>(column).unwrap_or_default()
+    }
+
+    /// `CIFLookup` -> `CIF Lookup`, `LastUpdate` -> `Last Update`, `trade_date` -> `Trade Date`
+    fn title_from_name(name: &str) -> String {
+        let chars: Vec<char> = name.chars().collect();
+        let mut title = String::with_capacity(name.len() + 4);
+
+        for (i, &c) in chars.iter().enumerate() {
+            if c == '_' || c == '-' {
+                if !title.ends_with(' ') && !title.is_empty() {
+                    title.push(' ');
+                }
+                continue;
+            }
+
+            if i > 0 && c.is_uppercase() && !title.ends_with(' ') {
+                let prev = chars[i - 1];
+                let next_is_lower = chars.get(i + 1).is_some_and(|n| n.is_lowercase());
+                if prev.is_lowercase() || prev.is_ascii_digit() || (prev.is_uppercase() && next_is_lower) {
+                    title.push(' ');
+                }
+            }
+
+            if title.is_empty() || title.ends_with(' ') {
+                title.extend(c.to_uppercase());
+            } else {
+                title.push(c);
+            }
+        }
+
+        title.trim_end().to_string()
+    }
+
+    /// Kolom LOB tidak bisa dipakai di ORDER BY
+    fn is_sortable(data_type: &str, max_length: Option<i32>) -> bool {
+        !matches!(data_type, "text" | "ntext" | "image" | "xml" | "geography" | "geometry" | "hierarchyid" | "sql_variant")
+            && max_length != Some(-1)
+    }
+
+    fn filter_control(data_type: &str) -> &'static str {
+        match data_type {
+            "date" | "datetime" | "datetime2" | "smalldatetime" | "datetimeoffset" => "datepicker",
+            "bit" => "select",
+            _ => "input",
+        }
+    }
+}
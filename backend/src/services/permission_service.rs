@@ -0,0 +1,97 @@
+use std::{collections::{HashMap, HashSet}, env, sync::{Arc, Mutex}, time::{Duration, Instant}};
+
+use bb8::Pool;
+use once_cell::sync::Lazy;
+
+use crate::contexts::{connection::{query_cancelled, query_timeout, ConnectionManager}, error::AppError};
+
+/// Role dan permission milik satu user (permission disimpan lowercase)
+#[derive(Debug, Default)]
+pub struct UserAccess {
+    pub roles: Vec<String>,
+    pub permissions: HashSet<String>,
+}
+
+impl UserAccess {
+    /// `admin` memberi semua akses, `view:*` memberi akses ke semua `view:{tablename}`
+    pub fn has(&self, permission: &str) -> bool {
+        if self.permissions.contains("admin") {
+            return true;
+        }
+
+        let permission = permission.to_lowercase();
+        self.permissions.contains(&permission)
+            || permission.split_once(':').is_some_and(|(scope, _)| self.permissions.contains(&format!("{}:*", scope)))
+    }
+}
+
+/// (waktu load, akses) per `auth_usernid`
+type AccessCache = HashMap<i32, (Instant, Arc<UserAccess>)>;
+
+/// Cache permission per user supaya guard tidak query ke database di setiap request
+static ACCESS_CACHE: Lazy<Mutex<AccessCache>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+pub struct PermissionService;
+
+impl PermissionService {
+    pub async fn access_for(connection: &Pool<ConnectionManager>, user_nid: i32) -> Result<Arc<UserAccess>, AppError> {
+        let ttl = Duration::from_secs(env::var("PERMISSION_CACHE_TTL_SECS").ok().and_then(|v| v.parse().ok()).unwrap_or(300));
+
+        if let Some((loaded_at, access)) = ACCESS_CACHE.lock().unwrap().get(&user_nid) {
+            if loaded_at.elapsed() < ttl {
+                return Ok(access.clone());
+            }
+        }
+
+        let access = Arc::new(Self::load(connection, user_nid).await?);
+        ACCESS_CACHE.lock().unwrap().insert(user_nid, (Instant::now(), access.clone()));
+
+        Ok(access)
+    }
+
+    /// Hapus cache satu user, atau semua user jika `None`. Return jumlah entry yang dihapus.
+    pub fn invalidate(user_nid: Option<i32>) -> usize {
+        let mut cache = ACCESS_CACHE.lock().unwrap();
+        match user_nid {
+            Some(user_nid) => cache.remove(&user_nid).map_or(0, |_| 1),
+            None => {
+                let removed = cache.len();
+                cache.clear();
+                removed
+            }
+        }
+    }
+
+    async fn load(connection: &Pool<ConnectionManager>, user_nid: i32) -> Result<UserAccess, AppError> {
+        let query = r#"
+            SELECT r.RoleName, rp.Permission
+            FROM [dbo].[UserRole] ur
+            JOIN [dbo].[Role] r ON r.RoleNID = ur.RoleNID
+            LEFT JOIN [dbo].[RolePermission] rp ON rp.RoleNID = r.RoleNID
+            WHERE ur.AuthUserNID = @P1
+        "#;
+
+        let timeout = query_timeout("PERMISSION");
+        let mut conn = connection.get().await?;
+
+        conn.mark_in_flight();
+        let rows = tokio::time::timeout(timeout, async {
+            conn.query(query, &[&user_nid]).await?.into_first_result().await
+        }).await.map_err(|_| query_cancelled(timeout))??;
+        conn.mark_done();
+
+        let mut access = UserAccess::default();
+        for row in &rows {
+            if let Some(role) = row.get::<&str, _>("RoleName") {
+                if !access.roles.iter().any(|r| r == role) {
+                    access.roles.push(role.to_string());
+                }
+            }
+            if let Some(permission) = row.get::<&str, _>("Permission") {
+                access.permissions.insert(permission.trim().to_lowercase());
+            }
+        }
+
+        Ok(access)
+    }
+}
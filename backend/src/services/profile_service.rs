@@ -0,0 +1,240 @@
+use std::{env, fs, io::Cursor, path::PathBuf};
+
+use base64::{engine::general_purpose, Engine as _};
+use bb8::Pool;
+use image::{codecs::jpeg::JpegEncoder, imageops::FilterType, DynamicImage, ImageDecoder, ImageReader, Limits, Rgb, RgbImage};
+use rand::Rng;
+use serde_json::json;
+use tiberius::Row;
+
+use crate::contexts::{auth::AuditActor, connection::ConnectionManager, error::AppError, logger::write_log, model::{ActionResult, AvatarUrls, Claims, ProfileInfo, UpdateProfileRequest}};
+use super::{audit_service::{AuditEntry, AuditService}, credential_service::CredentialService};
+
+/// Path URL tempat file avatar di-serve (lihat `avatar_scope`)
+pub const AVATAR_URL_PATH: &str = "/avatars";
+/// Ukuran thumbnail persegi yang dibuat dari setiap upload: small, medium, large
+const AVATAR_SIZES: [u32; 3] = [64, 128, 256];
+/// Batas dimensi gambar sumber supaya upload kecil tidak bisa memakan memori besar saat di-decode
+const MAX_SOURCE_DIMENSION: u32 = 4096;
+/// Batas memori decoder (cukup untuk 4096x4096 RGB 16-bit)
+const MAX_DECODE_ALLOC: u64 = 128 * 1024 * 1024;
+const JPEG_QUALITY: u8 = 85;
+
+const PROFILE_COLUMNS: &str = "AuthUserNID, Email, FullName, MobilePhone, EmailVerified, Picture, CompName, RegisterDate";
+
+pub struct ProfileService;
+
+impl ProfileService {
+    /// Folder file avatar (`AVATAR_DIR`, default `uploads/avatars`)
+    pub fn avatar_dir() -> PathBuf {
+        PathBuf::from(env::var("AVATAR_DIR").unwrap_or_else(|_| "uploads/avatars".to_string()))
+    }
+
+    fn avatar_file_name(stem: &str, size: u32) -> String {
+        format!("{}-{}.jpg", stem, size)
+    }
+
+    /// Nama dasar file dari kolom `Picture`, `None` untuk gambar yang bukan hasil upload avatar (mis. URL eksternal)
+    fn avatar_stem(picture: &str) -> Option<&str> {
+        let large = AVATAR_SIZES[AVATAR_SIZES.len() - 1];
+        picture.strip_prefix(AVATAR_URL_PATH)?
+            .strip_prefix('/')?
+            .strip_suffix(&format!("-{}.jpg", large))
+            .filter(|stem| !stem.is_empty() && stem.chars().all(|c| c.is_ascii_alphanumeric() || c == '-'))
+    }
+
+    fn avatar_urls(picture: &str) -> Option<AvatarUrls> {
+        let stem = Self::avatar_stem(picture)?;
+        let [small, medium, large] = AVATAR_SIZES.map(|size| format!("{}/{}", AVATAR_URL_PATH, Self::avatar_file_name(stem, size)));
+
+        Some(AvatarUrls { small, medium, large })
+    }
+
+    fn profile_from_row(row: &Row) -> ProfileInfo {
+        let picture = row.get::<&str, _>("Picture").map(str::to_string);
+
+        ProfileInfo {
+            auth_usernid: row.get("AuthUserNID").unwrap_or_default(),
+            email: row.get::<&str, _>("Email").unwrap_or_default().to_string(),
+            full_name: row.get::<&str, _>("FullName").map(str::to_string),
+            mobile_phone: row.get::<&str, _>("MobilePhone").map(str::to_string),
+            email_verified: row.get("EmailVerified").unwrap_or(false),
+            avatar: picture.as_deref().and_then(Self::avatar_urls),
+            picture,
+            comp_name: row.get::<&str, _>("CompName").map(str::to_string),
+            register_date: row.get("RegisterDate"),
+        }
+    }
+
+    pub async fn get(connection: &Pool<ConnectionManager>, user_nid: i32) -> Result<ActionResult<ProfileInfo, String>, AppError> {
+        let mut conn = connection.get().await?;
+        let query = format!("SELECT {} FROM [dbo].[AuthUser] WHERE AuthUserNID = @P1", PROFILE_COLUMNS);
+        let row = conn.query(query, &[&user_nid]).await?.into_row().await?
+            .ok_or_else(|| AppError::NotFound("User not found".to_string()))?;
+
+        Ok(ActionResult {
+            result: true,
+            message: "Profile retrieved successfully".to_string(),
+            data: Some(Self::profile_from_row(&row)),
+            ..Default::default()
+        })
+    }
+
+    /// Decode gambar (orientasi EXIF diterapkan), crop tengah persegi lalu resize ke setiap `AVATAR_SIZES` sebagai JPEG.
+    /// Bagian transparan diberi latar putih.
+    fn render_thumbnails(data: &str) -> Result<Vec<(u32, Vec<u8>)>, AppError> {
+        let invalid = |e: image::ImageError| AppError::BadRequest(format!("Invalid avatar image: {}", e));
+        let bytes = general_purpose::STANDARD.decode(data.split(',').next_back().unwrap_or_default().trim())
+            .map_err(|_| AppError::BadRequest("Invalid avatar image: base64 tidak valid".to_string()))?;
+
+        let mut limits = Limits::default();
+        limits.max_image_width = Some(MAX_SOURCE_DIMENSION);
+        limits.max_image_height = Some(MAX_SOURCE_DIMENSION);
+        limits.max_alloc = Some(MAX_DECODE_ALLOC);
+
+        let mut reader = ImageReader::new(Cursor::new(bytes)).with_guessed_format()
+            .map_err(|e| AppError::BadRequest(format!("Invalid avatar image: {}", e)))?;
+        reader.limits(limits);
+
+        let mut decoder = reader.into_decoder().map_err(invalid)?;
+        let orientation = decoder.orientation().map_err(invalid)?;
+        let mut source = DynamicImage::from_decoder(decoder).map_err(invalid)?;
+        source.apply_orientation(orientation);
+
+        AVATAR_SIZES.iter().map(|&size| {
+            let rgba = source.resize_to_fill(size, size, FilterType::Lanczos3).to_rgba8();
+            let flattened = RgbImage::from_fn(size, size, |x, y| {
+                let [r, g, b, a] = rgba.get_pixel(x, y).0;
+                let blend = |c: u8| ((c as u16 * a as u16 + 255 * (255 - a as u16)) / 255) as u8;
+                Rgb([blend(r), blend(g), blend(b)])
+            });
+
+            let mut jpeg = Vec::new();
+            JpegEncoder::new_with_quality(&mut jpeg, JPEG_QUALITY).encode_image(&flattened)
+                .map_err(|e| AppError::Internal(format!("Failed to encode avatar: {}", e)))?;
+
+            Ok((size, jpeg))
+        }).collect()
+    }
+
+    /// Proses dan simpan avatar baru, hasilnya URL thumbnail terbesar untuk kolom `Picture`.
+    /// Nama file acak supaya URL lama tidak tertahan di cache browser.
+    async fn save_avatar(user_nid: i32, data: String) -> Result<String, AppError> {
+        let stem = format!("{}-{:016x}", user_nid, rand::rng().random::<u64>());
+        let dir = Self::avatar_dir();
+
+        let file_stem = stem.clone();
+        tokio::task::spawn_blocking(move || {
+            let thumbnails = Self::render_thumbnails(&data)?;
+
+            fs::create_dir_all(&dir)
+                .map_err(|e| AppError::Internal(format!("Failed to create avatar directory {}: {}", dir.display(), e)))?;
+            for (size, jpeg) in thumbnails {
+                let path = dir.join(Self::avatar_file_name(&file_stem, size));
+                fs::write(&path, jpeg)
+                    .map_err(|e| AppError::Internal(format!("Failed to write avatar {}: {}", path.display(), e)))?;
+            }
+
+            Ok::<_, AppError>(())
+        })
+        .await
+        .map_err(|e| AppError::Internal(format!("Avatar processing failed: {}", e)))??;
+
+        let large = AVATAR_SIZES[AVATAR_SIZES.len() - 1];
+        Ok(format!("{}/{}", AVATAR_URL_PATH, Self::avatar_file_name(&stem, large)))
+    }
+
+    /// Hapus semua thumbnail avatar lama, kegagalan hanya ditulis ke log
+    fn remove_avatar_files(picture: &str) {
+        let Some(stem) = Self::avatar_stem(picture) else {
+            return;
+        };
+
+        let dir = Self::avatar_dir();
+        for size in AVATAR_SIZES {
+            let path = dir.join(Self::avatar_file_name(stem, size));
+            if let Err(e) = fs::remove_file(&path) {
+                if e.kind() != std::io::ErrorKind::NotFound {
+                    write_log("WARN", &format!("Failed to remove avatar {}: {}", path.display(), e));
+                }
+            }
+        }
+    }
+
+    /// Ubah nama, nomor HP dan / atau avatar user login. File avatar lama dihapus setelah update berhasil.
+    pub async fn update(connection: &Pool<ConnectionManager>, user_nid: i32, request: UpdateProfileRequest, actor: &AuditActor) -> Result<ActionResult<ProfileInfo, String>, AppError> {
+        let before = Self::get(connection, user_nid).await?.data
+            .ok_or_else(|| AppError::NotFound("User not found".to_string()))?;
+
+        let new_picture = match request.avatar {
+            Some(avatar) => Some(Self::save_avatar(user_nid, avatar).await?),
+            None => None,
+        };
+        let picture_changed = new_picture.is_some() || request.remove_avatar;
+        let full_name = request.full_name.map(|name| name.trim().to_string());
+
+        let updated = async {
+            let mut conn = connection.get().await?;
+            let query = format!(
+                r#"UPDATE [dbo].[AuthUser] SET
+                    FullName = COALESCE(@P2, FullName),
+                    MobilePhone = COALESCE(@P3, MobilePhone),
+                    Picture = CASE WHEN @P5 = 1 THEN @P4 ELSE Picture END,
+                    LastUpdate = GETDATE()
+                OUTPUT {}
+                WHERE AuthUserNID = @P1"#,
+                PROFILE_COLUMNS.split(", ").map(|column| format!("inserted.{}", column)).collect::<Vec<_>>().join(", ")
+            );
+            let row = conn.query(query, &[&user_nid, &full_name, &request.mobile_phone, &new_picture, &picture_changed])
+                .await?.into_row().await?
+                .ok_or_else(|| AppError::NotFound("User not found".to_string()))?;
+            let profile = Self::profile_from_row(&row);
+
+            AuditService::write(&mut conn, actor, AuditEntry {
+                action: "profile_update",
+                entity: "AuthUser",
+                entity_key: Some(user_nid.to_string()),
+                before: Some(json!({ "full_name": before.full_name, "mobile_phone": before.mobile_phone, "picture": before.picture })),
+                after: Some(json!({ "full_name": profile.full_name, "mobile_phone": profile.mobile_phone, "picture": profile.picture })),
+            }).await?;
+
+            Ok::<_, AppError>(profile)
+        }.await;
+
+        let profile = match updated {
+            Ok(profile) => profile,
+            Err(e) => {
+                if let Some(picture) = &new_picture {
+                    Self::remove_avatar_files(picture);
+                }
+                return Err(e);
+            }
+        };
+
+        if picture_changed {
+            if let Some(old_picture) = &before.picture {
+                Self::remove_avatar_files(old_picture);
+            }
+        }
+
+        Ok(ActionResult {
+            result: true,
+            message: "Profile updated successfully".to_string(),
+            data: Some(profile),
+            ..Default::default()
+        })
+    }
+
+    /// Claims terbaru dari database untuk access token pengganti. Sesi dan masa berlaku token lama dipertahankan.
+    pub async fn refreshed_claims(connection: &Pool<ConnectionManager>, claims: &Claims) -> Result<Claims, AppError> {
+        let mut conn = connection.get().await?;
+        let mut refreshed = CredentialService::load_claims(&mut conn, claims.auth_usernid, claims.ip_address.clone()).await?;
+
+        refreshed.sid = claims.sid.clone();
+        refreshed.exp = claims.exp;
+        refreshed.expired_token = claims.expired_token;
+        refreshed.expired_date = claims.expired_date.clone();
+
+        Ok(refreshed)
+    }
+}
@@ -0,0 +1,579 @@
+use std::{borrow::Cow, fmt::Write, time::Instant};
+
+use actix_web::web;
+use base64::{engine::general_purpose, Engine as _};
+use bb8::Pool;
+use chrono::{DateTime, NaiveDate, NaiveDateTime, NaiveTime};
+use serde_json::{Map, Value as JsonValue};
+use tiberius::ToSql;
+use validator::{ValidationError, ValidationErrors, ValidationErrorsKind};
+
+use crate::contexts::{auth::AuditActor, cache::QUERY_CACHE, config::APP_CONFIG, connection::{query_cancelled, query_timeout, ConnectionManager, Transaction}, error::AppError, metrics::METRICS, model::{ActionResult, RowRequest}, row_scope::RowScope};
+use super::{audit_service::{AuditEntry, AuditService}, data_service::DataService, metadata_service::MetadataService};
+
+type SqlParam = Box<dyn ToSql>;
+
+/// Metadata satu kolom yang dibutuhkan untuk validasi payload
+struct ColumnMeta {
+    name: String,
+    data_type: String,
+    nullable: bool,
+    max_length: Option<i64>,
+    precision: Option<i64>,
+    scale: Option<i64>,
+    primary_key: bool,
+    identity: bool,
+    computed: bool,
+    has_default: bool,
+}
+
+impl ColumnMeta {
+    fn from_json(column: &JsonValue) -> Self {
+        let text = |key: &str| column.get(key).and_then(|v| v.as_str()).unwrap_or_default().to_string();
+        let flag = |key: &str| column.get(key).and_then(|v| v.as_bool()).unwrap_or(false);
+        let number = |key: &str| column.get(key).and_then(|v| v.as_i64());
+
+        Self {
+            name: text("field"),
+            data_type: text("dataType").to_lowercase(),
+            nullable: column.get("nullable").and_then(|v| v.as_bool()).unwrap_or(true),
+            max_length: number("maxLength"),
+            precision: number("precision"),
+            scale: number("scale"),
+            primary_key: flag("primaryKey"),
+            identity: flag("identity"),
+            computed: flag("computed"),
+            has_default: flag("hasDefault"),
+        }
+    }
+
+    fn is_rowversion(&self) -> bool {
+        matches!(self.data_type.as_str(), "timestamp" | "rowversion")
+    }
+
+    fn is_writable(&self) -> bool {
+        !(self.identity || self.computed || self.is_rowversion())
+    }
+
+    /// Tipe kolom untuk table variable penampung `OUTPUT ... INTO`
+    fn sql_type(&self) -> String {
+        let length = || match self.max_length {
+            Some(length) if length > 0 => length.to_string(),
+            _ => "max".to_string(),
+        };
+
+        match self.data_type.as_str() {
+            "char" | "varchar" | "nchar" | "nvarchar" | "binary" | "varbinary" => format!("{}({})", self.data_type, length()),
+            "decimal" | "numeric" => format!("{}({}, {})", self.data_type, self.precision.unwrap_or(18), self.scale.unwrap_or(0)),
+            "timestamp" | "rowversion" => "binary(8)".to_string(),
+            "text" => "varchar(max)".to_string(),
+            "ntext" => "nvarchar(max)".to_string(),
+            "image" => "varbinary(max)".to_string(),
+            other => other.to_string(),
+        }
+    }
+}
+
+/// View yang boleh diubah beserta kolom kunci dan kolom versinya
+struct EditableView {
+    tablename: String,
+    columns: Vec<ColumnMeta>,
+    primary_key: usize,
+    version: Option<usize>,
+    /// Kondisi row-level security user, ditambahkan ke setiap WHERE
+    row_scope: String,
+}
+
+impl EditableView {
+    fn column(&self, name: &str) -> Option<(usize, &ColumnMeta)> {
+        self.columns.iter().enumerate().find(|(_, c)| c.name.eq_ignore_ascii_case(name))
+    }
+
+    fn quoted_table(&self) -> String {
+        quote(&self.tablename)
+    }
+
+    fn key_column(&self) -> &ColumnMeta {
+        &self.columns[self.primary_key]
+    }
+
+    fn version_column(&self) -> Option<&ColumnMeta> {
+        self.version.map(|i| &self.columns[i])
+    }
+
+    /// `OUTPUT` ke table variable `@output` (lihat `execute_write`). `OUTPUT` tanpa `INTO` ditolak
+    /// SQL Server untuk table yang punya trigger.
+    fn output_into(&self, source: &str) -> String {
+        let columns: Vec<String> = self.columns.iter().map(|c| format!("{}.{}", source, quote(&c.name))).collect();
+        format!("OUTPUT {} INTO @output", columns.join(", "))
+    }
+
+    fn declare_output(&self) -> String {
+        let columns: Vec<String> = self.columns.iter().map(|c| format!("{} {} NULL", quote(&c.name), c.sql_type())).collect();
+        format!("DECLARE @output TABLE ({});", columns.join(", "))
+    }
+}
+
+/// Quote nama table / kolom: `dbo.CIFLookup` -> `[dbo].[CIFLookup]`
+fn quote(name: &str) -> String {
+    name.split('.')
+        .map(|part| format!("[{}]", part.trim_matches(|c| c == '[' || c == ']').replace(']', "]]")))
+        .collect::<Vec<_>>()
+        .join(".")
+}
+
+fn validation_error(code: &'static str, message: String) -> ValidationError {
+    let mut error = ValidationError::new(code);
+    error.message = Some(Cow::Owned(message));
+    error
+}
+
+fn add_error(errors: &mut ValidationErrors, field: &str, error: ValidationError) {
+    errors.0.insert(Cow::Owned(field.to_string()), ValidationErrorsKind::Field(vec![error]));
+}
+
+pub struct RowService;
+
+impl RowService {
+    pub async fn insert_row(connection: web::Data<Pool<ConnectionManager>>, mut request: RowRequest, actor: &AuditActor, scope: &RowScope) -> Result<ActionResult<JsonValue, String>, AppError> {
+        let view = Self::load_view(&connection, request.tablename.as_deref().unwrap_or_default(), scope).await?;
+        Self::apply_scope(scope, &mut request.values, true)?;
+        let values = Self::validate_values(&view, &request.values, true)?;
+
+        let mut columns: Vec<String> = values.iter().map(|(i, _)| quote(&view.columns[*i].name)).collect();
+        let mut placeholders: Vec<String> = (1..=values.len()).map(|i| format!("@P{}", i)).collect();
+
+        // Kolom versi datetime diisi server
+        if let Some(version) = view.version_column().filter(|c| !c.is_rowversion()) {
+            columns.push(quote(&version.name));
+            placeholders.push("GETDATE()".to_string());
+        }
+
+        let query = if columns.is_empty() {
+            format!("INSERT INTO {} {} DEFAULT VALUES", view.quoted_table(), view.output_into("INSERTED"))
+        } else {
+            format!(
+                "INSERT INTO {} ({}) {} VALUES ({})",
+                view.quoted_table(), columns.join(", "), view.output_into("INSERTED"), placeholders.join(", ")
+            )
+        };
+
+        let params: Vec<SqlParam> = values.into_iter().map(|(_, p)| p).collect();
+        let row = Self::execute_write(&connection, &view, actor, "insert", query, params, None).await?;
+
+        Ok(ActionResult {
+            result: true,
+            message: "Row inserted successfully".to_string(),
+            data: Some(row),
+            ..Default::default()
+        })
+    }
+
+    pub async fn update_row(connection: web::Data<Pool<ConnectionManager>>, mut request: RowRequest, actor: &AuditActor, scope: &RowScope) -> Result<ActionResult<JsonValue, String>, AppError> {
+        let view = Self::load_view(&connection, request.tablename.as_deref().unwrap_or_default(), scope).await?;
+        Self::apply_scope(scope, &mut request.values, false)?;
+        let values = Self::validate_values(&view, &request.values, false)?;
+        let (key, version) = Self::validate_key(&view, &request)?;
+
+        if values.is_empty() {
+            return Err(AppError::BadRequest("No values to update".to_string()));
+        }
+
+        let mut assignments: Vec<String> = values.iter()
+            .enumerate()
+            .map(|(n, (i, _))| format!("{} = @P{}", quote(&view.columns[*i].name), n + 1))
+            .collect();
+
+        if let Some(version) = view.version_column().filter(|c| !c.is_rowversion()) {
+            assignments.push(format!("{} = GETDATE()", quote(&version.name)));
+        }
+
+        let mut params: Vec<SqlParam> = values.into_iter().map(|(_, p)| p).collect();
+        let mut query = format!("UPDATE {} SET {} {}", view.quoted_table(), assignments.join(", "), view.output_into("INSERTED"));
+        let key_index = Self::push_where(&view, &mut query, &mut params, key, version);
+
+        let row = Self::execute_write(&connection, &view, actor, "update", query, params, request.key.as_ref().map(|k| (k, key_index))).await?;
+
+        Ok(ActionResult {
+            result: true,
+            message: "Row updated successfully".to_string(),
+            data: Some(row),
+            ..Default::default()
+        })
+    }
+
+    pub async fn delete_row(connection: web::Data<Pool<ConnectionManager>>, request: RowRequest, actor: &AuditActor, scope: &RowScope) -> Result<ActionResult<JsonValue, String>, AppError> {
+        let view = Self::load_view(&connection, request.tablename.as_deref().unwrap_or_default(), scope).await?;
+        let (key, version) = Self::validate_key(&view, &request)?;
+
+        let mut params: Vec<SqlParam> = Vec::new();
+        let mut query = format!("DELETE FROM {} {}", view.quoted_table(), view.output_into("DELETED"));
+        let key_index = Self::push_where(&view, &mut query, &mut params, key, version);
+
+        let row = Self::execute_write(&connection, &view, actor, "delete", query, params, request.key.as_ref().map(|k| (k, key_index))).await?;
+
+        Ok(ActionResult {
+            result: true,
+            message: "Row deleted successfully".to_string(),
+            data: Some(row),
+            ..Default::default()
+        })
+    }
+
+    /// Ambil metadata view, hanya untuk view yang `editable` di config
+    async fn load_view(connection: &Pool<ConnectionManager>, tablename: &str, scope: &RowScope) -> Result<EditableView, AppError> {
+        let config = APP_CONFIG.view(tablename);
+        if !config.editable {
+            return Err(AppError::Forbidden(format!("View '{}' is not editable", tablename)));
+        }
+
+        let columns: Vec<ColumnMeta> = match MetadataService::get_columns_cached(connection, tablename).await? {
+            JsonValue::Array(columns) => columns.iter().map(ColumnMeta::from_json).collect(),
+            _ => Vec::new(),
+        };
+        if columns.is_empty() {
+            return Err(AppError::NotFound(format!("View '{}' not found", tablename)));
+        }
+
+        let find = |name: &str| columns.iter().position(|c| c.name.eq_ignore_ascii_case(name));
+
+        let primary_key = match &config.primary_key {
+            Some(name) => find(name),
+            None => columns.iter().position(|c| c.primary_key)
+                .or_else(|| columns.iter().position(|c| c.identity))
+                .or_else(|| find("AutoNID")),
+        }.ok_or_else(|| AppError::Internal(format!("No primary key found for editable view '{}'", tablename)))?;
+
+        let version = find(config.version_column.as_deref().unwrap_or("LastUpdate"));
+
+        // Versi dikirim balik oleh client apa adanya, jadi hanya tipe yang bisa dibaca `row_to_json` yang didukung
+        if let Some(column) = version.map(|i| &columns[i]) {
+            if !matches!(column.data_type.as_str(), "datetime" | "datetime2" | "smalldatetime" | "timestamp" | "rowversion") {
+                return Err(AppError::Internal(format!(
+                    "Version column '{}' of '{}' must be datetime, datetime2, smalldatetime or rowversion", column.name, tablename
+                )));
+            }
+        }
+
+        Ok(EditableView {
+            tablename: tablename.to_string(),
+            columns,
+            primary_key,
+            version,
+            row_scope: scope.and_clause(),
+        })
+    }
+
+    /// Nilai kolom row filter di payload harus di dalam scope user. Saat insert, kolom yang
+    /// tidak dikirim diisi otomatis jika user hanya punya satu nilai untuk kolom tersebut.
+    fn apply_scope(scope: &RowScope, values: &mut Map<String, JsonValue>, insert: bool) -> Result<(), AppError> {
+        for (column, allowed) in scope.conditions() {
+            match values.iter().find(|(k, _)| k.eq_ignore_ascii_case(column)) {
+                Some((field, value)) if !scope.allows(column, value) => {
+                    return Err(AppError::Forbidden(format!("Value of '{}' is outside your data scope", field)));
+                }
+                None if insert => match allowed.as_slice() {
+                    [value] => {
+                        values.insert(column.clone(), JsonValue::String(value.clone()));
+                    }
+                    _ => return Err(AppError::Forbidden(format!("'{}' must be set to a value within your data scope", column))),
+                },
+                _ => {}
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Validasi payload terhadap metadata kolom, hasilnya pasangan (index kolom, parameter SQL)
+    fn validate_values(view: &EditableView, values: &Map<String, JsonValue>, insert: bool) -> Result<Vec<(usize, SqlParam)>, AppError> {
+        let mut errors = ValidationErrors::new();
+        let mut params = Vec::new();
+
+        for (field, value) in values {
+            let Some((index, column)) = view.column(field) else {
+                add_error(&mut errors, field, validation_error("unknown_column", "Unknown column".to_string()));
+                continue;
+            };
+
+            if !column.is_writable() || Some(index) == view.version || (!insert && index == view.primary_key) {
+                add_error(&mut errors, field, validation_error("read_only", "This column is read-only".to_string()));
+                continue;
+            }
+
+            match Self::to_sql_param(column, value) {
+                Ok(param) => params.push((index, param)),
+                Err(error) => add_error(&mut errors, field, error),
+            }
+        }
+
+        if insert {
+            for (index, column) in view.columns.iter().enumerate() {
+                let missing = !values.keys().any(|k| k.eq_ignore_ascii_case(&column.name));
+                if missing && column.is_writable() && !column.nullable && !column.has_default && Some(index) != view.version {
+                    add_error(&mut errors, &column.name, ValidationError::new("required"));
+                }
+            }
+        }
+
+        if !errors.0.is_empty() {
+            return Err(errors.into());
+        }
+
+        Ok(params)
+    }
+
+    /// Parameter primary key dan versi untuk update / delete
+    fn validate_key(view: &EditableView, request: &RowRequest) -> Result<(SqlParam, Option<SqlParam>), AppError> {
+        let mut errors = ValidationErrors::new();
+
+        let key = match request.key.as_ref().filter(|k| !k.is_null()) {
+            Some(key) => Self::to_sql_param(view.key_column(), key).map_err(|e| add_error(&mut errors, "key", e)).ok(),
+            None => {
+                add_error(&mut errors, "key", ValidationError::new("required"));
+                None
+            }
+        };
+
+        let version = match (view.version_column(), &request.version) {
+            (None, _) => None,
+            (Some(_), None) => {
+                add_error(&mut errors, "version", ValidationError::new("required"));
+                None
+            }
+            (Some(_), Some(JsonValue::Null)) => None,
+            (Some(column), Some(version)) => Self::version_param(column, version).map_err(|e| add_error(&mut errors, "version", e)).ok(),
+        };
+
+        match key {
+            Some(key) if errors.0.is_empty() => Ok((key, version)),
+            _ => Err(errors.into()),
+        }
+    }
+
+    /// Tambah `WHERE` primary key + row scope + cek versi (optimistic concurrency), return index parameter key
+    fn push_where(view: &EditableView, query: &mut String, params: &mut Vec<SqlParam>, key: SqlParam, version: Option<SqlParam>) -> usize {
+        params.push(key);
+        let key_index = params.len() - 1;
+        let _ = write!(query, " WHERE {} = @P{}{}", quote(&view.key_column().name), params.len(), view.row_scope);
+
+        if let Some(column) = view.version_column() {
+            let name = quote(&column.name);
+            match version {
+                None => {
+                    let _ = write!(query, " AND {} IS NULL", name);
+                }
+                Some(version) if column.is_rowversion() => {
+                    params.push(version);
+                    let _ = write!(query, " AND {} = @P{}", name, params.len());
+                }
+                Some(version) => {
+                    // Dibandingkan sampai milidetik supaya presisi datetime tidak membuat mismatch
+                    params.push(version);
+                    let _ = write!(query, " AND CAST({0} AS datetime2(3)) = CAST(@P{1} AS datetime2(3))", name, params.len());
+                }
+            }
+        }
+
+        key_index
+    }
+
+    /// Jalankan query tulis + audit log dalam satu transaksi. Jika tidak ada baris yang kena,
+    /// bedakan antara baris tidak ditemukan / di luar scope user (404) dan versi yang sudah berubah (409).
+    async fn execute_write(connection: &Pool<ConnectionManager>, view: &EditableView, actor: &AuditActor, action: &str, query: String, params: Vec<SqlParam>, key: Option<(&JsonValue, usize)>) -> Result<JsonValue, AppError> {
+        let timeout = query_timeout("ROW_WRITE");
+        let started = Instant::now();
+
+        let row = tokio::time::timeout(timeout, async {
+            let trans = Transaction::begin(connection).await?;
+
+            let row = {
+                let mut conn_guard = trans.conn.lock().await;
+                let conn = conn_guard.as_mut()
+                    .ok_or_else(|| AppError::Internal("Failed to get connection from pool".to_string()))?;
+
+                let param_refs: Vec<&dyn ToSql> = params.iter().map(|p| p.as_ref()).collect();
+
+                // Data sebelum update untuk audit (delete memakai OUTPUT DELETED)
+                let before = match (action, key) {
+                    ("update", Some((_, key_index))) => {
+                        let select = format!("SELECT * FROM {} WITH (UPDLOCK) WHERE {} = @P1{}", view.quoted_table(), quote(&view.key_column().name), view.row_scope);
+                        conn.query(select, &[param_refs[key_index]]).await?
+                            .into_row().await?
+                            .map(|row| DataService::row_to_json(&row))
+                    }
+                    _ => None,
+                };
+
+                let query = format!("{} {}; SELECT * FROM @output;", view.declare_output(), query);
+                // Result set terakhir, trigger bisa saja ikut mengembalikan result set sendiri
+                let rows = conn.query(query, &param_refs).await?.into_results().await?.pop().unwrap_or_default();
+
+                match (rows.first(), key) {
+                    (Some(row), _) => {
+                        let row = DataService::row_to_json(row);
+                        let entity_key = row.get(&view.key_column().name).map(|k| match k {
+                            JsonValue::String(k) => k.clone(),
+                            k => k.to_string(),
+                        });
+                        let (before, after) = match action {
+                            "insert" => (None, Some(row.clone())),
+                            "delete" => (Some(row.clone()), None),
+                            _ => (before, Some(row.clone())),
+                        };
+
+                        AuditService::write(conn, actor, AuditEntry { action, entity: &view.tablename, entity_key, before, after }).await?;
+                        row
+                    }
+                    (None, None) => return Err(AppError::Internal(format!("Insert into '{}' returned no row", view.tablename))),
+                    (None, Some((key, key_index))) => {
+                        let exists = format!("SELECT COUNT(*) FROM {} WHERE {} = @P1{}", view.quoted_table(), quote(&view.key_column().name), view.row_scope);
+                        let count = conn.query(exists, &[param_refs[key_index]]).await?
+                            .into_row().await?
+                            .and_then(|r| r.get::<i32, _>(0))
+                            .unwrap_or(0);
+
+                        return Err(if count == 0 {
+                            AppError::NotFound(format!("Row {} not found in '{}'", key, view.tablename))
+                        } else {
+                            AppError::Conflict("Row was modified by another user, reload and try again".to_string())
+                        });
+                    }
+                }
+            };
+
+            trans.commit().await?;
+            Ok::<_, AppError>(row)
+        }).await.map_err(|_| query_cancelled(timeout))??;
+
+        METRICS.observe_query(&format!("row_{}", action), &view.tablename, started.elapsed());
+        QUERY_CACHE.invalidate_view(&view.tablename);
+
+        Ok(row)
+    }
+
+    /// Validasi satu nilai JSON terhadap tipe kolom lalu ubah ke parameter SQL
+    fn to_sql_param(column: &ColumnMeta, value: &JsonValue) -> Result<SqlParam, ValidationError> {
+        if value.is_null() {
+            return if column.nullable {
+                Ok(Box::new(Option::<String>::None))
+            } else {
+                Err(ValidationError::new("required"))
+            };
+        }
+
+        let text = value.as_str().map(str::trim);
+
+        match column.data_type.as_str() {
+            "tinyint" | "smallint" | "int" | "bigint" => {
+                let (min, max) = match column.data_type.as_str() {
+                    "tinyint" => (0, u8::MAX as i64),
+                    "smallint" => (i16::MIN as i64, i16::MAX as i64),
+                    "int" => (i32::MIN as i64, i32::MAX as i64),
+                    _ => (i64::MIN, i64::MAX),
+                };
+                let number = value.as_i64()
+                    .or_else(|| text.and_then(|s| s.parse().ok()))
+                    .ok_or_else(|| validation_error("invalid_type", "Value must be an integer".to_string()))?;
+
+                if number < min || number > max {
+                    return Err(validation_error("out_of_range", format!("Value must be between {} and {}", min, max)));
+                }
+                Ok(Box::new(number))
+            }
+            "bit" => {
+                let flag = match value {
+                    JsonValue::Bool(b) => Some(*b),
+                    JsonValue::Number(n) => n.as_i64().filter(|n| *n == 0 || *n == 1).map(|n| n == 1),
+                    JsonValue::String(s) => match s.trim().to_lowercase().as_str() {
+                        "true" | "1" => Some(true),
+                        "false" | "0" => Some(false),
+                        _ => None,
+                    },
+                    _ => None,
+                };
+                flag.map(|b| Box::new(b) as SqlParam)
+                    .ok_or_else(|| validation_error("invalid_type", "Value must be a boolean".to_string()))
+            }
+            "float" | "real" => value.as_f64()
+                .or_else(|| text.and_then(|s| s.parse().ok()))
+                .map(|n| Box::new(n) as SqlParam)
+                .ok_or_else(|| validation_error("invalid_type", "Value must be a number".to_string())),
+            "decimal" | "numeric" | "money" | "smallmoney" => {
+                // Dikirim sebagai text supaya presisi desimal tidak hilang lewat f64
+                let number = match value {
+                    JsonValue::Number(n) => n.to_string(),
+                    JsonValue::String(s) if s.trim().parse::<f64>().is_ok() => s.trim().to_string(),
+                    _ => return Err(validation_error("invalid_type", "Value must be a number".to_string())),
+                };
+
+                let (precision, scale) = match column.data_type.as_str() {
+                    "money" => (19, 4),
+                    "smallmoney" => (10, 4),
+                    _ => (column.precision.unwrap_or(38), column.scale.unwrap_or(0)),
+                };
+                let integer_digits = number.trim_start_matches('-').split('.').next().unwrap_or_default().trim_start_matches('0').len() as i64;
+                if integer_digits > precision - scale {
+                    return Err(validation_error("out_of_range", format!("Value exceeds precision ({}, {})", precision, scale)));
+                }
+                Ok(Box::new(number))
+            }
+            "date" => text.and_then(|s| NaiveDate::parse_from_str(s, "%Y-%m-%d").ok())
+                .map(|d| Box::new(d) as SqlParam)
+                .ok_or_else(|| validation_error("invalid_date", "Value must be a date (YYYY-MM-DD)".to_string())),
+            "datetime" | "datetime2" | "smalldatetime" => text.and_then(Self::parse_datetime)
+                .map(|d| Box::new(d) as SqlParam)
+                .ok_or_else(|| validation_error("invalid_date", "Value must be a datetime (YYYY-MM-DD HH:MM:SS)".to_string())),
+            "time" => text.filter(|s| NaiveTime::parse_from_str(s, "%H:%M:%S%.f").is_ok() || NaiveTime::parse_from_str(s, "%H:%M").is_ok())
+                .map(|s| Box::new(s.to_string()) as SqlParam)
+                .ok_or_else(|| validation_error("invalid_time", "Value must be a time (HH:MM:SS)".to_string())),
+            "datetimeoffset" => text.filter(|s| DateTime::parse_from_rfc3339(s).is_ok())
+                .map(|s| Box::new(s.to_string()) as SqlParam)
+                .ok_or_else(|| validation_error("invalid_date", "Value must be an RFC 3339 datetime".to_string())),
+            "uniqueidentifier" => text.filter(|s| uuid::Uuid::parse_str(s).is_ok())
+                .map(|s| Box::new(s.to_string()) as SqlParam)
+                .ok_or_else(|| validation_error("invalid_uuid", "Value must be a UUID".to_string())),
+            "char" | "varchar" | "nchar" | "nvarchar" | "text" | "ntext" => {
+                let text = value.as_str()
+                    .ok_or_else(|| validation_error("invalid_type", "Value must be a string".to_string()))?;
+
+                if let Some(max) = column.max_length.filter(|max| *max > 0) {
+                    if text.chars().count() as i64 > max {
+                        return Err(validation_error("max_length", format!("Maximum length is {} characters", max)));
+                    }
+                }
+                Ok(Box::new(text.to_string()))
+            }
+            other => Err(validation_error("unsupported_type", format!("Column type '{}' cannot be edited", other))),
+        }
+    }
+
+    /// Versi dari client: rowversion (base64 / array byte) atau datetime
+    fn version_param(column: &ColumnMeta, version: &JsonValue) -> Result<SqlParam, ValidationError> {
+        let invalid = || validation_error("invalid_version", "Invalid version value".to_string());
+
+        if column.is_rowversion() {
+            let bytes = match version {
+                JsonValue::String(s) => general_purpose::STANDARD.decode(s.trim()).map_err(|_| invalid())?,
+                JsonValue::Array(items) => items.iter()
+                    .map(|b| b.as_u64().and_then(|b| u8::try_from(b).ok()))
+                    .collect::<Option<Vec<u8>>>()
+                    .ok_or_else(invalid)?,
+                _ => return Err(invalid()),
+            };
+            return Ok(Box::new(bytes));
+        }
+
+        version.as_str()
+            .and_then(|s| Self::parse_datetime(s.trim()))
+            .map(|d| Box::new(d) as SqlParam)
+            .ok_or_else(invalid)
+    }
+
+    fn parse_datetime(value: &str) -> Option<NaiveDateTime> {
+        ["%Y-%m-%dT%H:%M:%S%.f", "%Y-%m-%d %H:%M:%S%.f", "%Y-%m-%dT%H:%M", "%Y-%m-%d %H:%M"]
+            .iter()
+            .find_map(|format| NaiveDateTime::parse_from_str(value, format).ok())
+            .or_else(|| NaiveDate::parse_from_str(value, "%Y-%m-%d").ok().and_then(|d| d.and_hms_opt(0, 0, 0)))
+    }
+}
@@ -0,0 +1,257 @@
+use std::{collections::HashMap, env, sync::Mutex, time::{Duration, Instant}};
+
+use base64::{engine::general_purpose, Engine as _};
+use bb8::Pool;
+use once_cell::sync::Lazy;
+use rand::Rng;
+use sha2::{Digest, Sha256};
+
+use crate::contexts::{auth::{encode_claims, AuditActor}, connection::{ConnectionManager, Transaction}, error::AppError, model::{ActionResult, Claims, SessionInfo}};
+use super::{audit_service::{AuditEntry, AuditService}, credential_service::CredentialService};
+
+/// Sesi yang dicabut di proses ini, disimpan sampai access token terakhirnya pasti kedaluwarsa.
+/// Di deployment multi-instance, instance lain tetap menolak paling lambat saat access token habis.
+static REVOKED_SESSIONS: Lazy<Mutex<HashMap<String, Instant>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// Access token + refresh token baru untuk satu sesi
+pub struct IssuedTokens {
+    pub claims: Claims,
+    pub access_token: String,
+    pub refresh_token: String,
+    /// Umur refresh token dalam detik, untuk `max-age` cookie
+    pub refresh_max_age: i64,
+}
+
+pub struct SessionService;
+
+impl SessionService {
+    /// Umur refresh token (`REFRESH_TOKEN_TTL_SECS`, default 14 hari), diperpanjang setiap refresh
+    fn refresh_ttl_secs() -> i64 {
+        env::var("REFRESH_TOKEN_TTL_SECS").ok().and_then(|v| v.parse().ok()).unwrap_or(14 * 24 * 3600)
+    }
+
+    /// Token acak untuk client, hanya SHA-256 hex-nya yang disimpan di database
+    fn new_refresh_token() -> (String, String) {
+        let mut bytes = [0u8; 32];
+        rand::rng().fill(&mut bytes[..]);
+        let token = general_purpose::URL_SAFE_NO_PAD.encode(bytes);
+        let hash = Self::hash_token(&token);
+
+        (token, hash)
+    }
+
+    fn hash_token(token: &str) -> String {
+        Sha256::digest(token.as_bytes()).iter().map(|b| format!("{:02x}", b)).collect()
+    }
+
+    fn issue(mut claims: Claims, session_id: &str, refresh_token: String) -> Result<IssuedTokens, AppError> {
+        claims.sid = Some(session_id.to_string());
+
+        Ok(IssuedTokens {
+            access_token: encode_claims(&claims)?,
+            claims,
+            refresh_token,
+            refresh_max_age: Self::refresh_ttl_secs(),
+        })
+    }
+
+    /// Buat sesi baru setelah login berhasil
+    pub async fn create(connection: &Pool<ConnectionManager>, claims: Claims, user_agent: Option<String>) -> Result<IssuedTokens, AppError> {
+        let session_id = uuid::Uuid::new_v4().to_string();
+        let (refresh_token, token_hash) = Self::new_refresh_token();
+        let ttl = Self::refresh_ttl_secs() as i32;
+
+        let trans = Transaction::begin(connection).await?;
+        {
+            let mut conn_guard = trans.conn.lock().await;
+            let conn = conn_guard.as_mut()
+                .ok_or_else(|| AppError::Internal("Failed to get connection from pool".to_string()))?;
+
+            conn.execute(
+                r#"INSERT INTO [dbo].[AuthSession]
+                ([SessionID],[AuthUserNID],[IpAddress],[AppName],[UserAgent],[ExpiresAt])
+                VALUES (@P1,@P2,@P3,@P4,@P5,DATEADD(second, @P6, GETDATE()))"#,
+                &[&session_id, &claims.auth_usernid, &claims.ip_address, &claims.app_name, &user_agent, &ttl],
+            ).await?;
+
+            conn.execute(
+                "INSERT INTO [dbo].[AuthRefreshToken] ([TokenHash],[SessionID]) VALUES (@P1,@P2)",
+                &[&token_hash, &session_id],
+            ).await?;
+        }
+        trans.commit().await?;
+
+        Self::issue(claims, &session_id, refresh_token)
+    }
+
+    /// Tukar refresh token dengan pasangan token baru (rotasi). Refresh token yang sudah pernah
+    /// dipakai dianggap bocor: seluruh sesi (family) dicabut.
+    pub async fn refresh(connection: &Pool<ConnectionManager>, refresh_token: &str, actor: &AuditActor, user_agent: Option<String>) -> Result<IssuedTokens, AppError> {
+        let token_hash = Self::hash_token(refresh_token);
+        let (new_token, new_hash) = Self::new_refresh_token();
+        let ttl = Self::refresh_ttl_secs() as i32;
+
+        let trans = Transaction::begin(connection).await?;
+        let outcome = {
+            let mut conn_guard = trans.conn.lock().await;
+            let conn = conn_guard.as_mut()
+                .ok_or_else(|| AppError::Internal("Failed to get connection from pool".to_string()))?;
+
+            let row = conn.query(
+                r#"SELECT t.SessionID, t.UsedAt, s.AuthUserNID, s.RevokedAt,
+                    CAST(CASE WHEN s.ExpiresAt <= GETDATE() THEN 1 ELSE 0 END AS bit) AS Expired
+                FROM [dbo].[AuthRefreshToken] t WITH (UPDLOCK)
+                JOIN [dbo].[AuthSession] s WITH (UPDLOCK) ON s.SessionID = t.SessionID
+                WHERE t.TokenHash = @P1"#,
+                &[&token_hash],
+            ).await?.into_row().await?
+                .ok_or_else(|| AppError::Unauthorized("Invalid refresh token".to_string()))?;
+
+            let session_id = row.get::<&str, _>("SessionID").unwrap_or_default().to_string();
+            let user_nid: i32 = row.get("AuthUserNID").unwrap_or_default();
+
+            if row.get::<chrono::NaiveDateTime, _>("RevokedAt").is_some() || row.get::<bool, _>("Expired").unwrap_or(true) {
+                return Err(AppError::Unauthorized("Session has ended, please login again".to_string()));
+            }
+
+            if row.get::<chrono::NaiveDateTime, _>("UsedAt").is_some() {
+                Self::revoke_in(conn, &session_id, "reuse").await?;
+
+                let actor = AuditActor { user_nid: Some(user_nid), ..actor.clone() };
+                AuditService::write(conn, &actor, AuditEntry { action: "session_reuse", entity: "AuthSession", entity_key: Some(session_id.clone()), before: None, after: None }).await?;
+                Err((session_id, AppError::Unauthorized("Refresh token was already used, session revoked".to_string())))
+            } else {
+                conn.execute("UPDATE [dbo].[AuthRefreshToken] SET UsedAt = GETDATE() WHERE TokenHash = @P1", &[&token_hash]).await?;
+                conn.execute(
+                    "INSERT INTO [dbo].[AuthRefreshToken] ([TokenHash],[SessionID]) VALUES (@P1,@P2)",
+                    &[&new_hash, &session_id],
+                ).await?;
+                conn.execute(
+                    r#"UPDATE [dbo].[AuthSession] SET LastRefreshAt = GETDATE(), ExpiresAt = DATEADD(second, @P2, GETDATE()),
+                        IpAddress = COALESCE(@P3, IpAddress), UserAgent = COALESCE(@P4, UserAgent)
+                    WHERE SessionID = @P1"#,
+                    &[&session_id, &ttl, &actor.ip_address, &user_agent],
+                ).await?;
+
+                let claims = CredentialService::load_claims(conn, user_nid, actor.ip_address.clone()).await?;
+                Ok((session_id, claims))
+            }
+        };
+        trans.commit().await?;
+
+        match outcome {
+            Ok((session_id, claims)) => Self::issue(claims, &session_id, new_token),
+            Err((session_id, e)) => {
+                Self::mark_revoked(&session_id);
+                Err(e)
+            }
+        }
+    }
+
+    /// Sesi aktif milik user, `current_sid` ditandai sebagai sesi yang sedang dipakai
+    pub async fn list(connection: &Pool<ConnectionManager>, user_nid: i32, current_sid: Option<&str>) -> Result<ActionResult<Vec<SessionInfo>, String>, AppError> {
+        let mut conn = connection.get().await?;
+        let rows = conn.query(
+            r#"SELECT SessionID, IpAddress, AppName, UserAgent, CreatedAt, LastRefreshAt, ExpiresAt
+            FROM [dbo].[AuthSession]
+            WHERE AuthUserNID = @P1 AND RevokedAt IS NULL AND ExpiresAt > GETDATE()
+            ORDER BY COALESCE(LastRefreshAt, CreatedAt) DESC"#,
+            &[&user_nid],
+        ).await?.into_first_result().await?;
+
+        let sessions = rows.iter().map(|row| {
+            let session_id = row.get::<&str, _>("SessionID").unwrap_or_default().to_string();
+            SessionInfo {
+                current: current_sid == Some(session_id.as_str()),
+                session_id,
+                ip_address: row.get::<&str, _>("IpAddress").map(str::to_string),
+                app_name: row.get::<&str, _>("AppName").map(str::to_string),
+                user_agent: row.get::<&str, _>("UserAgent").map(str::to_string),
+                created_at: row.get("CreatedAt"),
+                last_refresh_at: row.get("LastRefreshAt"),
+                expires_at: row.get("ExpiresAt"),
+            }
+        }).collect();
+
+        Ok(ActionResult {
+            result: true,
+            message: "Sessions retrieved successfully".to_string(),
+            data: Some(sessions),
+            ..Default::default()
+        })
+    }
+
+    /// Cabut satu sesi milik user (logout perangkat lain atau logout sesi saat ini)
+    pub async fn revoke(connection: &Pool<ConnectionManager>, user_nid: i32, session_id: &str, actor: &AuditActor) -> Result<(), AppError> {
+        let mut conn = connection.get().await?;
+        let affected = conn.execute(
+            r#"UPDATE [dbo].[AuthSession] SET RevokedAt = GETDATE(), RevokedReason = 'revoked'
+            WHERE SessionID = @P1 AND AuthUserNID = @P2 AND RevokedAt IS NULL"#,
+            &[&session_id, &user_nid],
+        ).await?.total();
+
+        if affected == 0 {
+            return Err(AppError::NotFound(format!("Session '{}' not found", session_id)));
+        }
+
+        Self::mark_revoked(session_id);
+        AuditService::write(&mut conn, actor, AuditEntry { action: "session_revoke", entity: "AuthSession", entity_key: Some(session_id.to_string()), before: None, after: None }).await
+    }
+
+    /// Cabut sesi pemilik refresh token (logout saat access token sudah kedaluwarsa)
+    pub async fn revoke_by_token(connection: &Pool<ConnectionManager>, refresh_token: &str) -> Result<(), AppError> {
+        let token_hash = Self::hash_token(refresh_token);
+        let mut conn = connection.get().await?;
+
+        let session_id = conn.query("SELECT SessionID FROM [dbo].[AuthRefreshToken] WHERE TokenHash = @P1", &[&token_hash])
+            .await?.into_row().await?
+            .and_then(|row| row.get::<&str, _>("SessionID").map(str::to_string));
+
+        if let Some(session_id) = session_id {
+            Self::revoke_in(&mut conn, &session_id, "logout").await?;
+            Self::mark_revoked(&session_id);
+        }
+
+        Ok(())
+    }
+
+    /// Cabut semua sesi user, misalnya setelah password diganti
+    pub async fn revoke_all(conn: &mut bb8_tiberius::rt::Client, user_nid: i32, reason: &str) -> Result<(), AppError> {
+        let rows = conn.query(
+            r#"UPDATE [dbo].[AuthSession] SET RevokedAt = GETDATE(), RevokedReason = @P2
+            OUTPUT inserted.SessionID
+            WHERE AuthUserNID = @P1 AND RevokedAt IS NULL"#,
+            &[&user_nid, &reason],
+        ).await?.into_first_result().await?;
+
+        rows.iter()
+            .filter_map(|row| row.get::<&str, _>("SessionID"))
+            .for_each(Self::mark_revoked);
+
+        Ok(())
+    }
+
+    async fn revoke_in(conn: &mut bb8_tiberius::rt::Client, session_id: &str, reason: &str) -> Result<(), AppError> {
+        conn.execute(
+            "UPDATE [dbo].[AuthSession] SET RevokedAt = GETDATE(), RevokedReason = @P2 WHERE SessionID = @P1 AND RevokedAt IS NULL",
+            &[&session_id, &reason],
+        ).await?;
+
+        Ok(())
+    }
+
+    fn mark_revoked(session_id: &str) {
+        let access_ttl: u64 = env::var("JWT_EXPIRES_SECS").ok().and_then(|v| v.parse().ok()).unwrap_or(15 * 60);
+        let mut revoked = REVOKED_SESSIONS.lock().unwrap();
+
+        revoked.retain(|_, until| *until > Instant::now());
+        revoked.insert(session_id.to_string(), Instant::now() + Duration::from_secs(access_ttl));
+    }
+
+    /// Dipakai saat decode access token: token dari sesi yang sudah dicabut ditolak
+    pub fn is_revoked(session_id: &str) -> bool {
+        REVOKED_SESSIONS.lock().unwrap()
+            .get(session_id)
+            .is_some_and(|until| *until > Instant::now())
+    }
+}
@@ -0,0 +1,152 @@
+use std::collections::HashMap;
+
+use bb8::Pool;
+use bb8_tiberius::rt::Client;
+use serde_json::{json, Value as JsonValue};
+
+use crate::contexts::{auth::AuditActor, connection::{ConnectionManager, Transaction}, error::AppError, model::{ActionResult, SettingSchema, UpdateSettingsRequest}, preferences::{setting, SettingKind, UserPreferences, SETTINGS}};
+use super::audit_service::{AuditEntry, AuditService};
+
+pub struct SettingService;
+
+impl SettingService {
+    /// Nilai yang tersimpan untuk user (belum digabung default). Key yang sudah tidak ada di schema diabaikan.
+    async fn stored_values(conn: &mut Client, user_nid: i32) -> Result<HashMap<String, JsonValue>, AppError> {
+        let rows = conn.query(
+            "SELECT SettingKey, SettingValue FROM [dbo].[AuthUserSetting] WHERE AuthUserNID = @P1",
+            &[&user_nid],
+        ).await?.into_first_result().await?;
+
+        Ok(rows.iter()
+            .filter_map(|row| {
+                let key = row.get::<&str, _>("SettingKey")?;
+                let value = serde_json::from_str(row.get::<&str, _>("SettingValue")?).ok()?;
+                setting(key).map(|_| (key.to_string(), value))
+            })
+            .collect())
+    }
+
+    /// Preferensi user untuk dipakai service lain (mis. format export)
+    pub async fn preferences(connection: &Pool<ConnectionManager>, user_nid: i32) -> Result<UserPreferences, AppError> {
+        let mut conn = connection.get().await?;
+        let values = Self::stored_values(&mut conn, user_nid).await?;
+
+        Ok(UserPreferences::from_values(&values))
+    }
+
+    pub async fn get(connection: &Pool<ConnectionManager>, user_nid: i32) -> Result<ActionResult<UserPreferences, String>, AppError> {
+        Ok(ActionResult {
+            result: true,
+            message: "Settings retrieved successfully".to_string(),
+            data: Some(Self::preferences(connection, user_nid).await?),
+            ..Default::default()
+        })
+    }
+
+    pub fn schema() -> ActionResult<Vec<SettingSchema>, String> {
+        let schema = SETTINGS.iter().map(|(key, kind)| {
+            let mut item = SettingSchema {
+                key: key.to_string(),
+                kind: String::new(),
+                default: kind.default_value(),
+                values: None,
+                min: None,
+                max: None,
+                max_length: None,
+            };
+            match kind {
+                SettingKind::Choice { values, .. } => {
+                    item.kind = "choice".to_string();
+                    item.values = Some(values.iter().map(|v| v.to_string()).collect());
+                }
+                SettingKind::Integer { min, max, .. } => {
+                    item.kind = "integer".to_string();
+                    item.min = Some(*min);
+                    item.max = Some(*max);
+                }
+                SettingKind::Text { max_length, .. } => {
+                    item.kind = "text".to_string();
+                    item.max_length = Some(*max_length);
+                }
+            }
+            item
+        }).collect();
+
+        ActionResult {
+            result: true,
+            message: "Setting schema retrieved successfully".to_string(),
+            data: Some(schema),
+            ..Default::default()
+        }
+    }
+
+    /// Simpan sebagian setting. Semua key divalidasi dulu, jika ada yang salah tidak ada yang disimpan.
+    pub async fn update(connection: &Pool<ConnectionManager>, user_nid: i32, request: UpdateSettingsRequest, actor: &AuditActor) -> Result<ActionResult<UserPreferences, String>, AppError> {
+        if request.values.is_empty() {
+            return Err(AppError::BadRequest("No settings to update".to_string()));
+        }
+
+        let mut errors = HashMap::new();
+        for (key, value) in &request.values {
+            match setting(key) {
+                None => { errors.insert(key.clone(), "Unknown setting".to_string()); }
+                Some(_) if value.is_null() => {}
+                Some(kind) => if let Err(message) = kind.validate(value) {
+                    errors.insert(key.clone(), message);
+                },
+            }
+        }
+        if !errors.is_empty() {
+            return Err(AppError::Validation(errors));
+        }
+
+        let trans = Transaction::begin(connection).await?;
+        let values = {
+            let mut conn_guard = trans.conn.lock().await;
+            let conn = conn_guard.as_mut()
+                .ok_or_else(|| AppError::Internal("Failed to get connection from pool".to_string()))?;
+
+            let before = Self::stored_values(conn, user_nid).await?;
+
+            for (key, value) in &request.values {
+                match value {
+                    JsonValue::Null => {
+                        conn.execute(
+                            "DELETE FROM [dbo].[AuthUserSetting] WHERE AuthUserNID = @P1 AND SettingKey = @P2",
+                            &[&user_nid, &key.as_str()],
+                        ).await?;
+                    }
+                    value => {
+                        conn.execute(
+                            r#"MERGE [dbo].[AuthUserSetting] WITH (HOLDLOCK) AS target
+                            USING (SELECT @P1 AS AuthUserNID, @P2 AS SettingKey) AS source
+                            ON target.AuthUserNID = source.AuthUserNID AND target.SettingKey = source.SettingKey
+                            WHEN MATCHED THEN UPDATE SET SettingValue = @P3, LastUpdate = GETDATE()
+                            WHEN NOT MATCHED THEN INSERT ([AuthUserNID],[SettingKey],[SettingValue]) VALUES (@P1,@P2,@P3);"#,
+                            &[&user_nid, &key.as_str(), &value.to_string()],
+                        ).await?;
+                    }
+                }
+            }
+
+            let changed: Vec<&String> = request.values.keys().collect();
+            AuditService::write(conn, actor, AuditEntry {
+                action: "settings_update",
+                entity: "AuthUserSetting",
+                entity_key: Some(user_nid.to_string()),
+                before: Some(json!(changed.iter().map(|k| (k.as_str(), before.get(*k))).collect::<HashMap<_, _>>())),
+                after: Some(json!(request.values)),
+            }).await?;
+
+            Self::stored_values(conn, user_nid).await?
+        };
+        trans.commit().await?;
+
+        Ok(ActionResult {
+            result: true,
+            message: "Settings updated successfully".to_string(),
+            data: Some(UserPreferences::from_values(&values)),
+            ..Default::default()
+        })
+    }
+}
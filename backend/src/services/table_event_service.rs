@@ -0,0 +1,246 @@
+use std::{collections::HashMap, env, fmt::Write, sync::Mutex, time::Duration};
+
+use actix_web::web::{self, Bytes};
+use base64::{engine::general_purpose, Engine as _};
+use bb8::Pool;
+use futures::Stream;
+use once_cell::sync::Lazy;
+use serde_json::{json, Value as JsonValue};
+use tiberius::{Row, ToSql};
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
+
+use crate::contexts::{column_mask::ColumnMasks, connection::{query_cancelled, query_timeout, ConnectionManager}, error::AppError, logger::write_log, model::TableEventParams, request_id::{current_request_id, scope_request_id}, row_scope::{is_identifier, RowScope}};
+use super::data_service::DataService;
+
+/// Jumlah subscriber SSE yang aktif per view
+static SUBSCRIBERS: Lazy<Mutex<HashMap<String, usize>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// Slot subscriber, otomatis dilepas saat task SSE selesai (client disconnect)
+struct SubscriberGuard {
+    tablename: String,
+}
+
+impl SubscriberGuard {
+    fn acquire(tablename: &str) -> Result<Self, AppError> {
+        let max: usize = env::var("SSE_MAX_SUBSCRIBERS_PER_VIEW").ok().and_then(|v| v.parse().ok()).unwrap_or(20);
+        let tablename = tablename.to_lowercase();
+        let mut subscribers = SUBSCRIBERS.lock().unwrap();
+        let count = subscribers.entry(tablename.clone()).or_insert(0);
+
+        if *count >= max {
+            return Err(AppError::Unavailable(format!("Too many live subscribers for this view (max {})", max)));
+        }
+
+        *count += 1;
+        Ok(Self { tablename })
+    }
+}
+
+impl Drop for SubscriberGuard {
+    fn drop(&mut self) {
+        if let Ok(mut subscribers) = SUBSCRIBERS.lock() {
+            if let Some(count) = subscribers.get_mut(&self.tablename) {
+                *count = count.saturating_sub(1);
+            }
+        }
+    }
+}
+
+/// Posisi terakhir yang sudah dikirim ke client: (nilai kolom watermark, primary key)
+struct Watermark {
+    value: JsonValue,
+    key: JsonValue,
+}
+
+impl Watermark {
+    /// Nilai asli dari baris (bukan hasil `row_to_json`), datetime diberi tag supaya di-bind sebagai `NaiveDateTime`
+    fn from_row(row: &Row, q_watermark: &str, q_primary_key: &str) -> Self {
+        Self {
+            value: DataService::cursor_value(row, q_watermark).unwrap_or(JsonValue::Null),
+            key: DataService::cursor_value(row, q_primary_key).unwrap_or(JsonValue::Null),
+        }
+    }
+
+    fn encode(&self) -> String {
+        general_purpose::URL_SAFE_NO_PAD.encode(json!([self.value, self.key]).to_string())
+    }
+
+    fn decode(event_id: &str) -> Result<Self, AppError> {
+        general_purpose::URL_SAFE_NO_PAD.decode(event_id.trim())
+            .ok()
+            .and_then(|bytes| serde_json::from_slice::<(JsonValue, JsonValue)>(&bytes).ok())
+            .map(|(value, key)| Self { value, key })
+            .ok_or_else(|| AppError::BadRequest("Invalid Last-Event-ID".to_string()))
+    }
+}
+
+pub struct TableEventService;
+
+impl TableEventService {
+    /// Buka stream SSE untuk satu view. Perubahan dideteksi dengan polling kolom watermark
+    /// (`LastUpdate`) + primary key, lalu dikirim sebagai event `insert` / `update`.
+    pub async fn subscribe(params: TableEventParams, connection: web::Data<Pool<ConnectionManager>>, last_event_id: Option<String>, scope: &RowScope, masks: ColumnMasks) -> Result<impl Stream<Item = Bytes>, AppError> {
+        for column in [params.nidkey.as_deref(), params.watermark.as_deref()].into_iter().flatten() {
+            if !is_identifier(column) {
+                return Err(AppError::BadRequest(format!("Invalid column name '{}'", column)));
+            }
+        }
+
+        let guard = SubscriberGuard::acquire(&params.tablename)?;
+
+        let pool = connection.get_ref().clone();
+        let poll_interval = Duration::from_secs(env::var("SSE_POLL_INTERVAL_SECS").ok().and_then(|v| v.parse().ok()).unwrap_or(5));
+        let heartbeat_interval = Duration::from_secs(env::var("SSE_HEARTBEAT_SECS").ok().and_then(|v| v.parse().ok()).unwrap_or(15));
+
+        let q_primary_key = params.nidkey.clone().unwrap_or_else(|| "AutoNID".to_string());
+        let q_watermark = params.watermark.clone().unwrap_or_else(|| "LastUpdate".to_string());
+        let q_where = Self::get_query_where(&params, scope);
+
+        // Posisi awal: dari Last-Event-ID (reconnect) atau baris terbaru saat ini
+        let mut watermark = match last_event_id.or(params.last_event_id.clone()).filter(|id| !id.is_empty()) {
+            Some(event_id) => Watermark::decode(&event_id)?,
+            None => Self::current_watermark(&pool, &params.tablename, &q_where, &q_watermark, &q_primary_key).await?,
+        };
+        let mut max_key: Option<i64> = Self::current_max_key(&pool, &params.tablename, &q_where, &q_primary_key).await?;
+
+        let (tx, rx) = mpsc::channel::<Bytes>(16);
+
+        tokio::spawn(scope_request_id(current_request_id(), async move {
+            let _guard = guard;
+            let mut poll = tokio::time::interval(poll_interval);
+            let mut heartbeat = tokio::time::interval(heartbeat_interval);
+
+            if tx.send(Bytes::from(format!("retry: {}\n\n", poll_interval.as_millis()))).await.is_err() {
+                return;
+            }
+
+            loop {
+                tokio::select! {
+                    _ = poll.tick() => {
+                        let rows = match Self::poll_changes(&pool, &params.tablename, &q_where, &q_watermark, &q_primary_key, &watermark).await {
+                            Ok(rows) => rows,
+                            Err(e) => {
+                                write_log("ERROR", &format!("[{}] SSE poll failed for '{}': {}", e.code(), params.tablename, e));
+                                let event = format!("event: error\ndata: {}\n\n", json!({ "message": e.message(), "error_code": e.code() }));
+                                if tx.send(Bytes::from(event)).await.is_err() {
+                                    return;
+                                }
+                                continue;
+                            }
+                        };
+
+                        for row in rows {
+                            watermark = Watermark::from_row(&row, &q_watermark, &q_primary_key);
+
+                            // Primary key di atas max saat subscribe dianggap baris baru
+                            let key = watermark.key.as_i64();
+                            let kind = match (key, max_key) {
+                                (Some(key), Some(max)) if key <= max => "update",
+                                _ => "insert",
+                            };
+                            if let Some(key) = key {
+                                max_key = Some(max_key.map_or(key, |max| max.max(key)));
+                            }
+
+                            let event = format!("id: {}\nevent: {}\ndata: {}\n\n", watermark.encode(), kind, masks.apply(DataService::row_to_json(&row)));
+                            if tx.send(Bytes::from(event)).await.is_err() {
+                                return;
+                            }
+                        }
+                    }
+                    _ = heartbeat.tick() => {
+                        if tx.send(Bytes::from_static(b": heartbeat\n\n")).await.is_err() {
+                            return;
+                        }
+                    }
+                }
+            }
+        }));
+
+        Ok(ReceiverStream::new(rx))
+    }
+
+    fn get_query_where(params: &TableEventParams, scope: &RowScope) -> String {
+        let q_and_where = scope.where_clause();
+
+        match params.filter.as_deref() {
+            Some(filter) if filter != "{filter:undefined}" => {
+                match serde_json::from_str::<HashMap<String, String>>(filter) {
+                    Ok(filter_name) if !filter_name.is_empty() => DataService::get_query_table_where(q_and_where, filter_name),
+                    _ => q_and_where,
+                }
+            }
+            _ => q_and_where,
+        }
+    }
+
+    async fn current_watermark(pool: &Pool<ConnectionManager>, tablename: &str, q_where: &str, q_watermark: &str, q_primary_key: &str) -> Result<Watermark, AppError> {
+        let query = format!(
+            "SELECT TOP 1 {0}, {1} FROM [{2}] {3} ORDER BY {0} DESC, {1} DESC",
+            q_watermark, q_primary_key, tablename, q_where
+        );
+
+        let timeout = query_timeout("SSE");
+        let mut conn = pool.get().await?;
+
+        conn.mark_in_flight();
+        let row = tokio::time::timeout(timeout, async {
+            conn.query(query, &[]).await?.into_row().await
+        }).await.map_err(|_| query_cancelled(timeout))??;
+        conn.mark_done();
+
+        Ok(match row {
+            Some(row) => Watermark::from_row(&row, q_watermark, q_primary_key),
+            None => Watermark { value: JsonValue::Null, key: JsonValue::Null },
+        })
+    }
+
+    async fn current_max_key(pool: &Pool<ConnectionManager>, tablename: &str, q_where: &str, q_primary_key: &str) -> Result<Option<i64>, AppError> {
+        let query = format!("SELECT CAST(MAX({}) AS bigint) FROM [{}] {}", q_primary_key, tablename, q_where);
+
+        let timeout = query_timeout("SSE");
+        let mut conn = pool.get().await?;
+
+        conn.mark_in_flight();
+        let row = tokio::time::timeout(timeout, async {
+            conn.query(query, &[]).await?.into_row().await
+        }).await.map_err(|_| query_cancelled(timeout))??;
+        conn.mark_done();
+
+        Ok(row.and_then(|r| r.try_get::<i64, _>(0).ok().flatten()))
+    }
+
+    /// Ambil baris dengan (watermark, primary key) setelah posisi terakhir
+    async fn poll_changes(pool: &Pool<ConnectionManager>, tablename: &str, q_where: &str, q_watermark: &str, q_primary_key: &str, watermark: &Watermark) -> Result<Vec<Row>, AppError> {
+        let mut query = format!("SELECT TOP 500 * FROM [{}] {}", tablename, q_where);
+        let mut params: Vec<Box<dyn ToSql>> = Vec::new();
+
+        match (&watermark.value, &watermark.key) {
+            (JsonValue::Null, JsonValue::Null) => {}
+            (JsonValue::Null, key) => {
+                params.push(DataService::json_to_sql_param(key)?);
+                let _ = write!(query, " AND (({0} IS NULL AND {1} > @P1) OR {0} IS NOT NULL)", q_watermark, q_primary_key);
+            }
+            (value, key) => {
+                params.push(DataService::json_to_sql_param(key)?);
+                params.push(DataService::json_to_sql_param(value)?);
+                let _ = write!(query, " AND ({0} > @P2 OR ({0} = @P2 AND {1} > @P1))", q_watermark, q_primary_key);
+            }
+        }
+
+        let _ = write!(query, " ORDER BY {} ASC, {} ASC", q_watermark, q_primary_key);
+
+        let timeout = query_timeout("SSE");
+        let mut conn = pool.get().await?;
+
+        conn.mark_in_flight();
+        let param_refs: Vec<&dyn ToSql> = params.iter().map(|p| p.as_ref()).collect();
+        let rows = tokio::time::timeout(timeout, async {
+            conn.query(query, &param_refs).await?.into_first_result().await
+        }).await.map_err(|_| query_cancelled(timeout))??;
+        conn.mark_done();
+
+        Ok(rows)
+    }
+}
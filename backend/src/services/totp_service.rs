@@ -0,0 +1,487 @@
+use std::env;
+
+use aes::{cipher::{KeyIvInit, StreamCipher}, Aes256};
+use base64::{engine::general_purpose, Engine as _};
+use bb8::Pool;
+use bb8_tiberius::rt::Client;
+use chrono::{NaiveDateTime, Utc};
+use hmac::{Hmac, Mac};
+use rand::Rng;
+use sha1::Sha1;
+use sha2::{Digest, Sha256};
+
+use crate::contexts::{auth::AuditActor, connection::{ConnectionManager, Transaction}, error::AppError, model::{ActionResult, Claims, TotpBackupCodes, TotpEnrollment}};
+use super::{audit_service::{AuditEntry, AuditService}, credential_service::CredentialService};
+
+type Aes256Ctr = ctr::Ctr128BE<Aes256>;
+
+/// Panjang secret TOTP (160 bit, sesuai rekomendasi RFC 4226)
+const SECRET_LENGTH: usize = 20;
+const TOTP_PERIOD: i64 = 30;
+const TOTP_DIGITS: usize = 6;
+/// Toleransi selisih jam client: kode dari 1 step sebelum / sesudah masih diterima
+const TOTP_SKEW_STEPS: i64 = 1;
+const BACKUP_CODE_COUNT: usize = 10;
+/// Huruf & angka tanpa karakter yang mirip (0/o, 1/l/i) untuk backup code
+const BACKUP_CODE_ALPHABET: &[u8] = b"abcdefghjkmnpqrstuvwxyz23456789";
+const BASE32_ALPHABET: &[u8; 32] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ234567";
+/// Versi format `SecretEncrypted`, disimpan sebagai prefix
+const ENCRYPTION_VERSION: &str = "v1";
+
+pub struct TotpService;
+
+impl TotpService {
+    /// Kunci AES-256 dari `TOTP_ENCRYPTION_KEY` (32 byte, base64) dan kunci HMAC turunannya
+    fn encryption_keys() -> Result<([u8; 32], [u8; 32]), AppError> {
+        let key: [u8; 32] = env::var("TOTP_ENCRYPTION_KEY").ok()
+            .and_then(|v| general_purpose::STANDARD.decode(v.trim()).ok())
+            .and_then(|k| k.try_into().ok())
+            .ok_or_else(|| AppError::Internal("TOTP_ENCRYPTION_KEY harus diatur (32 byte base64)".to_string()))?;
+        let mac_key: [u8; 32] = Sha256::new().chain_update(b"totp-mac").chain_update(key).finalize().into();
+
+        Ok((key, mac_key))
+    }
+
+    /// Enkripsi secret dengan AES-256-CTR lalu HMAC-SHA256 atas nonce + ciphertext
+    fn encrypt_secret(secret: &[u8]) -> Result<String, AppError> {
+        let (key, mac_key) = Self::encryption_keys()?;
+        let mut nonce = [0u8; 16];
+        rand::rng().fill(&mut nonce[..]);
+
+        let mut ciphertext = secret.to_vec();
+        Aes256Ctr::new(&key.into(), &nonce.into()).apply_keystream(&mut ciphertext);
+        let tag = Self::hmac_sha256(&mac_key, &[&nonce[..], &ciphertext].concat());
+
+        Ok(format!(
+            "{}${}${}${}",
+            ENCRYPTION_VERSION,
+            general_purpose::STANDARD_NO_PAD.encode(nonce),
+            general_purpose::STANDARD_NO_PAD.encode(ciphertext),
+            general_purpose::STANDARD_NO_PAD.encode(tag)
+        ))
+    }
+
+    fn decrypt_secret(stored: &str) -> Result<Vec<u8>, AppError> {
+        let invalid = || AppError::Internal("Stored TOTP secret is invalid".to_string());
+        let (key, mac_key) = Self::encryption_keys()?;
+
+        let parts: Vec<&str> = stored.split('$').collect();
+        let [version, nonce, ciphertext, tag] = parts.as_slice() else {
+            return Err(invalid());
+        };
+        if *version != ENCRYPTION_VERSION {
+            return Err(invalid());
+        }
+
+        let nonce: [u8; 16] = general_purpose::STANDARD_NO_PAD.decode(nonce).ok()
+            .and_then(|n| n.try_into().ok())
+            .ok_or_else(invalid)?;
+        let mut secret = general_purpose::STANDARD_NO_PAD.decode(ciphertext).map_err(|_| invalid())?;
+        let tag = general_purpose::STANDARD_NO_PAD.decode(tag).map_err(|_| invalid())?;
+
+        let expected = Self::hmac_sha256(&mac_key, &[&nonce[..], &secret].concat());
+        if !CredentialService::constant_time_eq(&expected, &tag) {
+            return Err(invalid());
+        }
+
+        Aes256Ctr::new(&key.into(), &nonce.into()).apply_keystream(&mut secret);
+        Ok(secret)
+    }
+
+    fn hmac_sha256(key: &[u8; 32], data: &[u8]) -> [u8; 32] {
+        let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC accepts keys of any length");
+        mac.update(data);
+        mac.finalize().into_bytes().into()
+    }
+
+    /// HMAC-SHA1, algoritma default TOTP yang didukung semua aplikasi authenticator
+    fn hmac_sha1(key: &[u8], data: &[u8]) -> Vec<u8> {
+        let mut mac = Hmac::<Sha1>::new_from_slice(key).expect("HMAC accepts keys of any length");
+        mac.update(data);
+        mac.finalize().into_bytes().to_vec()
+    }
+
+    /// Kode HOTP 6 digit (RFC 4226) untuk `counter`
+    fn hotp(secret: &[u8], counter: u64) -> String {
+        let hash = Self::hmac_sha1(secret, &counter.to_be_bytes());
+        let offset = (hash[hash.len() - 1] & 0x0f) as usize;
+        let binary = hash[offset..offset + 4].iter().fold(0u32, |acc, b| (acc << 8) | *b as u32) & 0x7fff_ffff;
+
+        format!("{:0width$}", binary % 10u32.pow(TOTP_DIGITS as u32), width = TOTP_DIGITS)
+    }
+
+    /// Time step (RFC 6238) yang cocok dengan `code` dan lebih baru dari `last_used_step`
+    fn matching_step(secret: &[u8], code: &str, last_used_step: Option<i64>) -> Option<i64> {
+        let current = Utc::now().timestamp() / TOTP_PERIOD;
+
+        (current - TOTP_SKEW_STEPS..=current + TOTP_SKEW_STEPS)
+            .filter(|step| last_used_step.is_none_or(|last| *step > last))
+            .find(|step| CredentialService::constant_time_eq(Self::hotp(secret, *step as u64).as_bytes(), code.as_bytes()))
+    }
+
+    fn is_totp_code(code: &str) -> bool {
+        code.len() == TOTP_DIGITS && code.chars().all(|c| c.is_ascii_digit())
+    }
+
+    fn base32_encode(bytes: &[u8]) -> String {
+        let mut output = String::new();
+        let (mut buffer, mut bits) = (0u32, 0u32);
+
+        for byte in bytes {
+            buffer = ((buffer << 8) | *byte as u32) & 0x1fff;
+            bits += 8;
+            while bits >= 5 {
+                output.push(BASE32_ALPHABET[((buffer >> (bits - 5)) & 0x1f) as usize] as char);
+                bits -= 5;
+            }
+        }
+        if bits > 0 {
+            output.push(BASE32_ALPHABET[((buffer << (5 - bits)) & 0x1f) as usize] as char);
+        }
+
+        output
+    }
+
+    fn percent_encode(value: &str) -> String {
+        value.bytes().map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'@' => (b as char).to_string(),
+            _ => format!("%{:02X}", b),
+        }).collect()
+    }
+
+    /// URI `otpauth://` untuk QR code aplikasi authenticator, issuer dari `TOTP_ISSUER`
+    fn provisioning_uri(email: &str, secret: &str) -> String {
+        let issuer = Self::percent_encode(&env::var("TOTP_ISSUER").unwrap_or_else(|_| "UBS Trade Dashboard".to_string()));
+
+        format!(
+            "otpauth://totp/{}:{}?secret={}&issuer={}&algorithm=SHA1&digits={}&period={}",
+            issuer, Self::percent_encode(email), secret, issuer, TOTP_DIGITS, TOTP_PERIOD
+        )
+    }
+
+    /// Backup code `xxxx-xxxx`, disimpan sebagai SHA-256 hex tanpa tanda `-`
+    fn new_backup_codes() -> Vec<String> {
+        let mut rng = rand::rng();
+
+        (0..BACKUP_CODE_COUNT).map(|_| {
+            let code: String = (0..8).map(|_| BACKUP_CODE_ALPHABET[rng.random_range(0..BACKUP_CODE_ALPHABET.len())] as char).collect();
+            format!("{}-{}", &code[..4], &code[4..])
+        }).collect()
+    }
+
+    fn hash_backup_code(code: &str) -> String {
+        let normalized: String = code.chars().filter(|c| c.is_ascii_alphanumeric()).collect::<String>().to_lowercase();
+        Sha256::digest(normalized.as_bytes()).iter().map(|b| format!("{:02x}", b)).collect()
+    }
+
+    pub async fn is_enabled(connection: &Pool<ConnectionManager>, user_nid: i32) -> Result<bool, AppError> {
+        let mut conn = connection.get().await?;
+        let row = conn.query("SELECT 1 AS Enabled FROM [dbo].[AuthUserTotp] WHERE AuthUserNID = @P1 AND Enabled = 1", &[&user_nid])
+            .await?.into_row().await?;
+
+        Ok(row.is_some())
+    }
+
+    /// Mulai enrolment: secret baru disimpan dengan `Enabled = 0` sampai kode pertama dikonfirmasi
+    pub async fn enroll(connection: &Pool<ConnectionManager>, claims: &Claims) -> Result<ActionResult<TotpEnrollment, String>, AppError> {
+        if Self::is_enabled(connection, claims.auth_usernid).await? {
+            return Err(AppError::Conflict("Two-factor authentication is already enabled".to_string()));
+        }
+
+        let mut secret = [0u8; SECRET_LENGTH];
+        rand::rng().fill(&mut secret[..]);
+        let encrypted = Self::encrypt_secret(&secret)?;
+
+        let mut conn = connection.get().await?;
+        conn.execute(
+            r#"DELETE FROM [dbo].[AuthUserTotp] WHERE AuthUserNID = @P1 AND Enabled = 0;
+            INSERT INTO [dbo].[AuthUserTotp] ([AuthUserNID],[SecretEncrypted]) VALUES (@P1,@P2)"#,
+            &[&claims.auth_usernid, &encrypted],
+        ).await?;
+
+        let secret = Self::base32_encode(&secret);
+        Ok(ActionResult {
+            result: true,
+            message: "Scan the QR code and confirm with the first code".to_string(),
+            data: Some(TotpEnrollment { provisioning_uri: Self::provisioning_uri(&claims.email, &secret), secret }),
+            ..Default::default()
+        })
+    }
+
+    /// Aktifkan 2FA setelah kode pertama dari aplikasi authenticator valid, backup code lama diganti
+    pub async fn confirm(connection: &Pool<ConnectionManager>, user_nid: i32, code: &str, actor: &AuditActor) -> Result<ActionResult<TotpBackupCodes, String>, AppError> {
+        let code = code.trim();
+        let backup_codes = Self::new_backup_codes();
+
+        let trans = Transaction::begin(connection).await?;
+        {
+            let mut conn_guard = trans.conn.lock().await;
+            let conn = conn_guard.as_mut()
+                .ok_or_else(|| AppError::Internal("Failed to get connection from pool".to_string()))?;
+
+            let stored = conn.query(
+                "SELECT SecretEncrypted FROM [dbo].[AuthUserTotp] WITH (UPDLOCK) WHERE AuthUserNID = @P1 AND Enabled = 0",
+                &[&user_nid],
+            ).await?.into_row().await?
+                .and_then(|row| row.get::<&str, _>("SecretEncrypted").map(str::to_string))
+                .ok_or_else(|| AppError::BadRequest("No pending two-factor enrolment, start enrolment first".to_string()))?;
+
+            let secret = Self::decrypt_secret(&stored)?;
+            let step = Some(code)
+                .filter(|code| Self::is_totp_code(code))
+                .and_then(|code| Self::matching_step(&secret, code, None))
+                .ok_or_else(|| AppError::BadRequest("Invalid verification code".to_string()))?;
+
+            conn.execute(
+                "UPDATE [dbo].[AuthUserTotp] SET Enabled = 1, EnabledAt = GETDATE(), LastUsedStep = @P2 WHERE AuthUserNID = @P1",
+                &[&user_nid, &step],
+            ).await?;
+            Self::replace_backup_codes(conn, user_nid, &backup_codes).await?;
+
+            AuditService::write(conn, actor, AuditEntry { action: "2fa_enable", entity: "AuthUser", entity_key: Some(user_nid.to_string()), before: None, after: None }).await?;
+        }
+        trans.commit().await?;
+
+        Ok(ActionResult {
+            result: true,
+            message: "Two-factor authentication enabled".to_string(),
+            data: Some(TotpBackupCodes { backup_codes }),
+            ..Default::default()
+        })
+    }
+
+    async fn replace_backup_codes(conn: &mut Client, user_nid: i32, backup_codes: &[String]) -> Result<(), AppError> {
+        conn.execute("DELETE FROM [dbo].[AuthUserBackupCode] WHERE AuthUserNID = @P1", &[&user_nid]).await?;
+
+        for code in backup_codes {
+            conn.execute(
+                "INSERT INTO [dbo].[AuthUserBackupCode] ([AuthUserNID],[CodeHash]) VALUES (@P1,@P2)",
+                &[&user_nid, &Self::hash_backup_code(code)],
+            ).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Pakai kode TOTP (step-nya dicatat supaya tidak bisa dipakai ulang) atau backup code sekali pakai
+    async fn consume_code(conn: &mut Client, user_nid: i32, stored: &str, last_used_step: Option<i64>, code: &str) -> Result<bool, AppError> {
+        if Self::is_totp_code(code) {
+            let secret = Self::decrypt_secret(stored)?;
+            let Some(step) = Self::matching_step(&secret, code, last_used_step) else {
+                return Ok(false);
+            };
+
+            let affected = conn.execute(
+                r#"UPDATE [dbo].[AuthUserTotp] SET LastUsedStep = @P2
+                WHERE AuthUserNID = @P1 AND (LastUsedStep IS NULL OR LastUsedStep < @P2)"#,
+                &[&user_nid, &step],
+            ).await?.total();
+
+            return Ok(affected > 0);
+        }
+
+        let affected = conn.execute(
+            "UPDATE [dbo].[AuthUserBackupCode] SET UsedAt = GETDATE() WHERE AuthUserNID = @P1 AND CodeHash = @P2 AND UsedAt IS NULL",
+            &[&user_nid, &Self::hash_backup_code(code)],
+        ).await?.total();
+
+        Ok(affected > 0)
+    }
+
+    /// Password benar untuk akun dengan 2FA aktif: simpan nonce baru untuk token login sementara.
+    /// `None` jika 2FA tidak aktif. Login berikutnya mengganti nonce, jadi token lama ikut tidak berlaku.
+    pub async fn begin_login(conn: &mut Client, user_nid: i32) -> Result<Option<String>, AppError> {
+        let mut bytes = [0u8; 16];
+        rand::rng().fill(&mut bytes[..]);
+        let nonce: String = bytes.iter().map(|b| format!("{:02x}", b)).collect();
+
+        let affected = conn.execute(
+            "UPDATE [dbo].[AuthUserTotp] SET PendingNonce = @P2 WHERE AuthUserNID = @P1 AND Enabled = 1",
+            &[&user_nid, &nonce],
+        ).await?.total();
+
+        Ok((affected > 0).then_some(nonce))
+    }
+
+    /// Langkah kedua login. Kode salah dihitung sebagai login gagal (ikut penguncian akun).
+    /// Hitungan gagal baru di-reset dan nonce dihapus setelah kode diterima.
+    pub async fn verify_login(connection: &Pool<ConnectionManager>, user_nid: i32, nonce: &str, code: &str, actor: &AuditActor) -> Result<Claims, AppError> {
+        let code = code.trim();
+        let expired = || AppError::Unauthorized("Two-factor login has expired, please login again".to_string());
+
+        let trans = Transaction::begin(connection).await?;
+        let (claims, email) = {
+            let mut conn_guard = trans.conn.lock().await;
+            let conn = conn_guard.as_mut()
+                .ok_or_else(|| AppError::Internal("Failed to get connection from pool".to_string()))?;
+
+            let row = conn.query(
+                r#"SELECT u.Email, u.LockedUntil, GETDATE() AS Now, t.SecretEncrypted, t.LastUsedStep, t.PendingNonce
+                FROM [dbo].[AuthUser] u
+                JOIN [dbo].[AuthUserTotp] t WITH (UPDLOCK) ON t.AuthUserNID = u.AuthUserNID
+                WHERE u.AuthUserNID = @P1 AND t.Enabled = 1"#,
+                &[&user_nid],
+            ).await?.into_row().await?
+                .ok_or_else(|| AppError::Unauthorized("Two-factor authentication is not enabled".to_string()))?;
+
+            let pending = row.get::<&str, _>("PendingNonce").unwrap_or_default();
+            if pending.is_empty() || !CredentialService::constant_time_eq(pending.as_bytes(), nonce.as_bytes()) {
+                return Err(expired());
+            }
+
+            let email = row.get::<&str, _>("Email").unwrap_or_default().to_string();
+            CredentialService::ensure_not_locked(row.get::<NaiveDateTime, _>("LockedUntil"), row.get("Now"))?;
+
+            let stored = row.get::<&str, _>("SecretEncrypted").unwrap_or_default().to_string();
+            if Self::consume_code(conn, user_nid, &stored, row.get("LastUsedStep"), code).await? {
+                conn.execute("UPDATE [dbo].[AuthUserTotp] SET PendingNonce = NULL WHERE AuthUserNID = @P1", &[&user_nid]).await?;
+                CredentialService::complete_login(conn, user_nid).await?;
+
+                let claims = CredentialService::load_claims(conn, user_nid, actor.ip_address.clone()).await?;
+                let actor = AuditActor { user_nid: Some(user_nid), email: Some(email.clone()), ip_address: actor.ip_address.clone() };
+                AuditService::write(conn, &actor, AuditEntry { action: "login_2fa", entity: "AuthUser", entity_key: Some(user_nid.to_string()), before: None, after: None }).await?;
+
+                (Some(claims), email)
+            } else {
+                (None, email)
+            }
+        };
+
+        match claims {
+            Some(claims) => {
+                trans.commit().await?;
+                Ok(claims)
+            }
+            None => {
+                drop(trans);
+                CredentialService::register_failed_attempt(connection, actor, user_nid, &email, "2fa_failed").await?;
+                Err(AppError::Unauthorized("Invalid two-factor code".to_string()))
+            }
+        }
+    }
+
+    /// Nonaktifkan 2FA, butuh kode TOTP atau backup code yang valid. Kode salah dihitung seperti di `verify_login`.
+    pub async fn disable(connection: &Pool<ConnectionManager>, user_nid: i32, code: &str, actor: &AuditActor) -> Result<(), AppError> {
+        let code = code.trim();
+        let mut conn = connection.get().await?;
+
+        let row = conn.query(
+            r#"SELECT u.Email, u.LockedUntil, GETDATE() AS Now, t.SecretEncrypted, t.LastUsedStep
+            FROM [dbo].[AuthUser] u
+            JOIN [dbo].[AuthUserTotp] t ON t.AuthUserNID = u.AuthUserNID
+            WHERE u.AuthUserNID = @P1 AND t.Enabled = 1"#,
+            &[&user_nid],
+        ).await?.into_row().await?
+            .ok_or_else(|| AppError::NotFound("Two-factor authentication is not enabled".to_string()))?;
+
+        CredentialService::ensure_not_locked(row.get::<NaiveDateTime, _>("LockedUntil"), row.get("Now"))?;
+
+        let stored = row.get::<&str, _>("SecretEncrypted").unwrap_or_default().to_string();
+        if !Self::consume_code(&mut conn, user_nid, &stored, row.get("LastUsedStep"), code).await? {
+            drop(conn);
+            let email = row.get::<&str, _>("Email").unwrap_or_default();
+            CredentialService::register_failed_attempt(connection, actor, user_nid, email, "2fa_disable_failed").await?;
+            return Err(AppError::BadRequest("Invalid verification code".to_string()));
+        }
+
+        conn.execute(
+            r#"DELETE FROM [dbo].[AuthUserBackupCode] WHERE AuthUserNID = @P1;
+            DELETE FROM [dbo].[AuthUserTotp] WHERE AuthUserNID = @P1"#,
+            &[&user_nid],
+        ).await?;
+
+        AuditService::write(&mut conn, actor, AuditEntry { action: "2fa_disable", entity: "AuthUser", entity_key: Some(user_nid.to_string()), before: None, after: None }).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Secret ASCII "12345678901234567890" dari RFC 4226 dan RFC 6238 (SHA-1)
+    const RFC_SECRET: &[u8] = b"12345678901234567890";
+
+    /// Kunci 32 byte yang sama di semua test, jadi aman walaupun test berjalan paralel
+    fn set_encryption_key() {
+        env::set_var("TOTP_ENCRYPTION_KEY", general_purpose::STANDARD.encode([42u8; 32]));
+    }
+
+    #[test]
+    fn hotp_matches_rfc4226_appendix_d() {
+        let expected = ["755224", "287082", "359152", "969429", "338314", "254676", "287922", "162583", "399871", "520489"];
+
+        for (counter, code) in expected.iter().enumerate() {
+            assert_eq!(TotpService::hotp(RFC_SECRET, counter as u64), *code, "counter={}", counter);
+        }
+    }
+
+    /// Nilai 8 digit RFC 6238 Appendix B (SHA-1), dipotong ke 6 digit terakhir
+    #[test]
+    fn totp_matches_rfc6238_sha1() {
+        let expected: [(i64, &str); 6] = [
+            (59, "287082"),
+            (1111111109, "081804"),
+            (1111111111, "050471"),
+            (1234567890, "005924"),
+            (2000000000, "279037"),
+            (20000000000, "353130"),
+        ];
+
+        for (time, code) in expected {
+            assert_eq!(TotpService::hotp(RFC_SECRET, (time / TOTP_PERIOD) as u64), code, "time={}", time);
+        }
+    }
+
+    #[test]
+    fn matching_step_rejects_reused_step() {
+        let step = Utc::now().timestamp() / TOTP_PERIOD;
+        let code = TotpService::hotp(RFC_SECRET, step as u64);
+
+        assert_eq!(TotpService::matching_step(RFC_SECRET, &code, None), Some(step));
+        assert_eq!(TotpService::matching_step(RFC_SECRET, &code, Some(step)), None);
+        assert_eq!(TotpService::matching_step(RFC_SECRET, &TotpService::hotp(RFC_SECRET, (step - 10) as u64), None), None);
+    }
+
+    #[test]
+    fn base32_encode_matches_rfc4648() {
+        let expected = [("", ""), ("f", "MY"), ("fo", "MZXQ"), ("foo", "MZXW6"), ("foob", "MZXW6YQ"), ("fooba", "MZXW6YTB"), ("foobar", "MZXW6YTBOI")];
+
+        for (input, output) in expected {
+            assert_eq!(TotpService::base32_encode(input.as_bytes()), output);
+        }
+        assert_eq!(TotpService::base32_encode(RFC_SECRET), "GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ");
+    }
+
+    #[test]
+    fn encrypted_secret_round_trips() {
+        set_encryption_key();
+
+        let stored = TotpService::encrypt_secret(RFC_SECRET).unwrap();
+        assert!(stored.starts_with("v1$"));
+        assert_eq!(TotpService::decrypt_secret(&stored).unwrap(), RFC_SECRET);
+
+        // Nonce acak, jadi secret yang sama tidak pernah menghasilkan teks yang sama
+        assert_ne!(TotpService::encrypt_secret(RFC_SECRET).unwrap(), stored);
+    }
+
+    #[test]
+    fn tampered_secret_is_rejected() {
+        set_encryption_key();
+
+        let stored = TotpService::encrypt_secret(RFC_SECRET).unwrap();
+        let parts: Vec<&str> = stored.split('$').collect();
+
+        let mut tag = general_purpose::STANDARD_NO_PAD.decode(parts[3]).unwrap();
+        tag[0] ^= 1;
+        let tampered_tag = format!("{}${}${}${}", parts[0], parts[1], parts[2], general_purpose::STANDARD_NO_PAD.encode(tag));
+        assert!(TotpService::decrypt_secret(&tampered_tag).is_err());
+
+        let mut ciphertext = general_purpose::STANDARD_NO_PAD.decode(parts[2]).unwrap();
+        ciphertext[0] ^= 1;
+        let tampered_ciphertext = format!("{}${}${}${}", parts[0], parts[1], general_purpose::STANDARD_NO_PAD.encode(ciphertext), parts[3]);
+        assert!(TotpService::decrypt_secret(&tampered_ciphertext).is_err());
+
+        assert!(TotpService::decrypt_secret(&stored.replacen("v1$", "v2$", 1)).is_err());
+        assert!(TotpService::decrypt_secret("v1$abc").is_err());
+    }
+}
@@ -46,10 +46,13 @@ pub mod validator {
     }
 
     pub fn valid_password(value: &str) -> Result<(), ValidationError> {
-        let password_regex = Regex::new(r"^(?=.*[A-Za-z])(?=.*\d)[A-Za-z\d]{8,}$")
+        // Crate regex tidak mendukung lookahead, jadi syarat huruf & angka dicek terpisah
+        let password_regex = Regex::new(r"^[A-Za-z\d]{8,}$")
             .map_err(|_| ValidationError::new("invalid_regex"))?;
+        let has_letter = value.chars().any(|c| c.is_ascii_alphabetic());
+        let has_digit = value.chars().any(|c| c.is_ascii_digit());
 
-        if !password_regex.is_match(value) {
+        if !password_regex.is_match(value) || !has_letter || !has_digit {
             let mut error = ValidationError::new("invalid_password");
             error.message = Some("Required character number and text".into());
             return Err(error);
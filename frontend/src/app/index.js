@@ -14,10 +14,21 @@ export const closeModal = (/** @type {any} */ id) => {
 
 export async function fetchColumns(tablename) {
   try {
-    const response = await fetch(`${base_url}/data/header?tablename=${tablename}`);
+    const response = await fetch(`${base_url}/data/header?tablename=${tablename}`, { credentials: 'include' });
     const data = await response.json();
     return data;
   } catch (error) {
     console.error("Error fetching columns:", error);
   }
-}
\ No newline at end of file
+}
+
+export async function fetchDistinct(tablename, column, filter = {}) {
+  try {
+    const params = new URLSearchParams({ tablename, column, filter: JSON.stringify(filter) });
+    const response = await fetch(`${base_url}/data/distinct?${params}`, { credentials: 'include' });
+    const data = await response.json();
+    return data;
+  } catch (error) {
+    console.error("Error fetching distinct values:", error);
+  }
+}